@@ -0,0 +1,290 @@
+use alloc::format;
+use alloc::string::{ String, ToString };
+
+
+/// tolerance used when comparing amounts
+///
+/// Amounts are compared with a relative tolerance for values larger than 1 in absolute value, and
+/// with an absolute tolerance for smaller ones (see `[amounts_equal]`).
+pub const AMOUNT_EPSILON: f64 = 1e-9;
+
+
+/// largest amount below which all integers can be represented exactly by an `f64`, i.e., 2^53
+///
+/// Above it, integer amounts (and a fortiori decimal ones) are rounded.
+pub const MAX_SAFE_AMOUNT: f64 = 9_007_199_254_740_992.;
+
+
+/// check if an amount goes from within `[MAX_SAFE_AMOUNT]` (in absolute value) to beyond it
+///
+/// # Example
+///
+/// ```
+/// use banking_exercise::amount::{ crosses_safe_limit, MAX_SAFE_AMOUNT };
+///
+/// assert!(crosses_safe_limit(MAX_SAFE_AMOUNT - 10., MAX_SAFE_AMOUNT + 90.));
+/// assert!(!crosses_safe_limit(MAX_SAFE_AMOUNT + 90., MAX_SAFE_AMOUNT + 190.));
+/// ```
+pub fn crosses_safe_limit(before: f64, after: f64) -> bool {
+    before.abs() <= MAX_SAFE_AMOUNT && after.abs() > MAX_SAFE_AMOUNT
+}
+
+
+/// check if two amounts are equal up to `[AMOUNT_EPSILON]`
+///
+/// # Example
+///
+/// ```
+/// use banking_exercise::amount::amounts_equal;
+///
+/// // 0.1 + 0.2 is not exactly 0.3 with floating-point numbers...
+/// assert_ne!(0.3, 0.1 + 0.2);
+///
+/// // ...but it is up to the tolerance
+/// assert!(amounts_equal(0.3, 0.1 + 0.2));
+/// ```
+pub fn amounts_equal(a: f64, b: f64) -> bool {
+    amounts_equal_within(a, b, AMOUNT_EPSILON)
+}
+
+
+/// check if two amounts are equal up to a given tolerance
+///
+/// The tolerance is relative if either amount is larger than 1 in absolute value and absolute
+/// otherwise.
+pub fn amounts_equal_within(a: f64, b: f64, epsilon: f64) -> bool {
+    (a - b).abs() <= epsilon * a.abs().max(b.abs()).max(1.)
+}
+
+
+/// round an amount to a given number of decimal places, half away from zero
+///
+/// # Example
+///
+/// ```
+/// use banking_exercise::amount::round_to;
+///
+/// assert_eq!(1.2346, round_to(1.23456, 4));
+/// assert_eq!(0., round_to(-0.00004, 4));
+/// ```
+pub fn round_to(amount: f64, decimal_places: u32) -> f64 {
+
+    // `powi` and `round` need `std`, so the scale is computed by multiplication (which is exact up
+    // to 10^22) and the scaled amount is rounded by hand
+    let scale = (0..decimal_places).fold(1., |scale, _| scale * 10.);
+    let scaled = amount * scale;
+
+    // scaled amounts beyond 2^52 (and infinite or NaN ones) have no fractional part
+    if !scaled.is_finite() || scaled.abs() >= 4_503_599_627_370_496. { return amount + 0.; }
+    let truncated = scaled as i64 as f64;
+    let rounded = if (scaled - truncated).abs() >= 0.5 { 
+        truncated + 1f64.copysign(scaled) 
+    } else { 
+        truncated 
+    };
+
+    // adding zero turns a negative zero into a positive one
+    rounded / scale + 0.
+}
+
+
+/// the exact decimal value of an amount, with all the digits of its binary representation
+///
+/// Unlike the `Display` implementation of `f64`, which prints the shortest representation
+/// identifying the stored value, this shows the stored value itself, without rounding. Trailing
+/// zeros are removed.
+///
+/// # Example
+///
+/// ```
+/// use banking_exercise::amount::exact_value;
+///
+/// assert_eq!("100.5", exact_value(100.5));
+/// assert_eq!("0.1000000000000000055511151231257827021181583404541015625", exact_value(0.1));
+/// ```
+pub fn exact_value(amount: f64) -> String {
+
+    // the binary fraction of an `f64` has at most 1074 digits, each giving one decimal digit
+    let text = format!("{:.1074}", amount + 0.);
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+
+/// number of decimal places of amounts in the standard format
+///
+/// This is the default of `[crate::config::ProcessingConfig::max_decimal_places]`; amounts with
+/// more decimal places are handled according to
+/// `[crate::config::ProcessingConfig::excess_decimals]`.
+pub const STANDARD_DECIMAL_PLACES: usize = 4;
+
+
+// currencies whose minor unit is not the hundredth, with their number of decimal places, as in
+// ISO 4217
+const NON_DECIMAL_MINOR_UNITS: [(&str, usize); 26] = [
+    ("BIF", 0), ("CLP", 0), ("DJF", 0), ("GNF", 0), ("ISK", 0), ("JPY", 0), ("KMF", 0),
+    ("KRW", 0), ("PYG", 0), ("RWF", 0), ("UGX", 0), ("UYI", 0), ("VND", 0), ("VUV", 0),
+    ("XAF", 0), ("XOF", 0), ("XPF", 0), ("BHD", 3), ("IQD", 3), ("JOD", 3), ("KWD", 3),
+    ("LYD", 3), ("OMR", 3), ("TND", 3), ("CLF", 4), ("UYW", 4),
+];
+
+
+/// number of decimal places of a currency, given by its ISO 4217 code (ignoring case), or `None`
+/// if the code is not made of three letters
+///
+/// Currencies which are not listed with another number of decimal places, as most of them, are
+/// assumed to have two.
+///
+/// # Example
+///
+/// ```
+/// use banking_exercise::amount::currency_decimal_places;
+///
+/// assert_eq!(Some(0), currency_decimal_places("JPY"));
+/// assert_eq!(Some(2), currency_decimal_places("usd"));
+/// assert_eq!(Some(3), currency_decimal_places("BHD"));
+/// assert_eq!(None, currency_decimal_places("dollar"));
+/// ```
+pub fn currency_decimal_places(code: &str) -> Option<usize> {
+    if code.len() != 3 || !code.chars().all(|c| c.is_ascii_alphabetic()) { return None; }
+    let places = NON_DECIMAL_MINOR_UNITS.iter()
+        .find(|(currency, _)| currency.eq_ignore_ascii_case(code))
+        .map_or(2, |(_, places)| *places);
+    Some(places)
+}
+
+
+/// number of decimal places of the decimal representation of an amount, ignoring trailing zeros,
+/// or `None` if it is not a decimal number without exponent
+///
+/// # Example
+///
+/// ```
+/// use banking_exercise::amount::decimal_places;
+///
+/// assert_eq!(Some(6), decimal_places("100.123456"));
+/// assert_eq!(Some(1), decimal_places("100.10"));
+/// assert_eq!(Some(0), decimal_places("100"));
+/// assert_eq!(None, decimal_places("1e-5"));
+/// ```
+pub fn decimal_places(text: &str) -> Option<usize> {
+    normalize_decimal(text).map(|text| text.len() - text.find('.').unwrap() - 1)
+}
+
+
+/// truncate the decimal representation of an amount to a given number of decimal places
+///
+/// Representations which are not decimal numbers without exponent are returned unchanged.
+///
+/// # Example
+///
+/// ```
+/// use banking_exercise::amount::truncate_decimal;
+///
+/// assert_eq!("100.1234", truncate_decimal("100.123456", 4));
+/// assert_eq!("100.12", truncate_decimal("100.12", 4));
+/// ```
+pub fn truncate_decimal(text: &str, decimal_places: usize) -> &str {
+    if normalize_decimal(text).is_none() { return text; }
+    match text.find('.') {
+        Some(position) if text.len() > position + 1 + decimal_places => 
+            &text[..position + 1 + decimal_places],
+        _ => text,
+    }
+}
+
+
+/// check if the decimal representation of an amount has more significant digits than its parsed
+/// value
+///
+/// Representations using an exponent, or which are not decimal numbers, are not checked.
+///
+/// # Example
+///
+/// ```
+/// use banking_exercise::amount::loses_precision;
+///
+/// assert!(!loses_precision("100.0001", 100.0001));
+/// assert!(loses_precision("100.00000000000000001", 100.00000000000000001));
+/// ```
+pub fn loses_precision(text: &str, value: f64) -> bool {
+    match (normalize_decimal(text), normalize_decimal(&value.to_string())) {
+        (Some(text), Some(value)) => text != value,
+        _ => false
+    }
+}
+
+
+// normalize the representation of a decimal number by removing the sign and leading and trailing
+// zeros, or return `None` if it is not a decimal number without exponent
+fn normalize_decimal(text: &str) -> Option<String> {
+    let text = text.trim().trim_start_matches(['+', '-']);
+    let (integer_part, fractional_part) = text.split_once('.').unwrap_or((text, ""));
+    if !(integer_part.chars().chain(fractional_part.chars()).all(|c| c.is_ascii_digit())) {
+        return None;
+    }
+    let integer_part = integer_part.trim_start_matches('0');
+    let fractional_part = fractional_part.trim_end_matches('0');
+    Some(format!("{}.{}", integer_part, fractional_part))
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn amounts_equal_1() {
+
+        // small amounts: absolute tolerance
+        assert!(amounts_equal(0., 0.5e-9));
+        assert!(amounts_equal(0., -1e-9));
+        assert!(!amounts_equal(0., 2e-9));
+        assert!(!amounts_equal(0.5, 0.5 + 2e-9));
+
+        // large amounts: relative tolerance
+        assert!(amounts_equal(1e6, 1e6 + 0.5e-3));
+        assert!(!amounts_equal(1e6, 1e6 + 2e-3));
+    }
+
+    #[test]
+    fn amounts_equal_within_1() {
+
+        // a tighter tolerance distinguishes amounts which are equal for the default one
+        assert!(amounts_equal(1., 1. + 1e-10));
+        assert!(!amounts_equal_within(1., 1. + 1e-10, 1e-12));
+
+        // a looser one does the opposite
+        assert!(!amounts_equal(1., 1.001));
+        assert!(amounts_equal_within(1., 1.001, 1e-2));
+    }
+
+    #[test]
+    fn loses_precision_1() {
+
+        // representable amounts, possibly with leading or trailing zeros
+        for text in ["100", "100.", "100.10", "007.5", "0.1", "-2.5", "+3", "123456789.1234"] {
+            assert!(!loses_precision(text, text.parse().unwrap()), "{}", text);
+        }
+
+        // amounts with too many significant digits
+        for text in ["100.00000000000000001", "12345678901234567891", "0.12345678901234567891"] {
+            assert!(loses_precision(text, text.parse().unwrap()), "{}", text);
+        }
+
+        // exponents are not checked
+        assert!(!loses_precision("1.00000000000000000001e2", 100.));
+    }
+    
+    #[test]
+    // decimal places are counted on the representation, ignoring trailing zeros, and truncation
+    // keeps the representation exact
+    fn decimal_places_1() {
+        assert_eq!(Some(4), decimal_places("-0.123400"));
+        assert_eq!(Some(0), decimal_places("100."));
+        assert_eq!(None, decimal_places("abc"));
+        assert_eq!("-0.9999", truncate_decimal("-0.99999", 4));
+        assert_eq!("100.", truncate_decimal("100.", 4));
+        assert_eq!("1e-10", truncate_decimal("1e-10", 4));
+    }
+}
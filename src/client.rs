@@ -1,6 +1,11 @@
-use std::collections::{ HashMap, HashSet };
+use std::cmp::{ Ordering, Reverse };
+use std::collections::{ BinaryHeap, HashMap, HashSet, VecDeque };
 use crate::transaction::*;
-use crate::style::warning_style;
+use crate::amount::{ AMOUNT_EPSILON, MAX_SAFE_AMOUNT, amounts_equal, round_to, exact_value, 
+                     crosses_safe_limit };
+use crate::config::{ OutputConfig, ProcessingConfig, DisputeFundMode, WithdrawalDisputePolicy, 
+                     AmountFormat, SafeIntegerPolicy, SortOrder, OverWithdrawalPolicy, Column };
+use crate::read_csv::ProcessingError;
 use itertools::Itertools; // to sort the client hashmap
 
 /// information about a client
@@ -8,29 +13,309 @@ use itertools::Itertools; // to sort the client hashmap
 /// We use 64-bit floating-point numbers for the amounts.Using 32-bit numbers would be enough to
 /// give a precision up to four places past the decimal for values up to about 10,000,000. We
 /// choose a higher precision to be able to deal with larger numbers if necessary.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Client {
     available: f64, 
     held: f64, 
     locked: bool, 
+    closed: bool, 
+    overdraft_limit: f64, 
     history: HashMap<TransactionId, Transaction>,
+    history_order: HistoryOrder,
+    // IDs of the transactions evicted from the history or reversed, never forgotten so that
+    // replaying them is always ignored; this set grows with the number of evicted transactions
+    retired_ids: HashSet<TransactionId>,
     disputed_transactions: HashSet<TransactionId>,
+    charged_back: HashSet<TransactionId>,
+    pending: VecDeque<(TransactionId, Transaction)>,
+    n_transactions: usize,
+    max_transaction_id: Option<TransactionId>,
+    last_notes: Option<String>,
+    withdrawn: f64,
+    last_activity: Option<u64>,
 }
 
 
-/// type used for the client ID
-#[derive(Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
-pub struct ClientId(pub u16);
+/// type used for the client ID, defined with the transactions so that it is available without
+/// `std`
+pub use crate::transaction::ClientId;
 
 
-/// a hashmap type relating client IDs to clients
-#[derive(Debug)]
-pub struct ClientMap(HashMap<ClientId, Client>);
+/// a map relating client IDs to clients, kept in a `[ClientStore]` (by default, an in-memory
+/// `HashMap`)
+///
+/// `ClientMap::default()` gives an empty in-memory map; a map using another store is created with
+/// `[ClientMap::with_store]`.
+#[derive(Debug, Clone)]
+pub struct ClientMap<S: ?Sized = HashMap<ClientId, Client>> {
+    // sequence number of the next executed transaction, for `[SortOrder::LastActivity]` and the
+    // expiry of disputes
+    sequence: u64,
+
+    // fingerprints of the files processed into the map (see `[ClientMap::mark_processed]`)
+    processed_files: HashSet<u64>,
+
+    // the open disputes, for `[ProcessingConfig::dispute_ttl]`
+    dispute_ages: DisputeAges,
+
+    // the clients, last so that a map can be viewed as a `ClientMap<dyn ClientStore>`
+    clients: S,
+}
+
+
+// the undisputed deposits and withdrawals of the history of a client, from the least to the most
+// recently used, tracked only if `[ProcessingConfig::history_capacity]` is set
+//
+// Each use of a transaction queues it with a new stamp; an entry of the queue whose stamp is not
+// the latest one of its transaction (or whose transaction is no longer tracked) is stale, and is
+// skipped when popped. The queue is compacted when stale entries make up most of it, so that all
+// operations take amortised constant time.
+#[derive(Debug, Clone, Default)]
+struct HistoryOrder {
+    queue: VecDeque<(u64, TransactionId)>,
+    stamps: HashMap<TransactionId, u64>,
+    next_stamp: u64,
+}
+
+impl HistoryOrder {
+
+    // number of tracked transactions
+    fn len(&self) -> usize {
+        self.stamps.len()
+    }
+
+    // mark a transaction as the most recently used one, tracking it if it was not
+    fn touch(&mut self, transaction_id: TransactionId) {
+        self.stamps.insert(transaction_id, self.next_stamp);
+        self.queue.push_back((self.next_stamp, transaction_id));
+        self.next_stamp += 1;
+        if self.queue.len() > 2 * self.stamps.len() + 16 {
+            let stamps = &self.stamps;
+            self.queue.retain(|(stamp, id)| stamps.get(id) == Some(stamp));
+        }
+    }
+
+    // stop tracking a transaction
+    fn remove(&mut self, transaction_id: &TransactionId) {
+        self.stamps.remove(transaction_id);
+    }
+
+    // stop tracking the least recently used transaction and return its ID
+    fn pop_least_recent(&mut self) -> Option<TransactionId> {
+        while let Some((stamp, transaction_id)) = self.queue.pop_front() {
+            if self.stamps.get(&transaction_id) == Some(&stamp) {
+                self.stamps.remove(&transaction_id);
+                return Some(transaction_id);
+            }
+        }
+        None
+    }
+
+    // the tracked transactions, from the least to the most recently used
+    fn iter(&self) -> impl Iterator<Item = TransactionId> + '_ {
+        self.queue.iter()
+            .filter(|(stamp, id)| self.stamps.get(id) == Some(stamp))
+            .map(|(_, id)| *id)
+    }
+}
+
+
+// the open disputes, with the sequence number of the transaction which opened them, used to
+// expire them after `[ProcessingConfig::dispute_ttl]` processed transactions
+//
+// The queue is ordered by sequence number; a dispute which has been resolved (or resolved and
+// disputed again) since its entry was queued is recognised by comparing it with `opened`.
+#[derive(Debug, Clone, Default)]
+struct DisputeAges {
+    queue: VecDeque<(u64, ClientId, TransactionId)>,
+    opened: HashMap<(ClientId, TransactionId), u64>,
+}
+
+impl DisputeAges {
+
+    // forget the disputes of a client
+    fn remove_client(&mut self, id: ClientId) {
+        self.queue.retain(|(_, client_id, _)| *client_id != id);
+        self.opened.retain(|(client_id, _), _| *client_id != id);
+    }
+
+    // move the disputes of a client to another one; if both have opened a dispute of the same
+    // transaction, the one of `into` is kept
+    fn rename_client(&mut self, from: ClientId, into: ClientId) {
+        for (_, client_id, _) in self.queue.iter_mut() {
+            if *client_id == from { *client_id = into; }
+        }
+        let moved: Vec<(TransactionId, u64)> = self.opened.iter()
+            .filter(|((client_id, _), _)| *client_id == from)
+            .map(|((_, transaction_id), &opened_at)| (*transaction_id, opened_at))
+            .collect();
+        for (transaction_id, opened_at) in moved {
+            self.opened.remove(&(from, transaction_id));
+            self.opened.entry((into, transaction_id)).or_insert(opened_at);
+        }
+    }
+
+    // add the disputes of another map, whose sequence number was `other_sequence`, keeping their
+    // ages relative to the sequence number `sequence` of the merged map, which must be at least
+    // as large
+    fn merge(&mut self, other: DisputeAges, other_sequence: u64, sequence: u64) {
+        let shift = sequence - other_sequence;
+        self.queue.extend(other.queue.into_iter()
+                          .map(|(opened_at, client_id, id)| (opened_at + shift, client_id, id)));
+        self.queue.make_contiguous().sort_by_key(|(opened_at, _, _)| *opened_at);
+        for (key, opened_at) in other.opened {
+            self.opened.entry(key).or_insert(opened_at + shift);
+        }
+    }
+
+    // shift the sequence numbers of the disputes by `shift`, keeping their ages when the sequence
+    // number of the map increases by as much
+    fn shift(&mut self, shift: u64) {
+        for (opened_at, _, _) in self.queue.iter_mut() { *opened_at += shift; }
+        for opened_at in self.opened.values_mut() { *opened_at += shift; }
+    }
+}
+
+
+/// a storage backend for the clients of a `[ClientMap]`
+///
+/// `HashMap<ClientId, Client>` implements it, keeping the clients in memory. Other
+/// implementations can keep them elsewhere, such as on disk or in a database; only `get`,
+/// `get_mut`, `insert`, `remove`, and `iter` need to be implemented.
+///
+/// The functions of `[crate::read_csv]` work with any store; those processing whole files also
+/// require it to implement `Clone`, to copy the map in
+/// `[crate::config::ProcessingConfig::all_or_nothing]` mode. The commit hook of
+/// `[crate::config::ProcessingConfig]` receives the map as a `ClientMap<dyn ClientStore>`.
+pub trait ClientStore {
+
+    /// get a reference to a client, or `None` if no client has this ID
+    fn get(&self, id: &ClientId) -> Option<&Client>;
+
+    /// get a mutable reference to a client, or `None` if no client has this ID
+    fn get_mut(&mut self, id: &ClientId) -> Option<&mut Client>;
+
+    /// insert a client, returning the one previously stored with the same ID, if any
+    fn insert(&mut self, id: ClientId, client: Client) -> Option<Client>;
+
+    /// remove a client, returning it if it was stored
+    fn remove(&mut self, id: &ClientId) -> Option<Client>;
+
+    /// iterate over the clients and their IDs, in any order
+    fn iter(&self) -> Box<dyn Iterator<Item = (&ClientId, &Client)> + '_>;
+
+    /// number of clients
+    fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    /// check if the store contains no client
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// check if a client is stored
+    fn contains_key(&self, id: &ClientId) -> bool {
+        self.get(id).is_some()
+    }
+}
+
+impl ClientStore for HashMap<ClientId, Client> {
+
+    fn get(&self, id: &ClientId) -> Option<&Client> {
+        HashMap::get(self, id)
+    }
+
+    fn get_mut(&mut self, id: &ClientId) -> Option<&mut Client> {
+        HashMap::get_mut(self, id)
+    }
+
+    fn insert(&mut self, id: ClientId, client: Client) -> Option<Client> {
+        HashMap::insert(self, id, client)
+    }
+
+    fn remove(&mut self, id: &ClientId) -> Option<Client> {
+        HashMap::remove(self, id)
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = (&ClientId, &Client)> + '_> {
+        Box::new(HashMap::iter(self))
+    }
+
+    fn len(&self) -> usize {
+        HashMap::len(self)
+    }
+
+    fn contains_key(&self, id: &ClientId) -> bool {
+        HashMap::contains_key(self, id)
+    }
+}
+
+// only the in-memory map implements `Default`, so that `ClientMap::default()` needs no type
+// annotation
+impl Default for ClientMap {
+    fn default() -> Self {
+        ClientMap::with_store(HashMap::new())
+    }
+}
 
 
 /// a warning triggered when overriding an existing client with a new one with the same ID
 #[derive(Debug)]
-pub struct ExistingClientWarning(Client);
+pub struct ExistingClientWarning(pub Box<Client>);
+
+
+/// a snapshot of the balances of a client, as in a line of the output
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ClientSummary {
+    pub client: ClientId,
+    pub available: f64,
+    pub held: f64,
+    pub total: f64,
+    pub locked: bool,
+}
+
+
+// a client summary ordered by total funds and, for equal totals, by decreasing ID, so that the
+// greatest one comes first in `[ClientMap::top_n_by_total]`
+struct RankedByTotal(ClientSummary);
+
+impl PartialEq for RankedByTotal {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for RankedByTotal {}
+
+impl PartialOrd for RankedByTotal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for RankedByTotal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total.total_cmp(&other.0.total).then_with(|| other.0.client.cmp(&self.0.client))
+    }
+}
+
+
+/// a difference between the clients of two maps, as returned by `[ClientMap::diff]`
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum ClientDiff {
+
+    /// the client only exists in the first map
+    OnlyInFirst(ClientSummary),
+
+    /// the client only exists in the second map
+    OnlyInSecond(ClientSummary),
+
+    /// the available or held funds (up to `[AMOUNT_EPSILON]`) or the lock of the client differ
+    Changed { first: ClientSummary, second: ClientSummary },
+}
 
 
 impl Client {
@@ -55,8 +340,127 @@ impl Client {
     /// ```
     pub fn new(available: f64, held: f64, locked: bool) -> Self {
         Client { available, held, locked, 
+                 closed: false, 
+                 overdraft_limit: 0., 
                  history: HashMap::new(), 
-                 disputed_transactions: HashSet::new() }
+                 history_order: HistoryOrder::default(), 
+                 retired_ids: HashSet::new(),
+                 disputed_transactions: HashSet::new(),
+                 charged_back: HashSet::new(),
+                 pending: VecDeque::new(),
+                 n_transactions: 0,
+                 max_transaction_id: None,
+                 last_notes: None,
+                 withdrawn: 0.,
+                 last_activity: None }
+    }
+    
+    /// notes of the last transaction, if it had any (see
+    /// `[crate::config::ProcessingConfig::notes_column]`)
+    pub fn last_notes(&self) -> Option<&str> {
+        self.last_notes.as_deref()
+    }
+
+    /// cumulative amount withdrawn since the account was created or the withdrawals were last
+    /// cleared (see `[Client::clear_withdrawals]`)
+    pub fn withdrawn(&self) -> f64 {
+        self.withdrawn
+    }
+
+    /// reset the cumulative amount withdrawn, for instance at the start of a new day
+    pub fn clear_withdrawals(&mut self) {
+        self.withdrawn = 0.;
+    }
+
+    /// sequence number of the last transaction executed on the account, in the order of execution
+    /// within its `[ClientMap]`, or `None` if there was none
+    pub fn last_activity(&self) -> Option<u64> {
+        self.last_activity
+    }
+
+    // the current balances of the client, with a given ID
+    fn summary(&self, client_id: ClientId) -> ClientSummary {
+        ClientSummary {
+            client: client_id,
+            available: self.available,
+            held: self.held,
+            total: self.available + self.held,
+            locked: self.locked,
+        }
+    }
+
+    /// available funds
+    pub fn available(&self) -> f64 {
+        self.available
+    }
+
+    /// held funds
+    pub fn held(&self) -> f64 {
+        self.held
+    }
+
+    /// maximum amount by which the available funds can become negative through withdrawals
+    pub fn overdraft_limit(&self) -> f64 {
+        self.overdraft_limit
+    }
+
+    /// available funds including the overdraft headroom, i.e., the maximum amount which can be
+    /// withdrawn
+    pub fn available_including_overdraft(&self) -> f64 {
+        self.available + self.overdraft_limit
+    }
+
+    /// number of transactions applied to the account
+    pub fn n_transactions(&self) -> usize {
+        self.n_transactions
+    }
+
+    /// check if a transaction is currently under dispute
+    ///
+    /// A transaction which has been resolved or charged back is no longer under dispute.
+    pub fn is_disputed(&self, transaction_id: &TransactionId) -> bool {
+        self.disputed_transactions.contains(transaction_id)
+    }
+
+    /// sum of the amounts of the transactions currently under dispute
+    pub fn disputed_amount(&self) -> f64 {
+
+        // adding zero turns the negative zero returned for an empty sum into a positive one
+        self.disputed_transactions.iter()
+            .filter_map(|id| match self.history.get(id) {
+                Some(Transaction::Deposit(amount) | Transaction::Withdrawal(amount)) => Some(amount),
+                _ => None
+            })
+            .sum::<f64>() + 0.
+    }
+
+    /// set the overdraft limit
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use banking_exercise::client::Client;
+    ///
+    /// // a client with 100 RustyDollars is allowed to go 50 RustyDollars into the red
+    /// let mut client = Client::new(100., 0., false);
+    /// client.set_overdraft_limit(50.);
+    /// assert_eq!(150., client.available_including_overdraft());
+    /// ```
+    pub fn set_overdraft_limit(&mut self, overdraft_limit: f64) {
+        self.overdraft_limit = overdraft_limit;
+    }
+
+    /// check that the client data is consistent
+    ///
+    /// The available and held funds must be finite, and the held funds must be non-negative up
+    /// to `[AMOUNT_EPSILON]`.
+    pub fn is_consistent(&self) -> bool {
+        self.available.is_finite() && self.held.is_finite() && self.held >= -AMOUNT_EPSILON
+    }
+
+    /// check if the account has no available or held funds and is not locked
+    pub fn is_empty(&self) -> bool {
+        self.available == 0. && self.held == 0. && !self.locked
     }
     
     // add to the available funds
@@ -70,6 +474,16 @@ impl Client {
         self.held += amount;
     }
     
+    // move from the held funds back to the available ones
+    //
+    // At most the held funds are moved, so that they never become negative (they could otherwise
+    // do so if they were adjusted after the dispute).
+    fn release_from_held(&mut self, amount: f64) {
+        let amount = amount.min(self.held.max(0.));
+        self.held -= amount;
+        self.available += amount;
+    }
+    
     fn remove_from_held(&mut self, amount: f64) {
         self.held -= amount;
     }
@@ -84,69 +498,362 @@ impl Client {
         self.history.insert(transaction_id, transaction);
     }
     
+    // mark an undisputed deposit or withdrawal of the history as the most recently used, if the
+    // history has a capacity
+    fn touch_history(&mut self, transaction_id: TransactionId, config: &ProcessingConfig) {
+        if config.history_capacity.is_some() && self.history.contains_key(&transaction_id) {
+            self.history_order.touch(transaction_id);
+        }
+    }
+    
+    // remove the least recently used undisputed deposits and withdrawals from the history, so
+    // that at most `capacity` of them remain
+    //
+    // Disputed transactions are not tracked in `history_order` (a transaction disputed in a
+    // merged client may still be, and is then dropped from it here). The IDs of the evicted
+    // transactions are kept in `retired_ids`, so that a replayed transaction with one of them is
+    // still ignored as a duplicate.
+    fn evict_history(&mut self, capacity: usize) {
+        while self.history_order.len() > capacity {
+            let Some(transaction_id) = self.history_order.pop_least_recent() else { break };
+            if !self.disputed_transactions.contains(&transaction_id) 
+                && self.history.remove(&transaction_id).is_some() {
+                self.retired_ids.insert(transaction_id);
+            }
+        }
+    }
+    
+    /// merge the data of another client into this one, for instance to unify the records of a
+    /// client appearing under two IDs
+    ///
+    /// The available and held funds and the numbers of transactions are summed, the account is
+    /// locked (resp. closed) if either account is, the overdraft limit of `self` is kept, and the
+    /// histories, sets of disputed and charged-back transactions, and queued transactions are
+    /// merged.
+    ///
+    /// If both histories contain the same transaction with the same ID, it is disputed (resp.
+    /// charged back) if it is in either client. If they contain different transactions with the
+    /// same ID, the one which is disputed or charged back is kept with its state, so that the held
+    /// funds match the disputed transactions; if neither is, the transaction of `self` is kept.
+    /// The funds of `other` are summed whatever the collisions, so that no funds are lost.
+    ///
+    /// # Errors
+    ///
+    /// This function returns a `[MergeConflictError]` if both clients have a different transaction
+    /// with the same ID which is disputed or charged back in each of them, since only one of them
+    /// could be kept; `self` is then unchanged.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use banking_exercise::client::Client;
+    ///
+    /// let mut client = Client::new(100., 0., false);
+    /// client.merge_from(Client::new(50., 10., true)).unwrap();
+    /// assert_eq!("150, 10, 160, true", format!("{}", client));
+    /// ```
+    pub fn merge_from(&mut self, other: Client) -> Result<(), MergeConflictError> {
+        let replaced = self.merge_conflicts(&other)?;
+        self.available += other.available;
+        self.held += other.held;
+        self.locked |= other.locked;
+        self.closed |= other.closed;
+        for (transaction_id, transaction) in other.history {
+            if replaced.contains(&transaction_id) {
+                self.history.insert(transaction_id, transaction);
+            } else {
+                self.history.entry(transaction_id).or_insert(transaction);
+            }
+        }
+        for transaction_id in other.history_order.iter() {
+            if !self.history_order.stamps.contains_key(&transaction_id) {
+                self.history_order.touch(transaction_id);
+            }
+        }
+        self.retired_ids.extend(other.retired_ids);
+        self.disputed_transactions.extend(other.disputed_transactions);
+        self.charged_back.extend(other.charged_back);
+        self.pending.extend(other.pending);
+        self.n_transactions += other.n_transactions;
+        self.max_transaction_id = match (self.max_transaction_id, other.max_transaction_id) {
+            (Some(id), Some(other_id)) => Some(if id.0 >= other_id.0 { id } else { other_id }),
+            (id, other_id) => id.or(other_id),
+        };
+        if self.last_notes.is_none() {
+            self.last_notes = other.last_notes;
+        }
+        self.withdrawn += other.withdrawn;
+        self.last_activity = self.last_activity.max(other.last_activity);
+        Ok(())
+    }
+
+    // the IDs of the transactions of `self` to be replaced by those of `other` when merging it
+    // (different transactions with the same ID, disputed or charged back only in `other`), or an
+    // error if both clients have a different transaction with the same ID in a dispute state
+    fn merge_conflicts(&self, other: &Client) 
+        -> Result<HashSet<TransactionId>, MergeConflictError> 
+    {
+        let mut replaced = HashSet::new();
+        for (transaction_id, transaction) in &other.history {
+            let Some(own_transaction) = self.history.get(transaction_id) else { continue };
+            if same_transaction(own_transaction, transaction) { continue; }
+            let in_dispute = (self.has_dispute_state(transaction_id), 
+                              other.has_dispute_state(transaction_id));
+            match in_dispute {
+                (true, true) => 
+                    return Err(MergeConflictError { transaction_id: *transaction_id }),
+                (false, true) => { replaced.insert(*transaction_id); },
+                _ => {},
+            }
+        }
+        Ok(replaced)
+    }
+
+    // check if a transaction is disputed or has been charged back
+    fn has_dispute_state(&self, transaction_id: &TransactionId) -> bool {
+        self.disputed_transactions.contains(transaction_id) 
+            || self.charged_back.contains(transaction_id)
+    }
+    
+    // amount actually taken by a withdrawal of `amount`, or `None` if the available funds
+    // (including the overdraft) are not sufficient
+    //
+    // A withdrawal exceeding the available funds by at most
+    // `[ProcessingConfig::withdrawal_tolerance]` takes all of them, leaving the available funds at
+    // zero (or at the opposite of the overdraft limit).
+    fn amount_to_withdraw(&self, amount: f64, config: &ProcessingConfig) -> Option<f64> {
+        let available = self.available_including_overdraft();
+        if available >= amount { return Some(amount); }
+        match config.withdrawal_tolerance {
+            Some(tolerance) if amount - available <= tolerance 
+                || amounts_equal(amount - available, tolerance) => Some(available),
+            _ => None
+        }
+    }
+
+    // reverse a disputed deposit with a given amount, removing it from the history and its funds
+    // from the held ones (or from the available ones in flag-only mode), and return its ID
+    //
+    // If several disputed deposits have the same amount, the one with the lowest ID is reversed.
+    // If there is none, this function does nothing and returns `None`.
+    fn reverse_disputed_deposit(&mut self, amount: f64, config: &ProcessingConfig) 
+        -> Option<TransactionId> 
+    {
+        let transaction_id = self.history.iter()
+            .filter(|(id, transaction)| self.disputed_transactions.contains(id) && 
+                    matches!(transaction, Transaction::Deposit(a) if amounts_equal(*a, amount)))
+            .map(|(id, _)| *id)
+            .min_by_key(|id| id.0)?;
+        self.history.remove(&transaction_id);
+        self.disputed_transactions.remove(&transaction_id);
+        match config.dispute_fund_mode {
+            DisputeFundMode::MoveToHeld => self.remove_from_held(amount),
+            DisputeFundMode::FlagOnly => self.add_to_available(-amount),
+        }
+        Some(transaction_id)
+    }
+    
+    // check if a deposit or withdrawal exists in the history
+    fn is_in_history(&self, transaction_id: &TransactionId) -> bool {
+        matches!(self.history.get(transaction_id), 
+                 Some(Transaction::Deposit(_) | Transaction::Withdrawal(_)))
+    }
+    
+    // check if a deposit or withdrawal exists and can be disputed, i.e., is neither under
+    // dispute nor charged back
+    fn is_disputable(&self, transaction_id: &TransactionId) -> bool {
+        self.is_in_history(transaction_id) 
+            && !self.disputed_transactions.contains(transaction_id)
+            && !self.charged_back.contains(transaction_id)
+    }
+    
+    // check if a deposit or withdrawal exists and is currently disputed
+    fn is_under_dispute(&self, transaction_id: &TransactionId) -> bool {
+        self.is_in_history(transaction_id) 
+            && self.disputed_transactions.contains(transaction_id)
+    }
+    
+    // amount which a dispute of a transaction would add to the held funds, or `None` if the
+    // transaction can not be disputed
+    fn amount_to_hold(&self, transaction_id: &TransactionId, config: &ProcessingConfig) 
+        -> Option<f64> 
+    {
+        if !self.is_disputable(transaction_id) { return None; }
+        if config.dispute_fund_mode == DisputeFundMode::FlagOnly { return Some(0.); }
+        match (self.history.get(transaction_id), config.withdrawal_dispute) {
+            (Some(Transaction::Deposit(amount)), _) 
+                | (Some(Transaction::Withdrawal(amount)), WithdrawalDisputePolicy::Hold) => 
+                Some(*amount),
+            (Some(Transaction::Withdrawal(_)), WithdrawalDisputePolicy::CreditAvailable) => Some(0.),
+            _ => None
+        }
+    }
+    
+    // check if a dispute, resolve, or chargeback would change anything
+    //
+    // Deposits and withdrawals always return `true`.
+    fn dispute_applies(&self, transaction: &Transaction) -> bool {
+        match transaction {
+            Transaction::Dispute(id) => self.is_disputable(id),
+            Transaction::Resolve(id) | Transaction::Chargeback(id) => self.is_under_dispute(id),
+            _ => true
+        }
+    }
+    
     // dispute a transaction
-    fn dispute(&mut self, transaction_id: TransactionId) {
+    fn dispute(&mut self, transaction_id: TransactionId, config: &ProcessingConfig) {
 
         // check if the transaction exists and is not already disputed
-        if self.history.contains_key(&transaction_id) 
-            && !self.disputed_transactions.contains(&transaction_id) {
+        if self.is_disputable(&transaction_id) {
 
-            // set the transaction as disputed
+            // set the transaction as disputed, so that it is not evicted from the history
             self.disputed_transactions.insert(transaction_id); 
+            self.history_order.remove(&transaction_id);
+
+            // in flag-only mode, the funds are not moved
+            if config.dispute_fund_mode == DisputeFundMode::FlagOnly { return; }
 
             // if the transaction is a deposit, move the funds from available to held
             if let Some(&Transaction::Deposit(amount)) = self.history.get(&transaction_id) {
                 self.move_to_held(amount);
             }
             
-            // if the transaction is a deposit, add the funds to held
+            // if the transaction is a withdrawal, add the funds to held or available depending on
+            // the policy
             if let Some(&Transaction::Withdrawal(amount)) = self.history.get(&transaction_id) {
-                self.held += amount;
+                match config.withdrawal_dispute {
+                    WithdrawalDisputePolicy::Hold => self.held += amount,
+                    WithdrawalDisputePolicy::CreditAvailable => self.add_to_available(amount),
+                }
             }
         }
     }
     
     // resolve a disputed transaction
-    fn resolve(&mut self, transaction_id: TransactionId) {
+    fn resolve(&mut self, transaction_id: TransactionId, config: &ProcessingConfig) {
         
         // check if the transaction exists and is disputed
-        if self.history.contains_key(&transaction_id)
-            && self.disputed_transactions.contains(&transaction_id) {
+        if self.is_under_dispute(&transaction_id) {
 
             // set the transaction as undisputed
             self.disputed_transactions.remove(&transaction_id); 
 
             // if the transaction is a deposit or withdrawal, move the funds back to available
-            // if it is a withdrawal, remove it from the history to avoid the risk of it being
-            // disputed twice
+            // (unless in flag-only mode, where they have not been moved)
+            let move_funds = config.dispute_fund_mode == DisputeFundMode::MoveToHeld;
             if let Some(&Transaction::Deposit(amount)) = self.history.get(&transaction_id) {
-                self.move_to_held(-amount);
+                if move_funds { self.release_from_held(amount); }
             } else if let Some(&Transaction::Withdrawal(amount)) = self.history.get(&transaction_id) {
-                self.move_to_held(-amount);
+
+                // if the funds of a disputed withdrawal were credited to the available ones
+                // rather than held, remove them
+                if move_funds { 
+                    match config.withdrawal_dispute {
+                        WithdrawalDisputePolicy::Hold => self.release_from_held(amount),
+                        WithdrawalDisputePolicy::CreditAvailable => self.add_to_available(-amount),
+                    }
+                }
+
+                // if it is a withdrawal, remove it from the history to avoid the risk of it being
+                // disputed twice
                 self.history.remove(&transaction_id);
             }
+
+            // a resolved deposit is now the most recently used transaction of the history
+            self.touch_history(transaction_id, config);
         }
     }
     
     // chargeback a disputed transaction
-    fn chargeback(&mut self, transaction_id: TransactionId) {
+    fn chargeback(&mut self, transaction_id: TransactionId, config: &ProcessingConfig) {
         
-        // check if the transaction exists and is disputed
-        if self.history.contains_key(&transaction_id) 
-            && self.disputed_transactions.contains(&transaction_id) {
+        // check if the transaction exists and is disputed, and has not already been charged back
+        // (which would remove its funds twice)
+        if self.is_under_dispute(&transaction_id) && !self.charged_back.contains(&transaction_id) {
 
-            // set the transaction as undisputed
+            // set the transaction as undisputed and charged back
             self.disputed_transactions.remove(&transaction_id); 
+            self.charged_back.insert(transaction_id);
+            self.touch_history(transaction_id, config);
 
-            // if the transaction is a deposit, remove the funds from the held funds
+            // if the transaction is a deposit, remove the funds from the held funds (or from the
+            // available ones in flag-only mode)
             if let Some(&Transaction::Deposit(amount)) = self.history.get(&transaction_id) {
-                self.remove_from_held(amount);
+                match config.dispute_fund_mode {
+                    DisputeFundMode::MoveToHeld => self.remove_from_held(amount),
+                    DisputeFundMode::FlagOnly => self.add_to_available(-amount),
+                }
             }
 
             // lock the account
             self.lock();
         }
     }
+
+    /// apply a transaction to the funds and state of the client, and return `true` if it was
+    /// executed
+    ///
+    /// Unlike `[ClientMap::execute_transaction]`, this does not check whether the account is
+    /// locked or closed, whether a deposit or withdrawal ID is already in the history, or the
+    /// limits set in `config`; `config` only selects how disputes affect the funds and the
+    /// tolerance for withdrawals. Withdrawals exceeding the available funds (including the
+    /// overdraft) by more than `[ProcessingConfig::withdrawal_tolerance]` are not executed, and
+    /// those within it drain the available funds and are recorded with the amount taken. Disputes,
+    /// resolves, and chargebacks referring to transactions which can not be disputed, resolved,
+    /// or charged back do nothing, but count as executed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use banking_exercise::client::Client;
+    /// use banking_exercise::transaction::*;
+    /// use banking_exercise::config::ProcessingConfig;
+    ///
+    /// let config = ProcessingConfig::default();
+    /// let mut client = Client::default();
+    /// assert!(client.apply(TransactionId(1), &Transaction::Deposit(100.), &config));
+    /// assert!(!client.apply(TransactionId(2), &Transaction::Withdrawal(150.), &config));
+    /// assert!(client.apply(TransactionId::default(), &Transaction::Dispute(TransactionId(1)), 
+    ///                      &config));
+    /// assert_eq!("0, 100, 100, false", format!("{}", client));
+    /// ```
+    pub fn apply(&mut self, transaction_id: TransactionId, transaction: &Transaction, 
+                 config: &ProcessingConfig) -> bool 
+    {
+        let mut transaction = *transaction;
+        match transaction {
+            Transaction::Deposit(amount) => self.add_to_available(amount),
+            Transaction::Withdrawal(amount) => {
+                
+                // if the client does not have enough available funds (including the
+                // overdraft), do nothing
+                let Some(amount) = self.amount_to_withdraw(amount, config) else {
+                    return false;
+                };
+
+                self.add_to_available(-amount);
+                self.withdrawn += amount;
+                transaction = Transaction::Withdrawal(amount);
+            },
+            Transaction::Dispute(id) => self.dispute(id, config), 
+            Transaction::Resolve(id) => self.resolve(id, config),
+            Transaction::Chargeback(id) => self.chargeback(id, config), 
+            Transaction::Open => self.closed = false,
+            Transaction::Close => self.closed = true,
+        }
+        
+        // add the transaction to the client history; only deposits and withdrawals are
+        // recorded, as they are the only ones which can be disputed (dispute-family
+        // transactions all have the default ID and would otherwise overwrite a deposit or
+        // withdrawal with this ID)
+        if matches!(transaction, Transaction::Deposit(_) | Transaction::Withdrawal(_)) {
+            self.add_to_history(transaction_id, transaction);
+            self.touch_history(transaction_id, config);
+        }
+
+        self.n_transactions += 1;
+        true
+    }
 }
 
 
@@ -165,619 +872,3498 @@ impl std::fmt::Display for Client {
 }
 
 
-impl std::fmt::Display for ClientId {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "{}", self.0)
-    }
-}
+impl<S: ClientStore + ?Sized> ClientMap<S> {
 
+    /// create an empty map keeping the clients in a given store
+    pub fn with_store(store: S) -> Self where S: Sized {
+        ClientMap { 
+            clients: store, 
+            sequence: 0, 
+            processed_files: HashSet::new(), 
+            dispute_ages: DisputeAges::default(),
+        }
+    }
 
-impl ClientMap {
+    /// the store keeping the clients
+    pub fn store(&self) -> &S {
+        &self.clients
+    }
  
+    /// number of clients in the map
+    pub fn len(&self) -> usize {
+        self.clients.len()
+    }
+
+    /// check if the map contains no client
+    pub fn is_empty(&self) -> bool {
+        self.clients.is_empty()
+    }
+
     /// check if a key is in te map
     pub fn contains_key(&self, key: &ClientId) -> bool {
-        self.0.contains_key(key)
+        self.clients.contains_key(key)
     }
 
-    /// insert a new `Client` and its `ClientId`
+    /// remove a client from the map, returning it if it was there
+    ///
+    /// The client's history is removed with it, as well as its open disputes from those tracked
+    /// for `[ProcessingConfig::dispute_ttl]`: a client later inserted with the same ID starts
+    /// afresh.
+    pub fn remove(&mut self, id: &ClientId) -> Option<Client> {
+        self.dispute_ages.remove_client(*id);
+        self.clients.remove(id)
+    }
+
+    /// the current balances of a client, or `None` if no client has this ID
     ///
     /// # Example
-    /// 
+    ///
     /// ```
     /// use banking_exercise::client::*;
     ///
-    /// // define a new empty ClientMap
     /// let mut clients_map = ClientMap::default();
+    /// clients_map.insert(ClientId(1), Client::new(10., 5., false)).unwrap();
+    /// assert_eq!(15., clients_map.get_summary(&ClientId(1)).unwrap().total);
+    /// assert_eq!(None, clients_map.get_summary(&ClientId(2)));
+    /// ```
+    pub fn get_summary(&self, client_id: &ClientId) -> Option<ClientSummary> {
+        self.get(client_id).map(|client| client.summary(*client_id))
+    }
+
+    /// the current balances of all clients, in the order of the default output (increasing
+    /// client IDs)
     ///
-    /// // Our firt client has just opened an account! 
-    /// // Let's give them the index ID.
-    /// let client_id = ClientId(1);
+    /// The map is only borrowed, so that the summaries can be built repeatedly, for instance for
+    /// API responses.
     ///
-    /// // Our first client deposits 100_000 RustyDollars in their account.
-    /// let client = Client::new(100_000., 0., false);
+    /// # Example
     ///
-    /// // add the client to the map
-    /// clients_map.insert(client_id, client);
     /// ```
-    pub fn insert(&mut self, id: ClientId, client: Client) -> Result<(), ExistingClientWarning> {
-        match self.0.insert(id, client) {
-            None => Ok(()), 
-            Some(client) => Err(ExistingClientWarning(client))
-        }
+    /// use banking_exercise::client::*;
+    ///
+    /// let mut clients_map = ClientMap::default();
+    /// clients_map.insert(ClientId(2), Client::new(20., 5., false)).unwrap();
+    /// clients_map.insert(ClientId(1), Client::new(10., 0., true)).unwrap();
+    /// let summaries = clients_map.to_sorted_summaries();
+    /// assert_eq!(vec![ClientId(1), ClientId(2)], 
+    ///            summaries.iter().map(|summary| summary.client).collect::<Vec<_>>());
+    /// assert_eq!(25., summaries[1].total);
+    /// ```
+    pub fn to_sorted_summaries(&self) -> Vec<ClientSummary> {
+        self.sorted_clients(SortOrder::ClientId).into_iter()
+            .map(|(id, client)| client.summary(*id))
+            .collect()
     }
 
-    /// get a reference to a `[Client]` from an ID if such a client exists
+    /// write the current balances of all clients as JSON Lines (`application/x-ndjson`), one
+    /// `[ClientSummary]` object per line, in the order of `[ClientMap::to_sorted_summaries]`
     ///
-    /// # Return type
+    /// Each line is written as soon as it is serialized, so that the whole output is never held
+    /// in memory.
     ///
-    /// This function returns an `Option<&Client>`, of the form `Some(client)` if `client` has the
-    /// right ID, or `None` if no such client exists.
-    fn get(&self, id: &ClientId) -> Option<&Client> {
-        self.0.get(id)
+    /// # Example
+    ///
+    /// ```
+    /// use banking_exercise::client::*;
+    ///
+    /// let mut clients_map = ClientMap::default();
+    /// clients_map.insert(ClientId(1), Client::new(10., 0., false)).unwrap();
+    /// let mut output = Vec::new();
+    /// clients_map.write_json_lines(&mut output).unwrap();
+    /// assert_eq!("{\"client\":1,\"available\":10.0,\"held\":0.0,\"total\":10.0,\"locked\":false}\n", 
+    ///            String::from_utf8(output).unwrap());
+    /// ```
+    #[cfg(feature = "serde")]
+    pub fn write_json_lines(&self, mut writer: impl std::io::Write) -> std::io::Result<()> {
+        for (id, client) in self.sorted_clients(SortOrder::ClientId) {
+            serde_json::to_writer(&mut writer, &client.summary(*id))?;
+            writeln!(writer)?;
+        }
+        Ok(())
     }
-    
-    /// get a mutable reference to a `[Client]` from an ID if such a client exists
+
+    // the clients in the order of the output: the most recently active ones first if required,
+    // and those without any activity last, in increasing order of their IDs
+    fn sorted_clients(&self, sort_order: SortOrder) -> Vec<(&ClientId, &Client)> {
+        self.clients.iter()
+            .sorted_by_key(|(id, client)| match sort_order {
+                SortOrder::ClientId => (std::cmp::Reverse(None), **id),
+                SortOrder::LastActivity => (std::cmp::Reverse(client.last_activity), **id),
+            })
+            .collect()
+    }
+
+    /// differences between the clients of this map and those of another one, in increasing order
+    /// of the client IDs
     ///
-    /// # Return type
+    /// Clients are compared through their summaries: the available and held funds are compared
+    /// with `[crate::amount::amounts_equal]`, so that rounding errors are not reported.
     ///
-    /// This function returns an `Option<&mut Client>`, of the form `Some(client)` if `client` has 
-    /// the right ID, or `None` if no such client exists.
-    fn get_mut(&mut self, id: &ClientId) -> Option<&mut Client> {
-        self.0.get_mut(id)
+    /// # Example
+    ///
+    /// ```
+    /// use banking_exercise::client::*;
+    ///
+    /// let mut first = ClientMap::default();
+    /// first.insert(ClientId(1), Client::new(10., 0., false)).unwrap();
+    /// let mut second = ClientMap::default();
+    /// second.insert(ClientId(1), Client::new(0.1 + 0.2 + 9.7, 0., false)).unwrap();
+    /// assert!(first.diff(&second).is_empty());
+    /// ```
+    pub fn diff<T: ClientStore>(&self, other: &ClientMap<T>) -> Vec<ClientDiff> {
+        self.clients.iter().chain(other.clients.iter())
+            .map(|(id, _)| *id)
+            .sorted()
+            .dedup()
+            .filter_map(|id| match (self.get_summary(&id), other.get_summary(&id)) {
+                (Some(first), Some(second)) => {
+                    let same = amounts_equal(first.available, second.available) 
+                        && amounts_equal(first.held, second.held) 
+                        && first.locked == second.locked;
+                    (!same).then_some(ClientDiff::Changed { first, second })
+                },
+                (Some(first), None) => Some(ClientDiff::OnlyInFirst(first)),
+                (None, Some(second)) => Some(ClientDiff::OnlyInSecond(second)),
+                (None, None) => None,
+            })
+            .collect()
     }
 
-    /// exxecute a transaction
+    /// summaries of the `n` clients with the highest total funds, in decreasing order of the
+    /// total funds and, for equal totals, increasing order of the IDs
     ///
-    /// # Errors
+    /// The clients are kept in a heap of at most `n` elements, so that the map is not sorted.
     ///
-    /// This function returns a `[ClientNotFoundError]` if the client is not found or a
-    /// `[LockedAccountError]` if their account is locked.
-    /// 
     /// # Example
-    /// 
+    ///
     /// ```
     /// use banking_exercise::client::*;
-    /// use banking_exercise::transaction::*;
     ///
-    /// // Create an empty ClientMap
     /// let mut clients_map = ClientMap::default();
-    ///
-    /// // Add a new client with an empty account and ID 1
-    /// clients_map.insert(ClientId(1), Client::new(0., 0., false));
-    /// 
-    /// // Execute a transaction: deposit
-    /// clients_map.execute_transaction(TransactionId(1), ClientId(1), 
-    ///                                 Transaction::Deposit(10_000.),
-    ///                                 false);
-    /// 
-    /// // Dispute the transaction
-    /// clients_map.execute_transaction(TransactionId::default(), ClientId(1), 
-    ///                                 Transaction::Dispute(TransactionId(1)),
-    ///                                 false);
-    /// 
-    /// // Resolve the transaction
-    /// clients_map.execute_transaction(TransactionId::default(), ClientId(1), 
-    ///                                 Transaction::Resolve(TransactionId(1)),
-    ///                                 false);
+    /// clients_map.insert(ClientId(1), Client::new(10., 0., false)).unwrap();
+    /// clients_map.insert(ClientId(2), Client::new(20., 5., false)).unwrap();
+    /// clients_map.insert(ClientId(3), Client::new(5., 0., false)).unwrap();
+    /// let top = clients_map.top_n_by_total(2);
+    /// assert_eq!(vec![ClientId(2), ClientId(1)], 
+    ///            top.iter().map(|summary| summary.client).collect::<Vec<_>>());
     /// ```
-    pub fn execute_transaction(&mut self, 
-                           transaction_id: TransactionId, 
-                           client_id: ClientId, 
-                           transaction: Transaction,
-                           is_term: bool)
-        -> Result<(), Box<dyn std::error::Error>> 
-    {
-        // get a reference to the client, or raise a `[ClientNotFoundError]` if the client does not
-        // exist 
-        if let Some(mut_ref_to_client) = self.get_mut(&client_id) {
-
-            // check that the account is not locked
-            if mut_ref_to_client.locked { return Err(Box::new(LockedAccountError {})); }
+    pub fn top_n_by_total(&self, n: usize) -> Vec<ClientSummary> {
+        if n == 0 { return Vec::new(); }
 
-            // if the transaction is a deposit or Withdrawal, check that its ID is not already in
-            // the client history
-            match &transaction
-            {
-                Transaction::Deposit(_) | Transaction::Withdrawal(_) => 
-                    if mut_ref_to_client.history.contains_key(&transaction_id) {
-                        let warning = format!("Warning: More than one transaction with client ID {} and transaction ID {}; all but the first will be ignored", 
-                                              client_id, transaction_id.0);
-                        eprintln!("{}", warning_style(warning, is_term));
-                        return Ok(());
-                    }
-                _ => ()
+        // a min-heap of the best clients seen so far, whose top is the worst of them
+        let mut heap = BinaryHeap::with_capacity(n + 1);
+        for (id, _) in self.clients.iter() {
+            if let Some(summary) = self.get_summary(id) {
+                heap.push(Reverse(RankedByTotal(summary)));
+                if heap.len() > n { heap.pop(); }
             }
+        }
+        heap.into_sorted_vec().into_iter().map(|Reverse(RankedByTotal(summary))| summary).collect()
+    }
 
-            // execute the transaction
-            match transaction {
-                Transaction::Deposit(amount) => mut_ref_to_client.add_to_available(amount),
-                Transaction::Withdrawal(amount) => {
-                    
-                    // if the client does not have enough available funds, do nothing
-                    if mut_ref_to_client.available < amount {
-                        return Ok(());
-                    }
+    /// check if a file with a given fingerprint has been processed into this map (see
+    /// `[ProcessingConfig::skip_processed_files]`)
+    pub fn is_processed(&self, fingerprint: u64) -> bool {
+        self.processed_files.contains(&fingerprint)
+    }
 
-                    mut_ref_to_client.add_to_available(-amount);
-                },
-                Transaction::Dispute(id) => mut_ref_to_client.dispute(id), 
-                Transaction::Resolve(id) => mut_ref_to_client.resolve(id),
-                Transaction::Chargeback(id) => mut_ref_to_client.chargeback(id), 
-            }
-            
-            // add the transaction to the client history
-            mut_ref_to_client.add_to_history(transaction_id, transaction);
-            
-            Ok(())
-    
-        } else {
-            Err(Box::new(ClientNotFoundError(client_id)))
-        }
-        
+    /// record that a file with a given fingerprint has been processed into this map, and return
+    /// `false` if it had already been recorded
+    pub fn mark_processed(&mut self, fingerprint: u64) -> bool {
+        self.processed_files.insert(fingerprint)
     }
-}
 
+    /// IDs of the clients with a locked account, in increasing order
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use banking_exercise::client::*;
+    ///
+    /// let mut clients_map = ClientMap::default();
+    /// clients_map.insert(ClientId(2), Client::new(0., 0., true)).unwrap();
+    /// clients_map.insert(ClientId(1), Client::new(0., 0., false)).unwrap();
+    /// assert_eq!(vec![ClientId(2)], clients_map.locked_clients());
+    /// ```
+    pub fn locked_clients(&self) -> Vec<ClientId> {
+        self.clients.iter()
+            .filter(|(_, client)| client.locked)
+            .map(|(id, _)| *id)
+            .sorted()
+            .collect()
+    }
 
-impl std::default::Default for ClientMap {
-    fn default() -> Self {
-        ClientMap(HashMap::<ClientId, Client>::new())
+    /// balances of the clients with a locked account and non-zero available or held funds (beyond
+    /// `[AMOUNT_EPSILON]`), in increasing order of their IDs
+    ///
+    /// The funds of these clients, for instance after a chargeback, can not be accessed until
+    /// their account is unlocked, and may need a follow-up.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use banking_exercise::client::*;
+    ///
+    /// let mut clients_map = ClientMap::default();
+    /// clients_map.insert(ClientId(3), Client::new(10., 0., true)).unwrap();
+    /// clients_map.insert(ClientId(2), Client::new(0., 0., true)).unwrap();
+    /// clients_map.insert(ClientId(1), Client::new(5., 0., false)).unwrap();
+    /// let stranded = clients_map.locked_with_funds();
+    /// assert_eq!(vec![ClientId(3)], 
+    ///            stranded.iter().map(|summary| summary.client).collect::<Vec<_>>());
+    /// ```
+    pub fn locked_with_funds(&self) -> Vec<ClientSummary> {
+        self.clients.iter()
+            .filter(|(_, client)| client.locked 
+                    && (client.available.abs() >= AMOUNT_EPSILON 
+                        || client.held.abs() >= AMOUNT_EPSILON))
+            .map(|(id, client)| client.summary(*id))
+            .sorted_by_key(|summary| summary.client)
+            .collect()
+    }
+
+    /// IDs of the clients with an empty history and no funds, in increasing order
+    ///
+    /// Such clients are usually artifacts of lines which created them without any effect, such as
+    /// a dispute for a client who never made a deposit, rather than real accounts.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use banking_exercise::client::*;
+    /// use banking_exercise::transaction::*;
+    /// use banking_exercise::config::ProcessingConfig;
+    ///
+    /// let mut clients_map = ClientMap::default();
+    /// clients_map.insert(ClientId(1), Client::new(0., 0., false)).unwrap();
+    /// clients_map.insert(ClientId(2), Client::new(0., 0., false)).unwrap();
+    /// clients_map.execute_transaction(TransactionId(1), ClientId(1), Transaction::Deposit(10.),
+    ///                                 &ProcessingConfig::default()).unwrap();
+    /// assert_eq!(vec![ClientId(2)], clients_map.phantom_clients());
+    /// ```
+    pub fn phantom_clients(&self) -> Vec<ClientId> {
+        self.clients.iter()
+            .filter(|(_, client)| client.history.is_empty() && client.is_empty())
+            .map(|(id, _)| *id)
+            .sorted()
+            .collect()
     }
-}
 
+    /// sum of the amounts of the transactions currently under dispute, over all clients
+    pub fn total_disputed_amount(&self) -> f64 {
+        self.clients.iter().map(|(_, client)| client.disputed_amount()).sum::<f64>() + 0.
+    }
 
-impl std::fmt::Display for ClientMap {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        let first_line = "client, available, held, total, locked";
-        writeln!(f, "{}", first_line)?;
-        for key in self.0.keys().sorted() {
-            if let Some(client) = self.get(key) {
-                writeln!(f, "{}, {}", key, client)?;
+    /// sum of the available funds, held funds, and total funds over all clients
+    pub fn total_funds(&self) -> (f64, f64, f64) {
+        let available = self.clients.iter().map(|(_, client)| client.available).sum::<f64>() + 0.;
+        let held = self.clients.iter().map(|(_, client)| client.held).sum::<f64>() + 0.;
+        (available, held, available + held)
+    }
+
+    /// add interest to the positive available funds of each client, i.e., increase them by
+    /// `available * rate`
+    ///
+    /// Held funds, negative available funds, and locked or closed accounts are left unchanged.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an `[InvalidRateError]` if `rate` is negative or not finite; the
+    /// map is then unchanged.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use banking_exercise::client::*;
+    ///
+    /// let mut clients_map = ClientMap::default();
+    /// clients_map.insert(ClientId(1), Client::new(100., 10., false)).unwrap();
+    /// clients_map.apply_interest(0.05).unwrap();
+    /// assert_eq!("client, available, held, total, locked\n1, 105, 10, 115, false\n", 
+    ///            format!("{}", clients_map));
+    /// assert!(clients_map.apply_interest(-0.05).is_err());
+    /// ```
+    pub fn apply_interest(&mut self, rate: f64) -> Result<(), InvalidRateError> {
+        if !(rate >= 0. && rate.is_finite()) { return Err(InvalidRateError(rate)); }
+        let ids: Vec<ClientId> = self.clients.iter().map(|(id, _)| *id).collect();
+        for id in ids {
+            if let Some(client) = self.clients.get_mut(&id) {
+                if !client.locked && !client.closed && client.available > 0. {
+                    client.add_to_available(client.available * rate);
+                }
             }
         }
         Ok(())
     }
-}
 
+    /// reset the cumulative withdrawals of all the clients (see
+    /// `[ProcessingConfig::withdrawal_limit]`), for instance at the start of a new day
+    pub fn clear_withdrawals(&mut self) {
+        let ids: Vec<ClientId> = self.clients.iter().map(|(id, _)| *id).collect();
+        for id in ids {
+            if let Some(client) = self.clients.get_mut(&id) {
+                client.clear_withdrawals();
+            }
+        }
+    }
 
-/// an error raised when a client is not found
-#[derive(Debug, Clone)]
-pub struct ClientNotFoundError(ClientId);
+    /// IDs of the clients with more than `threshold` applied transactions, in increasing order
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use banking_exercise::client::*;
+    /// use banking_exercise::transaction::*;
+    /// use banking_exercise::config::ProcessingConfig;
+    ///
+    /// let mut clients_map = ClientMap::default();
+    /// clients_map.insert(ClientId(1), Client::new(0., 0., false)).unwrap();
+    /// for i in 1..=3 {
+    ///     clients_map.execute_transaction(TransactionId(i), ClientId(1), 
+    ///                                     Transaction::Deposit(1.), 
+    ///                                     &ProcessingConfig::default()).unwrap();
+    /// }
+    /// assert_eq!(vec![ClientId(1)], clients_map.high_activity_clients(2));
+    /// assert!(clients_map.high_activity_clients(3).is_empty());
+    /// ```
+    pub fn high_activity_clients(&self, threshold: usize) -> Vec<ClientId> {
+        self.clients.iter()
+            .filter(|(_, client)| client.n_transactions > threshold)
+            .map(|(id, _)| *id)
+            .sorted()
+            .collect()
+    }
 
-impl std::fmt::Display for ClientNotFoundError {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "Client {} not found", self.0.0)
+    /// set the overdraft limit of a client
+    ///
+    /// # Errors
+    ///
+    /// This function returns a `[ClientNotFoundError]` if the client is not found.
+    pub fn set_overdraft_limit(&mut self, id: ClientId, overdraft_limit: f64) 
+        -> Result<(), ClientNotFoundError> 
+    {
+        let client = self.get_mut(&id).ok_or(ClientNotFoundError(id))?;
+        client.set_overdraft_limit(overdraft_limit);
+        Ok(())
     }
-}
 
-impl std::error::Error for ClientNotFoundError {}
+    /// check if a transaction of a client is currently under dispute, or return `None` if the
+    /// client does not exist
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use banking_exercise::client::*;
+    /// use banking_exercise::transaction::*;
+    /// use banking_exercise::config::ProcessingConfig;
+    ///
+    /// let config = ProcessingConfig::default();
+    /// let mut clients_map = ClientMap::default();
+    /// clients_map.insert(ClientId(1), Client::new(0., 0., false)).unwrap();
+    /// clients_map.execute_transaction(TransactionId(1), ClientId(1), Transaction::Deposit(10.), 
+    ///                                 &config).unwrap();
+    /// clients_map.execute_transaction(TransactionId::default(), ClientId(1), 
+    ///                                 Transaction::Dispute(TransactionId(1)), &config).unwrap();
+    /// assert_eq!(Some(true), clients_map.is_transaction_disputed(&ClientId(1), &TransactionId(1)));
+    /// assert_eq!(None, clients_map.is_transaction_disputed(&ClientId(2), &TransactionId(1)));
+    /// ```
+    pub fn is_transaction_disputed(&self, client_id: &ClientId, transaction_id: &TransactionId) 
+        -> Option<bool> 
+    {
+        self.get(client_id).map(|client| client.is_disputed(transaction_id))
+    }
 
+    /// check if a deposit or withdrawal is in the history of a client
+    pub(crate) fn has_transaction(&self, client_id: &ClientId, transaction_id: &TransactionId) 
+        -> bool 
+    {
+        self.get(client_id).is_some_and(|client| client.is_in_history(transaction_id))
+    }
 
-/// an error raised when trying to do a transaction on a locked account
-#[derive(Debug, Clone)]
-pub struct LockedAccountError {}
+    /// record the ID of a deposit or withdrawal of a client and return the highest ID recorded
+    /// earlier for this client if it is higher (i.e., if the transactions are out of order)
+    pub(crate) fn record_transaction_id(&mut self, 
+                                        client_id: &ClientId, 
+                                        transaction_id: TransactionId) 
+        -> Option<TransactionId> 
+    {
+        let client = self.get_mut(client_id)?;
+        match client.max_transaction_id {
+            Some(max_id) if max_id.0 > transaction_id.0 => Some(max_id),
+            _ => {
+                client.max_transaction_id = Some(transaction_id);
+                None
+            }
+        }
+    }
 
-impl std::fmt::Display for LockedAccountError {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "The client account is locked")
+    /// set the notes of the last transaction of a client
+    pub(crate) fn set_last_notes(&mut self, client_id: &ClientId, notes: Option<String>) {
+        if let Some(client) = self.get_mut(client_id) {
+            client.last_notes = notes;
+        }
     }
-}
 
-impl std::error::Error for LockedAccountError {}
+    /// check if the account of a client is locked (`false` if the client does not exist)
+    pub(crate) fn is_locked(&self, id: &ClientId) -> bool {
+        self.get(id).is_some_and(|client| client.locked)
+    }
 
+    /// queue a transaction on a locked account, to be executed when the account is unlocked
+    pub(crate) fn queue_transaction(&mut self, 
+                                    transaction_id: TransactionId, 
+                                    client_id: ClientId, 
+                                    transaction: Transaction) 
+        -> Result<(), ClientNotFoundError> 
+    {
+        let client = self.get_mut(&client_id).ok_or(ClientNotFoundError(client_id))?;
+        client.pending.push_back((transaction_id, transaction));
+        Ok(())
+    }
 
+    /// unlock the account of a client and execute the transactions queued while it was locked
+    ///
+    /// Queued transactions are executed in the order in which they were received. If one of them
+    /// locks the account again, the following ones remain queued.
+    ///
+    /// # Errors
+    ///
+    /// This function returns a `[ClientNotFoundError]` if the client is not found, or the first
+    /// error returned by a queued transaction; the transactions following it remain queued.
+    pub fn unlock(&mut self, client_id: ClientId, config: &ProcessingConfig) 
+        -> Result<(), Box<dyn std::error::Error>> 
+    {
+        let client = self.get_mut(&client_id).ok_or(ClientNotFoundError(client_id))?;
+        client.locked = false;
+        let mut pending = std::mem::take(&mut client.pending);
+        while let Some((transaction_id, transaction)) = pending.pop_front() {
 
-#[cfg(test)]
-mod tests {
-    
-    use super::*;
-    
-    #[test]
-    fn test_add_funds_1() {
- 
-        // Our new client deposits 2_022 RustyDollars in their account.
-        let mut client = Client::new(2_022., 0., false);
-        
-        // Our client just remembered they own 100_000 RustyDollars worth of RSACoin, the latest
-        // craze among classical tech investors. Unfortunately, cryptographic functions based on RSA
-        // are not quantum secure, and they risk osing most of their investment as soon as a 
-        // powerful enough quantum computer is built. They thus decide to sell their RSACoins and 
-        // deposit the money in their account
-        client.add_to_available(100_000.);
-        
-        // check the client info
-        assert_eq!("102022, 0, 102022, false".to_string(), format!("{}", client));
-    }
+            // if the account has been locked again, queue the remaining transactions
+            if self.is_locked(&client_id) {
+                pending.push_front((transaction_id, transaction));
+                break;
+            }
 
-    #[test]
-    fn test_lock_1() {
- 
-        // Our new client deposits 9e99 RustyDollars in their account.
-        let mut client = Client::new(9e+99_f64, 0., false);
-        
-        // Wait a minute... This is more than the number of atoms in the known universe—no one can
-        // be quite rich enough to have that many RustyDolars! Surely there is something frudulent
-        // here. Let's lock the account and investigate!
-        client.lock();
-    
-        // check the client info
-        assert_eq!(format!("{}, 0, {}, true", 9e+99_f64, 9e+99_f64), format!("{}", client));
+            // withdrawals exceeding the available funds are ignored, as when read from a file
+            let result = self.execute_transaction(transaction_id, client_id, transaction, config);
+            if let Err(error) = result.or_else(ignore_insufficient_funds) {
+                self.requeue(&client_id, pending);
+                return Err(error);
+            }
+        }
+        self.requeue(&client_id, pending);
+        Ok(())
     }
 
-    #[test]
-    fn test_move_to_held_1() {
- 
-        // Our new client deposits 2_023 RustyDollars in their account.
-        let mut client = Client::new(2_023., 0., false);
-       
-        // Our UberTransactionChecker™ system, using the latest Fourier Transformer Networks, has
-        // detected a possible error: depositing 2,023 RustyDollars now sounds one year early! We
-        // pre-emptively correct this likely error by moving 1 RustyDollar from the available funds 
-        // to the held ones, and make a note to contact the client to enquire about this.
-        client.move_to_held(1.);
-       
-        // check the client info
-        assert_eq!("2022, 1, 2023, false".to_string(), format!("{}", client));
+    // put some transactions back at the front of the queue of a client
+    fn requeue(&mut self, client_id: &ClientId, mut transactions: VecDeque<(TransactionId, Transaction)>) {
+        if let Some(client) = self.get_mut(client_id) {
+            transactions.append(&mut client.pending);
+            client.pending = transactions;
+        }
     }
 
-    #[test]
-    fn add_to_history() {
-
-        // Our new client deposits 2_022 RustyDollars in their account.
-        let mut client = Client::new(2_022., 0., false);
-        
+    /// merge another `[ClientMap]` into this one
+    ///
+    /// Clients present in only one map are kept as they are. Clients present in both maps are
+    /// merged: the available and held funds are summed, the account is locked (resp. closed) if
+    /// either account is, and the histories and sets of disputed transactions are merged. If a
+    /// transaction ID is present in both histories, the transaction is kept as described in
+    /// `[Client::merge_from]`. The maps may use different stores.
+    ///
+    /// The files processed into `other` are recorded as processed into `self` (see
+    /// `[ClientMap::mark_processed]`), and the open disputes of both maps keep their ages for
+    /// `[ProcessingConfig::dispute_ttl]`. The clients of `other` keep their last activity (see
+    /// `[SortOrder::LastActivity]`), numbered by the transactions of `other`, so that the clients
+    /// of both maps are interleaved as if the maps had been filled concurrently; transactions
+    /// executed after the merge are numbered after those of both maps.
+    ///
+    /// # Errors
+    ///
+    /// This function returns a `[MergeConflictError]` if the clients with the same ID in both
+    /// maps can not be merged (see `[Client::merge_from]`); `self` is then unchanged.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use banking_exercise::client::*;
+    ///
+    /// let mut clients_map = ClientMap::default();
+    /// clients_map.insert(ClientId(1), Client::new(100., 0., false)).unwrap();
+    ///
+    /// let mut other_clients_map = ClientMap::default();
+    /// other_clients_map.insert(ClientId(1), Client::new(50., 10., true)).unwrap();
+    /// other_clients_map.insert(ClientId(2), Client::new(20., 0., false)).unwrap();
+    ///
+    /// clients_map.merge(other_clients_map).unwrap();
+    /// assert_eq!("client, available, held, total, locked\n\
+    ///             1, 150, 10, 160, true\n\
+    ///             2, 20, 0, 20, false\n", 
+    ///            format!("{}", clients_map));
+    /// ```
+    pub fn merge<T: ClientStore>(&mut self, mut other: ClientMap<T>) 
+        -> Result<(), MergeConflictError> 
+    {
+
+        // check all the clients first, so that the map is unchanged on error
+        for (id, client) in other.clients.iter() {
+            if let Some(existing_client) = self.get(id) {
+                existing_client.merge_conflicts(client)?;
+            }
+        }
+        let ids: Vec<ClientId> = other.clients.iter().map(|(id, _)| *id).collect();
+        for id in ids {
+            let client = other.clients.remove(&id).expect("the client is in the store");
+            match self.get_mut(&id) {
+                Some(existing_client) => existing_client.merge_from(client)?,
+                None => { self.clients.insert(id, client); }
+            }
+        }
+        self.processed_files.extend(other.processed_files);
+        let sequence = self.sequence.max(other.sequence);
+        self.dispute_ages.shift(sequence - self.sequence);
+        self.dispute_ages.merge(other.dispute_ages, other.sequence, sequence);
+        self.sequence = sequence;
+        Ok(())
+    }
+
+    /// merge a client into another one, removing it from the map (see `[Client::merge_from]`)
+    ///
+    /// # Errors
+    ///
+    /// This function returns a `[ClientNotFoundError]` if either client is not found, or a
+    /// `[MergeConflictError]` if the clients can not be merged; the map is then unchanged. Merging
+    /// a client into itself does nothing.
+    ///
+    /// The open disputes of the removed client keep their ages for
+    /// `[ProcessingConfig::dispute_ttl]` as disputes of the merged one.
+    pub fn merge_clients(&mut self, into: ClientId, from: ClientId) 
+        -> Result<(), Box<dyn std::error::Error>> 
+    {
+        let client = self.get(&into).ok_or(ClientNotFoundError(into))?;
+        if into == from { return Ok(()); }
+        client.merge_conflicts(self.get(&from).ok_or(ClientNotFoundError(from))?)?;
+        self.dispute_ages.rename_client(from, into);
+        let other = self.remove(&from).expect("the client exists");
+        if let Some(client) = self.get_mut(&into) {
+            client.merge_from(other)?;
+        }
+        Ok(())
+    }
+
+    /// get a wrapper to display the map with a given `[OutputConfig]`
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use banking_exercise::client::*;
+    /// use banking_exercise::config::OutputConfig;
+    ///
+    /// // a client with an empty account...
+    /// let mut clients_map = ClientMap::default();
+    /// clients_map.insert(ClientId(1), Client::default()).unwrap();
+    ///
+    /// // ...is not shown when only non-empty clients are displayed
+    /// let config = OutputConfig { non_empty_only: true, ..Default::default() };
+    /// assert_eq!("client, available, held, total, locked\n", 
+    ///            format!("{}", clients_map.display(&config)));
+    /// ```
+    pub fn display<'a>(&'a self, config: &'a OutputConfig) -> ClientMapDisplay<'a, S> {
+        ClientMapDisplay { clients_map: self, config }
+    }
+
+    /// insert a new `Client` and its `ClientId`
+    ///
+    /// # Example
+    /// 
+    /// ```
+    /// use banking_exercise::client::*;
+    ///
+    /// // define a new empty ClientMap
+    /// let mut clients_map = ClientMap::default();
+    ///
+    /// // Our firt client has just opened an account! 
+    /// // Let's give them the index ID.
+    /// let client_id = ClientId(1);
+    ///
+    /// // Our first client deposits 100_000 RustyDollars in their account.
+    /// let client = Client::new(100_000., 0., false);
+    ///
+    /// // add the client to the map
+    /// clients_map.insert(client_id, client);
+    /// ```
+    pub fn insert(&mut self, id: ClientId, client: Client) -> Result<(), ExistingClientWarning> {
+        match self.clients.insert(id, client) {
+            None => Ok(()), 
+            Some(client) => Err(ExistingClientWarning(Box::new(client)))
+        }
+    }
+
+    /// get a mutable reference to the `[Client]` with a given ID, inserting an empty one first
+    /// if no client has this ID
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use banking_exercise::client::*;
+    ///
+    /// let mut clients_map = ClientMap::default();
+    /// clients_map.get_or_create(ClientId(1)).set_overdraft_limit(50.);
+    /// assert_eq!(50., clients_map.get_or_create(ClientId(1)).available_including_overdraft());
+    /// assert_eq!(1, clients_map.len());
+    /// ```
+    pub fn get_or_create(&mut self, id: ClientId) -> &mut Client {
+        if !self.clients.contains_key(&id) {
+            self.clients.insert(id, Client::default());
+        }
+        self.clients.get_mut(&id).expect("the client has been inserted if it did not exist")
+    }
+
+    /// get a reference to a `[Client]` from an ID if such a client exists
+    ///
+    /// # Return type
+    ///
+    /// This function returns an `Option<&Client>`, of the form `Some(client)` if `client` has the
+    /// right ID, or `None` if no such client exists.
+    pub(crate) fn get(&self, id: &ClientId) -> Option<&Client> {
+        self.clients.get(id)
+    }
+    
+    /// get a mutable reference to a `[Client]` from an ID if such a client exists
+    ///
+    /// # Return type
+    ///
+    /// This function returns an `Option<&mut Client>`, of the form `Some(client)` if `client` has 
+    /// the right ID, or `None` if no such client exists.
+    fn get_mut(&mut self, id: &ClientId) -> Option<&mut Client> {
+        self.clients.get_mut(id)
+    }
+
+    /// check if a dispute, resolve, or chargeback would change the data of an existing client
+    ///
+    /// Deposits and withdrawals always return `true`, and any transaction on a client which does
+    /// not exist returns `false`.
+    pub(crate) fn dispute_applies(&self, id: &ClientId, transaction: &Transaction) -> bool {
+        match self.get(id) {
+            Some(client) => client.dispute_applies(transaction),
+            None => false
+        }
+    }
+
+    /// exxecute a transaction
+    ///
+    /// A deposit or withdrawal with the same ID as an earlier one of the same client is ignored
+    /// with a warning, which is stronger if its type or amount differs; disputes, resolves, and
+    /// chargebacks with this ID then refer to the earlier one, which is the only one in the
+    /// history.
+    ///
+    /// # Errors
+    ///
+    /// This function returns a `[ClientNotFoundError]` if the client is not found, a
+    /// `[LockedAccountError]` if their account is locked, a `[ClosedAccountError]` if their
+    /// account is closed and the transaction does not re-open it, a `[MaxHeldExceededError]` if
+    /// the transaction is a dispute which would bring the held funds above
+    /// `[ProcessingConfig::max_held]`, or a `[WithdrawalLimitError]` if it is a withdrawal which
+    /// would bring the cumulative withdrawals of the client above
+    /// `[ProcessingConfig::withdrawal_limit]`. It returns an `[InsufficientFundsError]` for a
+    /// withdrawal exceeding the available funds of the client (including the overdraft). If
+    /// `[ProcessingConfig::error_on_conflicting_duplicate]` is set, it returns a
+    /// `[ConflictingDuplicateError]` for a deposit or withdrawal with the same ID as an earlier one
+    /// but a different type or amount. If `[ProcessingConfig::error_on_resolve_after_chargeback]`
+    /// is set, it returns a `[ResolveAfterChargebackError]` for a resolve of a transaction which
+    /// has already been charged back.
+    /// 
+    /// # Example
+    /// 
+    /// ```
+    /// use banking_exercise::client::*;
+    /// use banking_exercise::transaction::*;
+    /// use banking_exercise::config::ProcessingConfig;
+    ///
+    /// // Create an empty ClientMap
+    /// let mut clients_map = ClientMap::default();
+    ///
+    /// // Add a new client with an empty account and ID 1
+    /// clients_map.insert(ClientId(1), Client::new(0., 0., false));
+    /// 
+    /// // Execute a transaction: deposit
+    /// clients_map.execute_transaction(TransactionId(1), ClientId(1), 
+    ///                                 Transaction::Deposit(10_000.),
+    ///                                 &ProcessingConfig::default());
+    /// 
+    /// // Dispute the transaction
+    /// clients_map.execute_transaction(TransactionId::default(), ClientId(1), 
+    ///                                 Transaction::Dispute(TransactionId(1)),
+    ///                                 &ProcessingConfig::default());
+    /// 
+    /// // Resolve the transaction
+    /// clients_map.execute_transaction(TransactionId::default(), ClientId(1), 
+    ///                                 Transaction::Resolve(TransactionId(1)),
+    ///                                 &ProcessingConfig::default());
+    /// ```
+    pub fn execute_transaction(&mut self, 
+                           transaction_id: TransactionId, 
+                           client_id: ClientId, 
+                           transaction: Transaction,
+                           config: &ProcessingConfig)
+        -> Result<(), Box<dyn std::error::Error>> 
+    {
+        // round the amount if required
+        let transaction = match (transaction, config.round_amounts) {
+            (Transaction::Deposit(amount), Some(places)) => 
+                Transaction::Deposit(round_to(amount, places)),
+            (Transaction::Withdrawal(amount), Some(places)) => 
+                Transaction::Withdrawal(round_to(amount, places)),
+            _ => transaction
+        };
+
+        // get a reference to the client, or raise a `[ClientNotFoundError]` if the client does not
+        // exist 
+        if let Some(mut_ref_to_client) = self.clients.get_mut(&client_id) {
+
+            // a resolve for a transaction which has been charged back usually signals duplicate
+            // or out-of-order events: warn about it and ignore it (or return an error if
+            // required), whether the account is locked or not
+            if let Transaction::Resolve(id) = transaction {
+                if mut_ref_to_client.charged_back.contains(&id) {
+                    let error = ResolveAfterChargebackError { client_id, transaction_id: id };
+                    if config.error_on_resolve_after_chargeback { return Err(Box::new(error)); }
+                    config.warn(format!("Warning: {}; it will be ignored", error));
+                    return Ok(());
+                }
+            }
+
+            // check that the account is not locked
+            if mut_ref_to_client.locked { return Err(Box::new(LockedAccountError {})); }
+
+            // check that the account is not closed, unless the transaction re-opens it
+            if mut_ref_to_client.closed && transaction != Transaction::Open { 
+                return Err(Box::new(ClosedAccountError {})); 
+            }
+
+            // if the transaction is a deposit or Withdrawal, check that its ID is not already in
+            // the client history, or has not been evicted from it; a duplicate differing from the
+            // original, in type or amount, signals a data problem rather than a replayed line
+            if matches!(transaction, Transaction::Deposit(_) | Transaction::Withdrawal(_)) 
+                && mut_ref_to_client.retired_ids.contains(&transaction_id) {
+                let warning = format!("Warning: More than one transaction with client ID {} and transaction ID {}; all but the first will be ignored", 
+                                      client_id, transaction_id.0);
+                config.warn(warning);
+                return Ok(());
+            }
+            if let (Transaction::Deposit(_) | Transaction::Withdrawal(_), Some(original)) = 
+                (&transaction, mut_ref_to_client.history.get(&transaction_id)) {
+                if same_transaction(original, &transaction) {
+                    let warning = format!("Warning: More than one transaction with client ID {} and transaction ID {}; all but the first will be ignored", 
+                                          client_id, transaction_id.0);
+                    config.warn(warning);
+                    return Ok(());
+                }
+                let error = ConflictingDuplicateError { 
+                    client_id, transaction_id, original: *original, duplicate: transaction 
+                };
+                if config.error_on_conflicting_duplicate { return Err(Box::new(error)); }
+                config.warn(format!("Warning: {}; the duplicate will be ignored, but the data may be corrupted", 
+                                    error));
+                return Ok(());
+            }
+
+            // check that a dispute would not bring the held funds above the maximum
+            if let (Transaction::Dispute(id), Some(max_held)) = (transaction, config.max_held) {
+                if let Some(amount) = mut_ref_to_client.amount_to_hold(&id, config) {
+                    let held = mut_ref_to_client.held + amount;
+                    if held > max_held && !amounts_equal(held, max_held) {
+                        return Err(Box::new(MaxHeldExceededError { transaction_id: id, max_held }));
+                    }
+                }
+            }
+
+            // check that a withdrawal would not bring the cumulative withdrawals above the limit
+            if let (Transaction::Withdrawal(amount), Some(withdrawal_limit)) = 
+                (transaction, config.withdrawal_limit) {
+                let withdrawn = mut_ref_to_client.withdrawn + amount;
+                if withdrawn > withdrawal_limit && !amounts_equal(withdrawn, withdrawal_limit) {
+                    return Err(Box::new(WithdrawalLimitError { transaction_id, withdrawal_limit }));
+                }
+            }
+
+            // check that a deposit would not bring the funds beyond the largest representable
+            // amount, nor (if required) the total funds beyond the safe limit
+            let total_before = mut_ref_to_client.available + mut_ref_to_client.held;
+            if let Transaction::Deposit(amount) = transaction {
+                if !(mut_ref_to_client.available + amount).is_finite() 
+                    || !(total_before + amount).is_finite() {
+                    return Err(Box::new(AmountOverflowError { transaction_id }));
+                }
+            }
+            if let (Transaction::Deposit(amount), SafeIntegerPolicy::Reject) = 
+                (transaction, config.safe_integer) {
+                if (total_before + amount).abs() > MAX_SAFE_AMOUNT {
+                    return Err(Box::new(UnsafeTotalError { transaction_id }));
+                }
+            }
+
+            // check that the client has enough available funds (including the overdraft and the
+            // tolerance) for a withdrawal; if required, a withdrawal exceeding them is interpreted
+            // as the reversal of a disputed deposit with the same amount
+            let mut reversed_deposit = None;
+            if let Transaction::Withdrawal(amount) = transaction {
+                if mut_ref_to_client.amount_to_withdraw(amount, config).is_none() {
+                    if config.over_withdrawal == OverWithdrawalPolicy::ReverseDeposit {
+                        reversed_deposit = 
+                            mut_ref_to_client.reverse_disputed_deposit(amount, config);
+                    }
+                    if reversed_deposit.is_none() {
+                        let available = mut_ref_to_client.available_including_overdraft();
+                        let error = InsufficientFundsError { client_id, amount, available };
+                        return Err(Box::new(error));
+                    }
+                }
+            }
+
+            // check whether the transaction opens a dispute, whose age is then tracked if disputes
+            // expire
+            let opened_dispute = match transaction {
+                Transaction::Dispute(id) if config.dispute_ttl.is_some() 
+                    && mut_ref_to_client.is_disputable(&id) => Some(id),
+                _ => None,
+            };
+
+            // execute the transaction, unless it has reversed a deposit; the IDs of both are then
+            // kept, so that replaying either of them is ignored as a duplicate
+            if let Some(deposit_id) = reversed_deposit {
+                mut_ref_to_client.retired_ids.insert(deposit_id);
+                mut_ref_to_client.retired_ids.insert(transaction_id);
+                mut_ref_to_client.n_transactions += 1;
+                config.warn(format!("Warning: Withdrawal {} exceeds the available funds of client {}; deposit {} has been reversed instead", 
+                                    transaction_id.0, client_id, deposit_id.0));
+            } else if !mut_ref_to_client.apply(transaction_id, &transaction, config) {
+                return Ok(());
+            }
+
+            // record when the client was last active, for `[SortOrder::LastActivity]`, and when
+            // the dispute was opened, if any
+            mut_ref_to_client.last_activity = Some(self.sequence);
+            if let Some(id) = opened_dispute {
+                if mut_ref_to_client.is_under_dispute(&id) {
+                    self.dispute_ages.queue.push_back((self.sequence, client_id, id));
+                    self.dispute_ages.opened.insert((client_id, id), self.sequence);
+                }
+            }
+            self.sequence += 1;
+
+            // warn if the total funds have gone beyond the safe limit
+            let total_after = mut_ref_to_client.available + mut_ref_to_client.held;
+            if config.safe_integer != SafeIntegerPolicy::Ignore 
+                && crosses_safe_limit(total_before, total_after) {
+                config.warn(format!("Warning: The total funds of client {} ({}) are beyond 2^53 after transaction {}; integer amounts may lose precision", 
+                                    client_id, total_after, transaction_id.0));
+            }
+
+            // keep at most `[ProcessingConfig::history_capacity]` undisputed deposits and
+            // withdrawals in the history of the client
+            if let Some(capacity) = config.history_capacity {
+                mut_ref_to_client.evict_history(capacity);
+            }
+
+            // in debug builds, check that the transaction has left the client data consistent
+            debug_assert!(mut_ref_to_client.is_consistent(), 
+                          "inconsistent data for client {} after transaction {}: {:?}", 
+                          client_id, transaction_id.0, mut_ref_to_client);
+
+            self.expire_disputes(config);
+            
+            Ok(())
+    
+        } else {
+            Err(Box::new(ClientNotFoundError(client_id)))
+        }
+        
+    }
+
+    // resolve, with a warning, the disputes opened at least `[ProcessingConfig::dispute_ttl]`
+    // processed transactions ago; disputes of locked accounts are left as they are
+    fn expire_disputes(&mut self, config: &ProcessingConfig) {
+        let ttl = match config.dispute_ttl {
+            Some(ttl) => ttl,
+            None => return,
+        };
+        while let Some(&(opened_at, client_id, id)) = self.dispute_ages.queue.front() {
+            if self.sequence - opened_at <= ttl { break; }
+            self.dispute_ages.queue.pop_front();
+
+            // skip the entry if the dispute has been closed (or re-opened) since
+            if self.dispute_ages.opened.get(&(client_id, id)) != Some(&opened_at) { continue; }
+            self.dispute_ages.opened.remove(&(client_id, id));
+            if let Some(client) = self.clients.get_mut(&client_id) {
+                if client.locked || !client.is_under_dispute(&id) { continue; }
+                client.resolve(id, config);
+                config.warn(format!("Warning: The dispute of transaction {} of client {} has expired after {} transactions; it has been resolved", 
+                                    id.0, client_id, ttl));
+            }
+        }
+    }
+
+    /// execute a transaction as `[ClientMap::execute_transaction]` and return the balances of the
+    /// client afterwards
+    ///
+    /// Transactions which are ignored with a warning, such as duplicates, leave the balances
+    /// unchanged; the returned summary is then that of the client before the transaction.
+    ///
+    /// # Errors
+    ///
+    /// The errors of `[ClientMap::execute_transaction]` are returned wrapped in a
+    /// `[ProcessingError::Rejected]`, so that they can be handled with those of the files.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use banking_exercise::client::*;
+    /// use banking_exercise::transaction::*;
+    /// use banking_exercise::config::ProcessingConfig;
+    ///
+    /// let mut clients_map = ClientMap::default();
+    /// clients_map.insert(ClientId(1), Client::new(0., 0., false)).unwrap();
+    /// let summary = clients_map.apply_transaction_checked(TransactionId(1), ClientId(1), 
+    ///                                                     Transaction::Deposit(10.),
+    ///                                                     &ProcessingConfig::default()).unwrap();
+    /// assert_eq!(10., summary.available);
+    /// ```
+    pub fn apply_transaction_checked(&mut self, 
+                                     transaction_id: TransactionId, 
+                                     client_id: ClientId, 
+                                     transaction: Transaction,
+                                     config: &ProcessingConfig)
+        -> Result<ClientSummary, ProcessingError> 
+    {
+        self.execute_transaction(transaction_id, client_id, transaction, config)
+            .map_err(ProcessingError::Rejected)?;
+        Ok(self.get_summary(&client_id).expect("the client exists if the transaction succeeded"))
+    }
+}
+
+
+impl<S: ClientStore + ?Sized> std::fmt::Display for ClientMap<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        self.display(&OutputConfig::default()).fmt(f)
+    }
+}
+
+
+/// name of the column of the client IDs
+pub const COLUMN_CLIENT: &str = "client";
+
+/// name of the column of the available funds
+pub const COLUMN_AVAILABLE: &str = "available";
+
+/// name of the column of the held funds
+pub const COLUMN_HELD: &str = "held";
+
+/// name of the column of the total funds
+pub const COLUMN_TOTAL: &str = "total";
+
+/// name of the column showing if the account is locked
+pub const COLUMN_LOCKED: &str = "locked";
+
+/// name of the column of the overdraft limits (verbose output only)
+pub const COLUMN_OVERDRAFT_LIMIT: &str = "overdraft_limit";
+
+/// name of the column of the available funds including the overdraft (verbose output only)
+pub const COLUMN_AVAILABLE_INCLUDING_OVERDRAFT: &str = "available_including_overdraft";
+
+/// name of the column of the notes of the last transaction (only if
+/// `[OutputConfig::show_notes]` is set)
+pub const COLUMN_NOTES: &str = "notes";
+
+/// names of the output columns, in order
+pub const COLUMNS: [&str; 5] = column_names(Column::DEFAULT);
+
+/// names of the verbose output columns, in order
+pub const VERBOSE_COLUMNS: [&str; 7] = column_names(Column::VERBOSE);
+
+/// header of the output, i.e., the names of `[COLUMNS]` separated by `", "`
+pub const CSV_HEADER: &str = header_str(&CSV_HEADER_BYTES);
+
+/// header of the verbose output, i.e., the names of `[VERBOSE_COLUMNS]` separated by `", "`
+pub const VERBOSE_CSV_HEADER: &str = header_str(&VERBOSE_CSV_HEADER_BYTES);
+
+const CSV_HEADER_BYTES: [u8; header_len(&COLUMNS)] = header_bytes(&COLUMNS);
+const VERBOSE_CSV_HEADER_BYTES: [u8; header_len(&VERBOSE_COLUMNS)] = 
+    header_bytes(&VERBOSE_COLUMNS);
+
+// names of some columns, so that the constants above are all derived from `[Column]`
+const fn column_names<const N: usize>(columns: [Column; N]) -> [&'static str; N] {
+    let mut names = [""; N];
+    let mut i = 0;
+    while i < N {
+        names[i] = columns[i].name();
+        i += 1;
+    }
+    names
+}
+
+// length of the names of some columns separated by `", "`
+const fn header_len(names: &[&str]) -> usize {
+    let mut len = 0;
+    let mut i = 0;
+    while i < names.len() {
+        if i > 0 { len += 2; }
+        len += names[i].len();
+        i += 1;
+    }
+    len
+}
+
+// bytes of the names of some columns separated by `", "`, where `N` is their `[header_len]`
+const fn header_bytes<const N: usize>(names: &[&str]) -> [u8; N] {
+    let mut bytes = [0; N];
+    let mut n = 0;
+    let mut i = 0;
+    while i < names.len() {
+        if i > 0 {
+            bytes[n] = b',';
+            bytes[n + 1] = b' ';
+            n += 2;
+        }
+        let name = names[i].as_bytes();
+        let mut j = 0;
+        while j < name.len() {
+            bytes[n] = name[j];
+            n += 1;
+            j += 1;
+        }
+        i += 1;
+    }
+    bytes
+}
+
+// a header built by `[header_bytes]`, which is valid UTF-8 since the names are
+const fn header_str(bytes: &'static [u8]) -> &'static str {
+    match std::str::from_utf8(bytes) {
+        Ok(header) => header,
+        Err(_) => panic!("the names of the columns are valid UTF-8"),
+    }
+}
+
+
+/// label of the row of totals in the first column (see `[OutputConfig::totals_row]`)
+pub const TOTALS_ROW_LABEL: &str = "TOTAL";
+
+
+/// a wrapper displaying a `[ClientMap]` with a given `[OutputConfig]`
+pub struct ClientMapDisplay<'a, S: ?Sized = HashMap<ClientId, Client>> {
+    clients_map: &'a ClientMap<S>,
+    config: &'a OutputConfig,
+}
+
+impl<S: ClientStore + ?Sized> ClientMapDisplay<'_, S> {
+
+    // the cells of the displayed rows, excluding the header
+    fn rows(&self) -> Vec<Vec<String>> {
+        // the clients in the same order as `[ClientMap::to_sorted_summaries]` by default
+        let clients: Vec<(&ClientId, &Client)> = self.clients_map
+            .sorted_clients(self.config.sort_order).into_iter()
+
+            // skip the clients with no funds and an unlocked account if required
+            .filter(|(_, client)| !self.config.non_empty_only || !client.is_empty())
+
+            // skip the clients with an unlocked account if required
+            .filter(|(_, client)| !self.config.locked_only || client.locked)
+            .collect();
+        let mut rows: Vec<Vec<String>> = clients.iter()
+            .map(|(id, client)| self.row(id.to_string(), client.available, client.held, 
+                                         client.locked.to_string(), client.overdraft_limit, 
+                                         &quote_notes(client.last_notes().unwrap_or_default())))
+            .collect();
+
+        // add the totals over the displayed clients if required, with the number of locked
+        // accounts in the `locked` column
+        if self.config.totals_row {
+            let sum = |amount: fn(&Client) -> f64| 
+                clients.iter().map(|(_, client)| amount(client)).sum::<f64>() + 0.;
+            let n_locked = clients.iter().filter(|(_, client)| client.locked).count();
+            rows.push(self.row(TOTALS_ROW_LABEL.to_string(), sum(|client| client.available), 
+                               sum(|client| client.held), n_locked.to_string(), 
+                               sum(|client| client.overdraft_limit), ""));
+        }
+        rows
+    }
+
+    // the cells of a row, in the order of the displayed columns
+    fn row(&self, label: String, available: f64, held: f64, locked: String, 
+           overdraft_limit: f64, notes: &str) -> Vec<String> 
+    {
+        self.config.shown_columns().into_iter()
+            .map(|column| match column {
+                Column::Client => label.clone(),
+                Column::Available => self.format_amount(available),
+                Column::Held => self.format_amount(held),
+                Column::Total => self.format_amount(available + held),
+                Column::Locked => locked.clone(),
+                Column::OverdraftLimit => self.format_amount(overdraft_limit),
+                Column::AvailableIncludingOverdraft => 
+                    self.format_amount(available + overdraft_limit),
+                Column::Notes => notes.to_string(),
+            })
+            .collect()
+    }
+
+    // format an amount according to the configuration
+    //
+    // Except when showing the exact stored values, amounts within `[AMOUNT_EPSILON]` of zero,
+    // which may be residuals of floating-point arithmetic, are shown as `0` (which also avoids
+    // showing `-0`).
+    fn format_amount(&self, amount: f64) -> String {
+        let amount = match (self.config.decimal_places, self.config.amount_format) {
+            (_, AmountFormat::ExactValue) => amount,
+            _ if amount.abs() < AMOUNT_EPSILON => 0.,
+            (None, _) => amount,
+            (Some(places), _) => round_to(amount, places),
+        };
+        match self.config.amount_format {
+            AmountFormat::Shortest => amount.to_string(),
+            AmountFormat::Fixed(places) => format!("{:.*}", places as usize, amount + 0.),
+            AmountFormat::ExactValue => exact_value(amount),
+        }
+    }
+}
+
+impl<S: ClientStore + ?Sized> std::fmt::Display for ClientMapDisplay<'_, S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let rows = self.rows();
+        if !self.config.pretty {
+            if !self.config.omit_header { writeln!(f, "{}", self.config.header())?; }
+            for row in rows {
+                writeln!(f, "{}", row.join(", "))?;
+            }
+            return Ok(());
+        }
+
+        // pad each cell to the width of its column; the numbers are right-aligned, and the
+        // `locked` and `notes` columns left-aligned
+        let columns = self.config.shown_columns();
+        let header = columns.iter()
+            .map(|&column| self.config.column_label(column).to_string())
+            .collect::<Vec<_>>();
+        let header_row = if self.config.omit_header { None } else { Some(&header) };
+        let widths: Vec<usize> = (0..columns.len())
+            .map(|i| header_row.into_iter().chain(rows.iter()).map(|row| row[i].len()).max()
+                 .unwrap_or(0))
+            .collect();
+        for row in header_row.into_iter().chain(rows.iter()) {
+            let line = row.iter().zip(&widths).zip(columns.iter())
+                .map(|((cell, &width), column)| match column {
+                    Column::Locked | Column::Notes => format!("{:<width$}", cell),
+                    _ => format!("{:>width$}", cell),
+                })
+                .join("  ");
+            writeln!(f, "{}", line.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+
+// quote notes containing a comma or a double quote, doubling the latter, so that they form a
+// single CSV field
+fn quote_notes(notes: &str) -> String {
+    if notes.contains([',', '"']) {
+        format!("\"{}\"", notes.replace('"', "\"\""))
+    } else {
+        notes.to_string()
+    }
+}
+
+
+/// an error raised when a client is not found
+#[derive(Debug, Clone)]
+pub struct ClientNotFoundError(ClientId);
+
+// serialized as an object, rather than as the bare client ID
+#[cfg(feature = "serde")]
+impl serde::Serialize for ClientNotFoundError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut state = serializer.serialize_struct("ClientNotFoundError", 1)?;
+        state.serialize_field("client_id", &self.0)?;
+        state.end()
+    }
+}
+
+impl std::fmt::Display for ClientNotFoundError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Client {} not found", self.0.0)
+    }
+}
+
+impl std::error::Error for ClientNotFoundError {}
+
+
+/// an error raised when trying to do a transaction on a locked account
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct LockedAccountError {}
+
+impl std::fmt::Display for LockedAccountError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "The client account is locked")
+    }
+}
+
+impl std::error::Error for LockedAccountError {}
+
+
+/// an error raised when trying to do a transaction on a closed account
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ClosedAccountError {}
+
+impl std::fmt::Display for ClosedAccountError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "The client account is closed")
+    }
+}
+
+impl std::error::Error for ClosedAccountError {}
+
+
+/// an error raised when a deposit or withdrawal has the same ID as an earlier one of the same
+/// client, but a different type or amount
+#[derive(Debug, Clone)]
+pub struct ConflictingDuplicateError {
+    pub client_id: ClientId,
+    pub transaction_id: TransactionId,
+    pub original: Transaction,
+    pub duplicate: Transaction,
+}
+
+impl std::fmt::Display for ConflictingDuplicateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Transaction {} of client {} ({}) has the same ID as an earlier one with different data ({})", 
+               self.transaction_id.0, self.client_id, self.duplicate, self.original)
+    }
+}
+
+impl std::error::Error for ConflictingDuplicateError {}
+
+
+/// an error raised, if `[ProcessingConfig::error_on_resolve_after_chargeback]` is set, when a
+/// resolve refers to a transaction which has already been charged back
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ResolveAfterChargebackError {
+    pub client_id: ClientId,
+    pub transaction_id: TransactionId,
+}
+
+impl std::fmt::Display for ResolveAfterChargebackError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Resolve for transaction {} of client {}, which has already been charged back", 
+               self.transaction_id.0, self.client_id)
+    }
+}
+
+impl std::error::Error for ResolveAfterChargebackError {}
+
+
+// check if two deposits or withdrawals have the same type and amount (up to `AMOUNT_EPSILON`)
+fn same_transaction(a: &Transaction, b: &Transaction) -> bool {
+    match (a, b) {
+        (Transaction::Deposit(a), Transaction::Deposit(b)) 
+            | (Transaction::Withdrawal(a), Transaction::Withdrawal(b)) => amounts_equal(*a, *b),
+        _ => a == b
+    }
+}
+
+
+/// an error raised when two clients can not be merged, as they have a different transaction with
+/// the same ID which is disputed or charged back in each of them
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct MergeConflictError {
+    pub transaction_id: TransactionId,
+}
+
+impl std::fmt::Display for MergeConflictError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Transaction {} differs between the merged clients and is disputed or charged back in both", 
+               self.transaction_id.0)
+    }
+}
+
+impl std::error::Error for MergeConflictError {}
+
+
+/// an error raised when a dispute would bring the held funds of a client above the maximum
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct MaxHeldExceededError {
+    pub transaction_id: TransactionId,
+    pub max_held: f64,
+}
+
+impl std::fmt::Display for MaxHeldExceededError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Disputing transaction {} would bring the held funds above the maximum of {}", 
+               self.transaction_id.0, self.max_held)
+    }
+}
+
+impl std::error::Error for MaxHeldExceededError {}
+
+
+/// an error raised when a withdrawal would bring the cumulative withdrawals of a client above the
+/// limit
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct WithdrawalLimitError {
+    pub transaction_id: TransactionId,
+    pub withdrawal_limit: f64,
+}
+
+impl std::fmt::Display for WithdrawalLimitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Withdrawal {} would bring the cumulative withdrawals above the limit of {}", 
+               self.transaction_id.0, self.withdrawal_limit)
+    }
+}
+
+impl std::error::Error for WithdrawalLimitError {}
+
+
+/// an error raised when a withdrawal exceeds the available funds of a client (including the
+/// overdraft)
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct InsufficientFundsError {
+    pub client_id: ClientId,
+    pub amount: f64,
+    pub available: f64,
+}
+
+impl std::fmt::Display for InsufficientFundsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Withdrawal of {} exceeds the available funds of client {} ({})", 
+               self.amount, self.client_id, self.available)
+    }
+}
+
+impl std::error::Error for InsufficientFundsError {}
+
+
+// treat an `[InsufficientFundsError]` as a transaction without effect, and keep other errors
+fn ignore_insufficient_funds(error: Box<dyn std::error::Error>) 
+    -> Result<(), Box<dyn std::error::Error>> 
+{
+    if error.is::<InsufficientFundsError>() { Ok(()) } else { Err(error) }
+}
+
+
+/// an error raised when a deposit would bring the total funds of a client beyond
+/// `[MAX_SAFE_AMOUNT]`
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct UnsafeTotalError {
+    pub transaction_id: TransactionId,
+}
+
+impl std::fmt::Display for UnsafeTotalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Deposit {} would bring the total funds beyond 2^53, above which integer amounts lose precision", 
+               self.transaction_id.0)
+    }
+}
+
+impl std::error::Error for UnsafeTotalError {}
+
+
+/// an error raised when a deposit would bring the funds of a client beyond the largest
+/// representable amount
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct AmountOverflowError {
+    pub transaction_id: TransactionId,
+}
+
+impl std::fmt::Display for AmountOverflowError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Deposit {} would bring the funds beyond the largest representable amount", 
+               self.transaction_id.0)
+    }
+}
+
+impl std::error::Error for AmountOverflowError {}
+
+
+/// an error raised when an interest rate is negative or not finite
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct InvalidRateError(pub f64);
+
+impl std::fmt::Display for InvalidRateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Invalid interest rate {}: it must be non-negative and finite", self.0)
+    }
+}
+
+impl std::error::Error for InvalidRateError {}
+
+
+
+#[cfg(test)]
+mod tests {
+    
+    use super::*;
+    
+    #[test]
+    fn test_add_funds_1() {
+ 
+        // Our new client deposits 2_022 RustyDollars in their account.
+        let mut client = Client::new(2_022., 0., false);
+        
+        // Our client just remembered they own 100_000 RustyDollars worth of RSACoin, the latest
+        // craze among classical tech investors. Unfortunately, cryptographic functions based on RSA
+        // are not quantum secure, and they risk osing most of their investment as soon as a 
+        // powerful enough quantum computer is built. They thus decide to sell their RSACoins and 
+        // deposit the money in their account
+        client.add_to_available(100_000.);
+        
+        // check the client info
+        assert_eq!("102022, 0, 102022, false".to_string(), format!("{}", client));
+    }
+
+    #[test]
+    fn test_lock_1() {
+ 
+        // Our new client deposits 9e99 RustyDollars in their account.
+        let mut client = Client::new(9e+99_f64, 0., false);
+        
+        // Wait a minute... This is more than the number of atoms in the known universe—no one can
+        // be quite rich enough to have that many RustyDolars! Surely there is something frudulent
+        // here. Let's lock the account and investigate!
+        client.lock();
+    
+        // check the client info
+        assert_eq!(format!("{}, 0, {}, true", 9e+99_f64, 9e+99_f64), format!("{}", client));
+    }
+
+    #[test]
+    fn test_move_to_held_1() {
+ 
+        // Our new client deposits 2_023 RustyDollars in their account.
+        let mut client = Client::new(2_023., 0., false);
+       
+        // Our UberTransactionChecker™ system, using the latest Fourier Transformer Networks, has
+        // detected a possible error: depositing 2,023 RustyDollars now sounds one year early! We
+        // pre-emptively correct this likely error by moving 1 RustyDollar from the available funds 
+        // to the held ones, and make a note to contact the client to enquire about this.
+        client.move_to_held(1.);
+       
+        // check the client info
+        assert_eq!("2022, 1, 2023, false".to_string(), format!("{}", client));
+    }
+
+    #[test]
+    fn add_to_history() {
+
+        // Our new client deposits 2_022 RustyDollars in their account.
+        let mut client = Client::new(2_022., 0., false);
+        
         // Let us add this first transaction to their history, with the ID 1
         client.add_to_history(TransactionId(1), Transaction::Deposit(2_022.));
     }
 
     #[test]
-    fn test_get() {
-        // define a new empty ClientMap
+    fn test_get() {
+        // define a new empty ClientMap
+        let mut clients_map = ClientMap::default();
+       
+        // Our first client has just opened an account! 
+        // Let's give them the index ID.
+        let client_id = ClientId(1);
+       
+        // Our first client deposits 100_000 RustyDollars in their account.
+        let client = Client::new(100_000., 0., false);
+       
+        // add the client to the map
+        clients_map.insert(client_id, client).unwrap();
+        
+        // get a reference to our client
+        let opt_ref_to_client = clients_map.get(&ClientId(1));
+       
+        // check that the result is not None
+        if let Some(ref_to_client) = opt_ref_to_client {
+            
+            // check the client info
+            assert_eq!("100000, 0, 100000, false".to_string(), format!("{}", ref_to_client));
+        
+        } else {
+            panic!("Could not find our client");
+        };
+       
+        // try to get a reference to a client which does not exist 
+        if clients_map.get(&ClientId(2)).is_some() {
+            panic!("Found a client which does not exist");
+        }
+    }
+
+    #[test]
+    fn test_get_mut_1() {
+    
+        // define a new empty ClientMap
+        let mut clients_map = ClientMap::default();
+        
+        // Our first client has just opened an account! 
+        // Let's give them the index ID.
+        let client_id = ClientId(1);
+        
+        // Our first client deposits 100_000 RustyDollars in their account.
+        let client = Client::new(100_000., 0., false);
+        
+        // add the client to the map
+        clients_map.insert(client_id, client).unwrap();
+        
+        // get a reference to our client
+        let opt_mut_ref_to_client = clients_map.get_mut(&ClientId(1));
+        
+        // check that the result is not None
+        if let Some(mut_ref_to_client) = opt_mut_ref_to_client {
+            
+            // as a welcome gift, let's give away 100 RustyDollars to our client!
+            mut_ref_to_client.add_to_available(100.);
+        
+            // check the client info
+            assert_eq!("100100, 0, 100100, false".to_string(), format!("{}", mut_ref_to_client));
+        
+        } else {
+            panic!("Could not find our client");
+        };
+        
+        // try to get a reference to a client which does not exist 
+        if clients_map.get_mut(&ClientId(2)).is_some() {
+            panic!("Found a client which does not exist");
+        }
+    }
+    
+    #[test]
+    fn deposit_1() {
+
+        // Create an empty ClientMap
+        let mut clients_map = ClientMap::default();
+
+        // Add a new client with an empty account and ID 1
+        clients_map.insert(ClientId(1), Client::new(0., 0., false)).unwrap();
+        
+        // Execute a transaction: deposit
+        clients_map.execute_transaction(TransactionId(1), ClientId(1), 
+                                        Transaction::Deposit(2_022.),
+                                        &ProcessingConfig::default()).unwrap();
+
+        // check the client info
+        if let Some(ref_to_client) = clients_map.get(&ClientId(1)) {
+            assert_eq!("2022, 0, 2022, false".to_string(), 
+                       format!("{}", ref_to_client));
+        } else {
+            panic!("Client not found!");
+        }
+    }
+    
+    #[test]
+    fn withdrawal_1() {
+
+        // Create an empty ClientMap
+        let mut clients_map = ClientMap::default();
+
+        // Add a new client with an empty account and ID 1
+        clients_map.insert(ClientId(1), Client::new(0., 0., false)).unwrap();
+        
+        // Execute a transaction: deposit
+        clients_map.execute_transaction(TransactionId(1), ClientId(1), 
+                                        Transaction::Deposit(12_022.),
+                                        &ProcessingConfig::default()).unwrap();
+        
+        // Execute a transaction: withdrawal
+        clients_map.execute_transaction(TransactionId(2), ClientId(1), 
+                                        Transaction::Withdrawal(2_022.),
+                                        &ProcessingConfig::default()).unwrap();
+
+        // check the client info
+        if let Some(ref_to_client) = clients_map.get(&ClientId(1)) {
+            assert_eq!("10000, 0, 10000, false".to_string(), 
+                       format!("{}", ref_to_client));
+        } else {
+            panic!("Client not found!");
+        }
+    }
+    
+    #[test]
+    fn withdrawal_2() {
+
+        // Create an empty ClientMap
+        let mut clients_map = ClientMap::default();
+
+        // Add a new client with an empty account and ID 1
+        clients_map.insert(ClientId(1), Client::new(0., 0., false)).unwrap();
+        
+        // Execute a transaction: deposit
+        clients_map.execute_transaction(TransactionId(1), ClientId(1), 
+                                        Transaction::Deposit(2_022.),
+                                        &ProcessingConfig::default()).unwrap();
+        
+        // Try to withdraw more funds than the client has available
+        clients_map.execute_transaction(TransactionId(2), ClientId(1), 
+                                        Transaction::Withdrawal(10_000.),
+                                        &ProcessingConfig::default()).unwrap_err();
+
+        // check the client info
+        if let Some(ref_to_client) = clients_map.get(&ClientId(1)) {
+            assert_eq!("2022, 0, 2022, false".to_string(), 
+                       format!("{}", ref_to_client));
+        } else {
+            panic!("Client not found!");
+        }
+    }
+    
+    #[test]
+    fn dispute_1() {
+
+        // Create an empty ClientMap
+        let mut clients_map = ClientMap::default();
+
+        // Add a new client with an empty account and ID 1
+        clients_map.insert(ClientId(1), Client::new(0., 0., false)).unwrap();
+        
+        // Execute a transaction: deposit
+        clients_map.execute_transaction(TransactionId(1), ClientId(1), 
+                                        Transaction::Deposit(10_000.),
+                                        &ProcessingConfig::default()).unwrap();
+        
+        // Dispute the transaction
+        clients_map.execute_transaction(TransactionId::default(), ClientId(1), 
+                                        Transaction::Dispute(TransactionId(1)),
+                                        &ProcessingConfig::default()).unwrap();
+
+        // check the client info
+        if let Some(ref_to_client) = clients_map.get(&ClientId(1)) {
+            assert_eq!("0, 10000, 10000, false".to_string(), 
+                       format!("{}", ref_to_client));
+        } else {
+            panic!("Client not found!");
+        }
+    }
+    
+    #[test]
+    // disputing a non-existent transaction should not change the client information
+    fn dispute_2() {
+
+        // Create an empty ClientMap
+        let mut clients_map = ClientMap::default();
+
+        // Add a new client with an empty account and ID 1
+        clients_map.insert(ClientId(1), Client::new(0., 0., false)).unwrap();
+        
+        // Execute a transaction: deposit
+        clients_map.execute_transaction(TransactionId(1), ClientId(1), 
+                                        Transaction::Deposit(10_000.),
+                                        &ProcessingConfig::default()).unwrap();
+        
+        // Dispute the transaction
+        clients_map.execute_transaction(TransactionId::default(), ClientId(1), 
+                                        Transaction::Dispute(TransactionId(2)),
+                                        &ProcessingConfig::default()).unwrap();
+
+        // check the client info
+        if let Some(ref_to_client) = clients_map.get(&ClientId(1)) {
+            assert_eq!("10000, 0, 10000, false".to_string(), 
+                       format!("{}", ref_to_client));
+        } else {
+            panic!("Client not found!");
+        }
+    }
+    
+    #[test]
+    fn resolve_1() {
+
+        // Create an empty ClientMap
         let mut clients_map = ClientMap::default();
-       
-        // Our first client has just opened an account! 
-        // Let's give them the index ID.
-        let client_id = ClientId(1);
-       
-        // Our first client deposits 100_000 RustyDollars in their account.
-        let client = Client::new(100_000., 0., false);
-       
-        // add the client to the map
-        clients_map.insert(client_id, client).unwrap();
+
+        // Add a new client with an empty account and ID 1
+        clients_map.insert(ClientId(1), Client::new(0., 0., false)).unwrap();
         
-        // get a reference to our client
-        let opt_ref_to_client = clients_map.get(&ClientId(1));
-       
-        // check that the result is not None
-        if let Some(ref_to_client) = opt_ref_to_client {
-            
-            // check the client info
-            assert_eq!("100000, 0, 100000, false".to_string(), format!("{}", ref_to_client));
+        // Execute a transaction: deposit
+        clients_map.execute_transaction(TransactionId(1), ClientId(1), 
+                                        Transaction::Deposit(10_000.),
+                                        &ProcessingConfig::default()).unwrap();
+        
+        // Dispute the transaction
+        clients_map.execute_transaction(TransactionId::default(), ClientId(1), 
+                                        Transaction::Dispute(TransactionId(1)),
+                                        &ProcessingConfig::default()).unwrap();
+        
+        // Resolve the transaction
+        clients_map.execute_transaction(TransactionId::default(), ClientId(1), 
+                                        Transaction::Resolve(TransactionId(1)),
+                                        &ProcessingConfig::default()).unwrap();
+
+        // check the client info
+        if let Some(ref_to_client) = clients_map.get(&ClientId(1)) {
+            assert_eq!("10000, 0, 10000, false".to_string(), 
+                       format!("{}", ref_to_client));
+        } else {
+            panic!("Client not found!");
+        }
+    }
+    
+    #[test]
+    // resolving a transaction which is not disputed should not change the client info
+    fn resolve_2() {
+
+        // Create an empty ClientMap
+        let mut clients_map = ClientMap::default();
+
+        // Add a new client with an empty account and ID 1
+        clients_map.insert(ClientId(1), Client::new(0., 0., false)).unwrap();
+        
+        // Execute a transaction: deposit
+        clients_map.execute_transaction(TransactionId(1), ClientId(1), 
+                                        Transaction::Deposit(10_000.),
+                                        &ProcessingConfig::default()).unwrap();
+        
+        // Execute a transaction: deposit
+        clients_map.execute_transaction(TransactionId(2), ClientId(1), 
+                                        Transaction::Deposit(5_000.),
+                                        &ProcessingConfig::default()).unwrap();
+        
+        // Dispute the first transaction
+        clients_map.execute_transaction(TransactionId::default(), ClientId(1), 
+                                        Transaction::Dispute(TransactionId(1)),
+                                        &ProcessingConfig::default()).unwrap();
+        
+        // Resolve the second transaction
+        clients_map.execute_transaction(TransactionId::default(), ClientId(1), 
+                                        Transaction::Resolve(TransactionId(2)),
+                                        &ProcessingConfig::default()).unwrap();
+
+        // check the client info
+        if let Some(ref_to_client) = clients_map.get(&ClientId(1)) {
+            assert_eq!("5000, 10000, 15000, false".to_string(), 
+                       format!("{}", ref_to_client));
+        } else {
+            panic!("Client not found!");
+        }
+    }
+    
+    #[test]
+    fn chargeback_1() {
+
+        // Create an empty ClientMap
+        let mut clients_map = ClientMap::default();
+
+        // Add a new client with an empty account and ID 1
+        clients_map.insert(ClientId(1), Client::new(0., 0., false)).unwrap();
+        
+        // Execute a transaction: deposit
+        clients_map.execute_transaction(TransactionId(1), ClientId(1), 
+                                        Transaction::Deposit(10_000.),
+                                        &ProcessingConfig::default()).unwrap();
+        
+        // Dispute the transaction
+        clients_map.execute_transaction(TransactionId::default(), ClientId(1), 
+                                        Transaction::Dispute(TransactionId(1)),
+                                        &ProcessingConfig::default()).unwrap();
+        
+        // Chargeback
+        clients_map.execute_transaction(TransactionId::default(), ClientId(1), 
+                                        Transaction::Chargeback(TransactionId(1)),
+                                        &ProcessingConfig::default()).unwrap();
+
+        // check the client info
+        if let Some(ref_to_client) = clients_map.get(&ClientId(1)) {
+            assert_eq!("0, 0, 0, true".to_string(), 
+                       format!("{}", ref_to_client));
+        } else {
+            panic!("Client not found!");
+        }
+    }
+    
+    #[test]
+    // chargeback on a transaction which is not disputed should not change the client info
+    fn chargeback_2() {
+
+        // Create an empty ClientMap
+        let mut clients_map = ClientMap::default();
+
+        // Add a new client with an empty account and ID 1
+        clients_map.insert(ClientId(1), Client::new(0., 0., false)).unwrap();
+        
+        // Execute a transaction: deposit
+        clients_map.execute_transaction(TransactionId(1), ClientId(1), 
+                                        Transaction::Deposit(10_000.),
+                                        &ProcessingConfig::default()).unwrap();
+        
+        // Execute a transaction: deposit
+        clients_map.execute_transaction(TransactionId(2), ClientId(1), 
+                                        Transaction::Deposit(5_000.),
+                                        &ProcessingConfig::default()).unwrap();
+        
+        // Dispute the first transaction
+        clients_map.execute_transaction(TransactionId::default(), ClientId(1), 
+                                        Transaction::Dispute(TransactionId(1)),
+                                        &ProcessingConfig::default()).unwrap();
+        
+        // Resolve the second transaction
+        clients_map.execute_transaction(TransactionId::default(), ClientId(1), 
+                                        Transaction::Chargeback(TransactionId(2)),
+                                        &ProcessingConfig::default()).unwrap();
+
+        // check the client info
+        if let Some(ref_to_client) = clients_map.get(&ClientId(1)) {
+            assert_eq!("5000, 10000, 15000, false".to_string(), 
+                       format!("{}", ref_to_client));
+        } else {
+            panic!("Client not found!");
+        }
+    }
+    
+    #[test]
+    // clients with an empty, unlocked account are omitted when displaying only non-empty clients
+    fn display_non_empty_1() {
+
+        // Create an empty ClientMap
+        let mut clients_map = ClientMap::default();
+
+        // Add three clients: one with funds, one with an empty account, and one with a locked
+        // empty account
+        clients_map.insert(ClientId(1), Client::new(100., 0., false)).unwrap();
+        clients_map.insert(ClientId(2), Client::new(0., 0., false)).unwrap();
+        clients_map.insert(ClientId(3), Client::new(0., 0., true)).unwrap();
+
+        // all clients are shown by default
+        assert_eq!("client, available, held, total, locked\n\
+                    1, 100, 0, 100, false\n\
+                    2, 0, 0, 0, false\n\
+                    3, 0, 0, 0, true\n", 
+                   format!("{}", clients_map));
+
+        // the second client is omitted when only non-empty clients are shown
+        let config = OutputConfig { non_empty_only: true, ..Default::default() };
+        assert_eq!("client, available, held, total, locked\n\
+                    1, 100, 0, 100, false\n\
+                    3, 0, 0, 0, true\n", 
+                   format!("{}", clients_map.display(&config)));
+    }
+    
+    #[test]
+    // no transaction can be executed on a closed account until it is opened again
+    fn close_1() {
+
+        // Create an empty ClientMap
+        let mut clients_map = ClientMap::default();
+
+        // Add a new client with an empty account and ID 1
+        clients_map.insert(ClientId(1), Client::new(0., 0., false)).unwrap();
+        
+        // Execute a transaction: deposit
+        clients_map.execute_transaction(TransactionId(1), ClientId(1), 
+                                        Transaction::Deposit(100.),
+                                        &ProcessingConfig::default()).unwrap();
+        
+        // Close the account
+        clients_map.execute_transaction(TransactionId::default(), ClientId(1), 
+                                        Transaction::Close,
+                                        &ProcessingConfig::default()).unwrap();
+        
+        // Deposits are now rejected
+        assert!(clients_map.execute_transaction(TransactionId(2), ClientId(1), 
+                                                Transaction::Deposit(100.),
+                                                &ProcessingConfig::default()).is_err());
+        
+        // Re-open the account and try again
+        clients_map.execute_transaction(TransactionId::default(), ClientId(1), 
+                                        Transaction::Open,
+                                        &ProcessingConfig::default()).unwrap();
+        clients_map.execute_transaction(TransactionId(2), ClientId(1), 
+                                        Transaction::Deposit(100.),
+                                        &ProcessingConfig::default()).unwrap();
+
+        // check the client info
+        if let Some(ref_to_client) = clients_map.get(&ClientId(1)) {
+            assert_eq!("200, 0, 200, false".to_string(), 
+                       format!("{}", ref_to_client));
+        } else {
+            panic!("Client not found!");
+        }
+    }
+    
+    #[test]
+    // merging two maps with an overlapping client
+    fn merge_1() {
+
+        // a first map with two clients, one of them with a disputed deposit
+        let mut clients_map = ClientMap::default();
+        clients_map.insert(ClientId(1), Client::default()).unwrap();
+        clients_map.insert(ClientId(7), Client::default()).unwrap();
+        clients_map.execute_transaction(TransactionId(1), ClientId(1), 
+                                        Transaction::Deposit(100.),
+                                        &ProcessingConfig::default()).unwrap();
+        clients_map.execute_transaction(TransactionId(2), ClientId(7), 
+                                        Transaction::Deposit(50.),
+                                        &ProcessingConfig::default()).unwrap();
+        clients_map.execute_transaction(TransactionId::default(), ClientId(7), 
+                                        Transaction::Dispute(TransactionId(2)),
+                                        &ProcessingConfig::default()).unwrap();
+
+        // a second map with the same client 7 and another one
+        let mut other_clients_map = ClientMap::default();
+        other_clients_map.insert(ClientId(7), Client::default()).unwrap();
+        other_clients_map.insert(ClientId(3), Client::default()).unwrap();
+        other_clients_map.execute_transaction(TransactionId(3), ClientId(7), 
+                                              Transaction::Deposit(20.),
+                                              &ProcessingConfig::default()).unwrap();
+        other_clients_map.execute_transaction(TransactionId(4), ClientId(3), 
+                                              Transaction::Deposit(5.),
+                                              &ProcessingConfig::default()).unwrap();
+
+        // merge the maps
+        clients_map.merge(other_clients_map).unwrap();
+        assert_eq!("client, available, held, total, locked\n\
+                    1, 100, 0, 100, false\n\
+                    3, 5, 0, 5, false\n\
+                    7, 20, 50, 70, false\n", 
+                   format!("{}", clients_map));
+
+        // both transactions of client 7 are in the merged history: the first one can be
+        // resolved and the second one disputed
+        clients_map.execute_transaction(TransactionId::default(), ClientId(7), 
+                                        Transaction::Resolve(TransactionId(2)),
+                                        &ProcessingConfig::default()).unwrap();
+        clients_map.execute_transaction(TransactionId::default(), ClientId(7), 
+                                        Transaction::Dispute(TransactionId(3)),
+                                        &ProcessingConfig::default()).unwrap();
+        if let Some(ref_to_client) = clients_map.get(&ClientId(7)) {
+            assert_eq!("50, 20, 70, false".to_string(), 
+                       format!("{}", ref_to_client));
+        } else {
+            panic!("Client not found!");
+        }
+    }
+    
+    #[test]
+    // merging two clients with different transactions with the same ID keeps the disputed one,
+    // so that resolving it releases the funds actually held
+    fn merge_2() {
+        let config = ProcessingConfig::default();
+        let mut client = Client::new(0., 0., false);
+        client.apply(TransactionId(1), &Transaction::Deposit(100.), &config);
+        let mut other_client = Client::new(0., 0., false);
+        other_client.apply(TransactionId(1), &Transaction::Deposit(50.), &config);
+        other_client.apply(TransactionId::default(), &Transaction::Dispute(TransactionId(1)), 
+                           &config);
+        client.merge_from(other_client).unwrap();
+        assert_eq!(Some(&Transaction::Deposit(50.)), client.history.get(&TransactionId(1)));
+        assert!(client.is_disputed(&TransactionId(1)));
+        assert_eq!("100, 50, 150, false".to_string(), format!("{}", client));
+        client.apply(TransactionId::default(), &Transaction::Resolve(TransactionId(1)), &config);
+        assert_eq!("150, 0, 150, false".to_string(), format!("{}", client));
+
+        // the transaction of the first client is kept if neither is disputed
+        let mut client = Client::new(0., 0., false);
+        client.apply(TransactionId(1), &Transaction::Deposit(100.), &config);
+        let mut other_client = Client::new(0., 0., false);
+        other_client.apply(TransactionId(1), &Transaction::Deposit(50.), &config);
+        client.merge_from(other_client).unwrap();
+        assert_eq!(Some(&Transaction::Deposit(100.)), client.history.get(&TransactionId(1)));
+    }
+
+    #[test]
+    // clients with different transactions with the same ID, disputed in both, can not be merged
+    fn merge_3() {
+        let config = ProcessingConfig::default();
+        let mut clients_map = ClientMap::default();
+        for (client_id, amount) in [(1, 100.), (2, 50.)] {
+            clients_map.insert(ClientId(client_id), Client::new(0., 0., false)).unwrap();
+            for transaction in [Transaction::Deposit(amount), 
+                                Transaction::Dispute(TransactionId(1))] {
+                clients_map.execute_transaction(TransactionId(1), ClientId(client_id),
+                                                transaction, &config).unwrap();
+            }
+        }
+        let error = clients_map.merge_clients(ClientId(1), ClientId(2)).unwrap_err();
+        let error = error.downcast_ref::<MergeConflictError>().unwrap();
+        assert_eq!(TransactionId(1), error.transaction_id);
+        assert_eq!(2, clients_map.len());
+        assert_eq!("0, 100, 100, false", format!("{}", clients_map.get(&ClientId(1)).unwrap()));
+    }
+    
+    #[test]
+    fn is_consistent_1() {
+
+        // a client with finite, non-negative amounts is consistent
+        let mut client = Client::new(100., 10., false);
+        assert!(client.is_consistent());
+
+        // rounding errors slightly below zero are tolerated
+        client.held = -1e-12;
+        assert!(client.is_consistent());
+
+        // negative or infinite amounts are not
+        client.held = -1.;
+        assert!(!client.is_consistent());
+        client.held = 0.;
+        client.available = f64::INFINITY;
+        assert!(!client.is_consistent());
+    }
+    
+    #[test]
+    // the output is sorted by client ID, whatever the insertion order
+    fn display_order_1() {
+        let ids = [10, 2, 300, 1, 25, 7, 65535, 0];
+
+        // insert the clients in two maps, in opposite orders
+        let mut clients_map = ClientMap::default();
+        let mut other_clients_map = ClientMap::default();
+        for (i, &id) in ids.iter().enumerate() {
+            clients_map.insert(ClientId(id), Client::new(id as f64, 0., false)).unwrap();
+            let other_id = ids[ids.len() - 1 - i];
+            other_clients_map.insert(ClientId(other_id), 
+                                     Client::new(other_id as f64, 0., false)).unwrap();
+        }
+
+        // the outputs are identical, and sorted numerically
+        let output = format!("{}", clients_map);
+        assert_eq!(output, format!("{}", other_clients_map));
+        let displayed_ids: Vec<u16> = output.lines().skip(1)
+            .map(|line| line.split(',').next().unwrap().parse().unwrap())
+            .collect();
+        assert_eq!(vec![0, 1, 2, 7, 10, 25, 300, 65535], displayed_ids);
+    }
+    
+    #[test]
+    // withdrawals can use the overdraft, which is shown in the verbose output only
+    fn overdraft_1() {
+
+        // Create an empty ClientMap
+        let mut clients_map = ClientMap::default();
+
+        // Add a client with 100 RustyDollars and an overdraft limit of 50
+        clients_map.insert(ClientId(1), Client::new(100., 0., false)).unwrap();
+        clients_map.set_overdraft_limit(ClientId(1), 50.).unwrap();
+        
+        // Withdraw 120 RustyDollars: this is within the overdraft
+        clients_map.execute_transaction(TransactionId(1), ClientId(1), 
+                                        Transaction::Withdrawal(120.),
+                                        &ProcessingConfig::default()).unwrap();
         
+        // Withdraw 40 RustyDollars more: this would exceed the overdraft limit
+        clients_map.execute_transaction(TransactionId(2), ClientId(1), 
+                                        Transaction::Withdrawal(40.),
+                                        &ProcessingConfig::default()).unwrap_err();
+
+        // check the client info
+        if let Some(ref_to_client) = clients_map.get(&ClientId(1)) {
+            assert_eq!(-20., ref_to_client.available());
+            assert_eq!(50., ref_to_client.overdraft_limit());
+            assert_eq!(30., ref_to_client.available_including_overdraft());
         } else {
-            panic!("Could not find our client");
-        };
-       
-        // try to get a reference to a client which does not exist 
-        if let Some(_) = clients_map.get(&ClientId(2)) {
-            panic!("Found a client which does not exist");
+            panic!("Client not found!");
         }
+
+        // the default output is unchanged
+        assert_eq!("client, available, held, total, locked\n\
+                    1, -20, 0, -20, false\n", 
+                   format!("{}", clients_map));
+
+        // the verbose one shows the overdraft
+        let config = OutputConfig { verbose: true, ..Default::default() };
+        assert_eq!("client, available, held, total, locked, overdraft_limit, available_including_overdraft\n\
+                    1, -20, 0, -20, false, 50, 30\n", 
+                   format!("{}", clients_map.display(&config)));
     }
+    
+    #[test]
+    // the same sequence of transactions with the two dispute fund modes
+    fn dispute_fund_mode_1() {
+        for (mode, after_dispute) in [(DisputeFundMode::MoveToHeld, "50, 100, 150, false"), 
+                                      (DisputeFundMode::FlagOnly, "150, 0, 150, false")] {
+
+            let config = ProcessingConfig { dispute_fund_mode: mode, ..Default::default() };
+            let mut clients_map = ClientMap::default();
+            clients_map.insert(ClientId(1), Client::new(0., 0., false)).unwrap();
+            let transactions = [(TransactionId(1), Transaction::Deposit(100.)),
+                                (TransactionId(2), Transaction::Deposit(50.)),
+                                (TransactionId::default(), Transaction::Dispute(TransactionId(1))),
+                                (TransactionId::default(), Transaction::Resolve(TransactionId(1))),
+                                (TransactionId::default(), Transaction::Dispute(TransactionId(1)))];
+            for (transaction_id, transaction) in transactions {
+                clients_map.execute_transaction(transaction_id, ClientId(1), transaction, 
+                                                &config).unwrap();
+            }
+            
+            // the disputed funds are held only when moved
+            assert_eq!(after_dispute, format!("{}", clients_map.get(&ClientId(1)).unwrap()));
 
+            // in both cases, a chargeback removes them and locks the account
+            clients_map.execute_transaction(TransactionId::default(), ClientId(1), 
+                                            Transaction::Chargeback(TransactionId(1)),
+                                            &config).unwrap();
+            assert_eq!("50, 0, 50, true", format!("{}", clients_map.get(&ClientId(1)).unwrap()));
+        }
+    }
+    
     #[test]
-    fn test_get_mut_1() {
+    // the header constants match the column names and the output
+    fn csv_header_1() {
+        assert_eq!("client, available, held, total, locked", CSV_HEADER);
+        assert_eq!(CSV_HEADER, COLUMNS.join(", "));
+        assert_eq!(VERBOSE_CSV_HEADER, VERBOSE_COLUMNS.join(", "));
+        
+        let mut clients_map = ClientMap::default();
+        clients_map.insert(ClientId(1), Client::new(10., 0., false)).unwrap();
+        assert!(format!("{}", clients_map).starts_with(&format!("{}\n", CSV_HEADER)));
+        let config = OutputConfig { verbose: true, ..Default::default() };
+        assert!(format!("{}", clients_map.display(&config))
+                .starts_with(&format!("{}\n", VERBOSE_CSV_HEADER)));
+    }
     
-        // define a new empty ClientMap
+    #[test]
+    // evicting the history keeps the disputed transactions
+    fn evict_history_1() {
+
+        // without a capacity, the order in which the transactions are used is not tracked
+        let mut clients_map = ClientMap::default();
+        clients_map.insert(ClientId(1), Client::new(0., 0., false)).unwrap();
+        clients_map.execute_transaction(TransactionId(1), ClientId(1), Transaction::Deposit(10.),
+                                        &ProcessingConfig::default()).unwrap();
+        assert_eq!(0, clients_map.get(&ClientId(1)).unwrap().history_order.len());
+
+        // keep two undisputed transactions (4 and 5) on top of the disputed one
+        let config = ProcessingConfig { history_capacity: Some(2), ..Default::default() };
         let mut clients_map = ClientMap::default();
+        clients_map.insert(ClientId(1), Client::new(0., 0., false)).unwrap();
+        clients_map.execute_transaction(TransactionId(1), ClientId(1), Transaction::Deposit(10.),
+                                        &config).unwrap();
+        clients_map.execute_transaction(TransactionId::default(), ClientId(1), 
+                                        Transaction::Dispute(TransactionId(1)), &config).unwrap();
+        for id in 2..=5 {
+            clients_map.execute_transaction(TransactionId(id), ClientId(1), 
+                                            Transaction::Deposit(10.), &config).unwrap();
+        }
+        let client = clients_map.get(&ClientId(1)).unwrap();
+        assert!(client.is_under_dispute(&TransactionId(1)));
+        assert!(!client.is_disputable(&TransactionId(2)));
+        assert!(!client.is_disputable(&TransactionId(3)));
+        assert!(client.is_disputable(&TransactionId(4)));
+        assert!(client.is_disputable(&TransactionId(5)));
         
-        // Our first client has just opened an account! 
-        // Let's give them the index ID.
-        let client_id = ClientId(1);
+        // once resolved, the disputed transaction is the most recently used one
+        clients_map.execute_transaction(TransactionId::default(), ClientId(1), 
+                                        Transaction::Resolve(TransactionId(1)), &config).unwrap();
+        let client = clients_map.get(&ClientId(1)).unwrap();
+        assert!(client.is_disputable(&TransactionId(1)));
+        assert!(!client.is_disputable(&TransactionId(4)));
+        assert!(client.is_disputable(&TransactionId(5)));
+        assert_eq!("50, 0, 50, false", format!("{}", client));
+    }
+    
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serialize_errors() {
+        assert_eq!(r#"{"client_id":7}"#, 
+                   serde_json::to_string(&ClientNotFoundError(ClientId(7))).unwrap());
+        assert_eq!("{}", serde_json::to_string(&LockedAccountError {}).unwrap());
+        assert_eq!("{}", serde_json::to_string(&ClosedAccountError {}).unwrap());
+    }
+    
+    #[test]
+    // rounding each amount when applied or only the output gives different totals
+    fn round_amounts_1() {
+        let deposits = |config: &ProcessingConfig| {
+            let mut clients_map = ClientMap::default();
+            clients_map.insert(ClientId(1), Client::new(0., 0., false)).unwrap();
+            for id in 1..=3 {
+                clients_map.execute_transaction(TransactionId(id), ClientId(1), 
+                                                Transaction::Deposit(0.00004), config).unwrap();
+            }
+            clients_map
+        };
         
-        // Our first client deposits 100_000 RustyDollars in their account.
-        let client = Client::new(100_000., 0., false);
+        // each deposit is rounded to 0
+        let config = ProcessingConfig { round_amounts: Some(4), ..Default::default() };
+        assert_eq!("client, available, held, total, locked\n1, 0, 0, 0, false\n", 
+                   format!("{}", deposits(&config)));
         
-        // add the client to the map
-        clients_map.insert(client_id, client).unwrap();
+        // the total of 0.00012 is rounded to 0.0001
+        let output_config = OutputConfig { decimal_places: Some(4), ..Default::default() };
+        assert_eq!("client, available, held, total, locked\n1, 0.0001, 0, 0.0001, false\n", 
+                   format!("{}", deposits(&ProcessingConfig::default()).display(&output_config)));
+    }
+    
+    #[test]
+    // a resolve after a chargeback is ignored
+    fn resolve_after_chargeback_1() {
+
+        let mut clients_map = ClientMap::default();
+        clients_map.insert(ClientId(1), Client::new(0., 0., false)).unwrap();
+        let config = ProcessingConfig::default();
+        let transactions = [(TransactionId(1), Transaction::Deposit(100.)),
+                            (TransactionId(2), Transaction::Deposit(50.)),
+                            (TransactionId::default(), Transaction::Dispute(TransactionId(1))),
+                            (TransactionId::default(), Transaction::Chargeback(TransactionId(1))),
+                            (TransactionId::default(), Transaction::Resolve(TransactionId(1)))];
+        for (transaction_id, transaction) in transactions {
+            clients_map.execute_transaction(transaction_id, ClientId(1), transaction, &config)
+                .unwrap();
+        }
+        let client = clients_map.get(&ClientId(1)).unwrap();
+        assert!(client.charged_back.contains(&TransactionId(1)));
+        assert_eq!("50, 0, 50, true", format!("{}", client));
         
-        // get a reference to our client
-        let opt_mut_ref_to_client = clients_map.get_mut(&ClientId(1));
+        // even once the account is unlocked
+        clients_map.unlock(ClientId(1), &config).unwrap();
+        clients_map.execute_transaction(TransactionId::default(), ClientId(1), 
+                                        Transaction::Resolve(TransactionId(1)), &config).unwrap();
+        assert_eq!("50, 0, 50, false", format!("{}", clients_map.get(&ClientId(1)).unwrap()));
+    }
+    
+    #[test]
+    // list and display the locked accounts
+    fn locked_clients_1() {
+        let mut clients_map = ClientMap::default();
+        clients_map.insert(ClientId(3), Client::new(10., 0., true)).unwrap();
+        clients_map.insert(ClientId(1), Client::new(20., 0., false)).unwrap();
+        clients_map.insert(ClientId(2), Client::new(0., 0., true)).unwrap();
+        clients_map.insert(ClientId(4), Client::new(0., 5., false)).unwrap();
+        assert_eq!(vec![ClientId(2), ClientId(3)], clients_map.locked_clients());
         
-        // check that the result is not None
-        if let Some(mut_ref_to_client) = opt_mut_ref_to_client {
-            
-            // as a welcome gift, let's give away 100 RustyDollars to our client!
-            mut_ref_to_client.add_to_available(100.);
+        let config = OutputConfig { locked_only: true, ..Default::default() };
+        assert_eq!("client, available, held, total, locked\n\
+                    2, 0, 0, 0, true\n\
+                    3, 10, 0, 10, true\n", 
+                   format!("{}", clients_map.display(&config)));
+    }
+    
+    #[test]
+    // disputes, resolves, and chargebacks (which have the default transaction ID) do not replace
+    // a deposit with the same ID in the history
+    fn dispute_default_id_1() {
+
+        let mut clients_map = ClientMap::default();
+        clients_map.insert(ClientId(1), Client::new(0., 0., false)).unwrap();
+        let config = ProcessingConfig::default();
+        let transactions = [(TransactionId::default(), Transaction::Deposit(100.)),
+                            (TransactionId::default(), Transaction::Dispute(TransactionId(0))),
+                            (TransactionId::default(), Transaction::Resolve(TransactionId(0)))];
+        for (transaction_id, transaction) in transactions {
+            clients_map.execute_transaction(transaction_id, ClientId(1), transaction, &config)
+                .unwrap();
+        }
         
-            // check the client info
-            assert_eq!("100100, 0, 100100, false".to_string(), format!("{}", mut_ref_to_client));
+        // the resolve targets the deposit, and its funds are available again
+        let client = clients_map.get(&ClientId(1)).unwrap();
+        assert_eq!("100, 0, 100, false", format!("{}", client));
+        assert_eq!(Some(&Transaction::Deposit(100.)), client.history.get(&TransactionId(0)));
         
-        } else {
-            panic!("Could not find our client");
-        };
+        // the deposit can be disputed and charged back
+        for transaction in [Transaction::Dispute(TransactionId(0)), 
+                            Transaction::Chargeback(TransactionId(0))] {
+            clients_map.execute_transaction(TransactionId::default(), ClientId(1), transaction, 
+                                            &config).unwrap();
+        }
+        assert_eq!("0, 0, 0, true", format!("{}", clients_map.get(&ClientId(1)).unwrap()));
+    }
+    
+    #[test]
+    // resolving a dispute does not make the held funds negative if they have been reduced
+    fn resolve_clamped_1() {
+
+        let mut clients_map = ClientMap::default();
+        clients_map.insert(ClientId(1), Client::new(0., 0., false)).unwrap();
+        let config = ProcessingConfig::default();
+        clients_map.execute_transaction(TransactionId(1), ClientId(1), 
+                                        Transaction::Deposit(100.), &config).unwrap();
+        clients_map.execute_transaction(TransactionId::default(), ClientId(1), 
+                                        Transaction::Dispute(TransactionId(1)), &config).unwrap();
+
+        // adjust the held funds below the disputed amount
+        clients_map.get_mut(&ClientId(1)).unwrap().held = 30.;
         
-        // try to get a reference to a client which does not exist 
-        if let Some(_) = clients_map.get_mut(&ClientId(2)) {
-            panic!("Found a client which does not exist");
+        // only the held funds are released
+        clients_map.execute_transaction(TransactionId::default(), ClientId(1), 
+                                        Transaction::Resolve(TransactionId(1)), &config).unwrap();
+        let client = clients_map.get(&ClientId(1)).unwrap();
+        assert_eq!("30, 0, 30, false", format!("{}", client));
+        assert!(client.is_consistent());
+    }
+    
+    #[test]
+    // the funds of a disputed withdrawal are held or credited to the available ones
+    fn withdrawal_dispute_1() {
+        for (policy, after_dispute, after_resolve, after_chargeback) in 
+            [(WithdrawalDisputePolicy::Hold, 
+              "60, 40, 100, false", "100, 0, 100, false", "60, 40, 100, true"), 
+             (WithdrawalDisputePolicy::CreditAvailable, 
+              "100, 0, 100, false", "60, 0, 60, false", "100, 0, 100, true")] {
+
+            let config = ProcessingConfig { withdrawal_dispute: policy, ..Default::default() };
+            let execute = |clients_map: &mut ClientMap, transaction_id, transaction| {
+                clients_map.execute_transaction(transaction_id, ClientId(1), transaction, &config)
+                    .unwrap();
+                format!("{}", clients_map.get(&ClientId(1)).unwrap())
+            };
+            let mut clients_map = ClientMap::default();
+            clients_map.insert(ClientId(1), Client::new(0., 0., false)).unwrap();
+            execute(&mut clients_map, TransactionId(1), Transaction::Deposit(100.));
+            execute(&mut clients_map, TransactionId(2), Transaction::Withdrawal(40.));
+            
+            // dispute and resolve the withdrawal
+            assert_eq!(after_dispute, execute(&mut clients_map, TransactionId::default(), 
+                                              Transaction::Dispute(TransactionId(2))));
+            assert_eq!(after_resolve, execute(&mut clients_map, TransactionId::default(), 
+                                              Transaction::Resolve(TransactionId(2))));
+
+            // dispute and charge back another withdrawal
+            let mut clients_map = ClientMap::default();
+            clients_map.insert(ClientId(1), Client::new(0., 0., false)).unwrap();
+            execute(&mut clients_map, TransactionId(1), Transaction::Deposit(100.));
+            execute(&mut clients_map, TransactionId(2), Transaction::Withdrawal(40.));
+            execute(&mut clients_map, TransactionId::default(), 
+                    Transaction::Dispute(TransactionId(2)));
+            assert_eq!(after_chargeback, execute(&mut clients_map, TransactionId::default(), 
+                                                 Transaction::Chargeback(TransactionId(2))));
         }
     }
     
     #[test]
-    fn deposit_1() {
+    // the pretty output is aligned in columns
+    fn pretty_1() {
+        let mut clients_map = ClientMap::default();
+        clients_map.insert(ClientId(1), Client::new(1.5, 0., false)).unwrap();
+        clients_map.insert(ClientId(12), Client::new(12345.25, 100., true)).unwrap();
+        let config = OutputConfig { pretty: true, ..Default::default() };
+        assert_eq!(format!("{}", clients_map.display(&config)), 
+                   concat!("client  available  held     total  locked\n",
+                           "     1        1.5     0       1.5  false\n",
+                           "    12   12345.25   100  12445.25  true\n"));
+    }
+    
+    #[test]
+    // only the clients with more applied transactions than the threshold are reported
+    fn high_activity_clients_1() {
+        let mut clients_map = ClientMap::default();
+        let config = ProcessingConfig::default();
+        clients_map.insert(ClientId(1), Client::new(0., 0., false)).unwrap();
+        clients_map.insert(ClientId(2), Client::new(0., 0., false)).unwrap();
+        for i in 1..=5 {
+            clients_map.execute_transaction(TransactionId(i), ClientId(1), 
+                                            Transaction::Deposit(1.), &config).unwrap();
+        }
+        for i in 6..=7 {
+            clients_map.execute_transaction(TransactionId(i), ClientId(2), 
+                                            Transaction::Deposit(1.), &config).unwrap();
+        }
 
-        // Create an empty ClientMap
+        // transactions which are ignored are not counted
+        clients_map.execute_transaction(TransactionId(6), ClientId(2), 
+                                        Transaction::Deposit(1.), &config).unwrap();
+
+        assert_eq!(5, clients_map.get(&ClientId(1)).unwrap().n_transactions());
+        assert_eq!(2, clients_map.get(&ClientId(2)).unwrap().n_transactions());
+        assert_eq!(vec![ClientId(1)], clients_map.high_activity_clients(3));
+        assert_eq!(vec![ClientId(1), ClientId(2)], clients_map.high_activity_clients(1));
+    }
+    
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "inconsistent data for client 1")]
+    // in debug builds, a transaction leaving the client data inconsistent panics
+    fn consistency_assertion_1() {
+        let mut clients_map = ClientMap::default();
+        clients_map.insert(ClientId(1), Client::new(0., -1., false)).unwrap();
+        clients_map.execute_transaction(TransactionId(1), ClientId(1), Transaction::Deposit(1.), 
+                                        &ProcessingConfig::default()).unwrap();
+    }
+    
+    #[test]
+    // the header can be omitted, in CSV or pretty output
+    fn omit_header_1() {
+        let mut clients_map = ClientMap::default();
+        clients_map.insert(ClientId(1), Client::new(1.5, 0., false)).unwrap();
+        let config = OutputConfig { omit_header: true, ..Default::default() };
+        assert_eq!("1, 1.5, 0, 1.5, false\n", format!("{}", clients_map.display(&config)));
+        let config = OutputConfig { omit_header: true, pretty: true, ..Default::default() };
+        assert_eq!("1  1.5  0  1.5  false\n", format!("{}", clients_map.display(&config)));
+    }
+    
+    #[test]
+    // amounts are printed with their shortest representation, a fixed number of decimal places, or
+    // their exact stored value
+    fn amount_format_1() {
+        let mut clients_map = ClientMap::default();
+        clients_map.insert(ClientId(1), Client::new(100.0001, 0., false)).unwrap();
+        let output = |amount_format| {
+            let config = OutputConfig { amount_format, omit_header: true, ..Default::default() };
+            format!("{}", clients_map.display(&config))
+        };
+        assert_eq!("1, 100.0001, 0, 100.0001, false\n", output(AmountFormat::Shortest));
+        assert_eq!("1, 100.0001, 0.0000, 100.0001, false\n", output(AmountFormat::Fixed(4)));
+        assert_eq!(concat!("1, 100.000100000000003319655661471188068389892578125, 0, ", 
+                           "100.000100000000003319655661471188068389892578125, false\n"), 
+                   output(AmountFormat::ExactValue));
+    }
+    
+    #[test]
+    // a transaction is under dispute from its dispute until it is resolved or charged back
+    fn is_disputed_1() {
+        let mut clients_map = ClientMap::default();
+        let config = ProcessingConfig::default();
+        clients_map.insert(ClientId(1), Client::new(0., 0., false)).unwrap();
+        for (transaction_id, transaction, disputed) in [
+            (TransactionId(1), Transaction::Deposit(10.), false),
+            (TransactionId::default(), Transaction::Dispute(TransactionId(1)), true),
+            (TransactionId::default(), Transaction::Resolve(TransactionId(1)), false),
+            (TransactionId::default(), Transaction::Dispute(TransactionId(1)), true),
+            (TransactionId::default(), Transaction::Chargeback(TransactionId(1)), false)] {
+            clients_map.execute_transaction(transaction_id, ClientId(1), transaction, &config)
+                .unwrap();
+            assert_eq!(Some(disputed), 
+                       clients_map.is_transaction_disputed(&ClientId(1), &TransactionId(1)));
+            assert_eq!(disputed, clients_map.get(&ClientId(1)).unwrap()
+                       .is_disputed(&TransactionId(1)));
+        }
+        assert_eq!(None, clients_map.is_transaction_disputed(&ClientId(2), &TransactionId(1)));
+    }
+    
+    #[test]
+    // a transaction can not be charged back twice, even after the account is unlocked
+    fn duplicate_chargeback_1() {
         let mut clients_map = ClientMap::default();
+        let config = ProcessingConfig::default();
+        clients_map.insert(ClientId(1), Client::new(0., 0., false)).unwrap();
+        let execute = |clients_map: &mut ClientMap, transaction_id, transaction| {
+            clients_map.execute_transaction(transaction_id, ClientId(1), transaction, &config)
+                .unwrap();
+            format!("{}", clients_map.get(&ClientId(1)).unwrap())
+        };
+        execute(&mut clients_map, TransactionId(1), Transaction::Deposit(100.));
+        execute(&mut clients_map, TransactionId(2), Transaction::Deposit(50.));
+        execute(&mut clients_map, TransactionId::default(), Transaction::Dispute(TransactionId(1)));
+        assert_eq!("50, 0, 50, true", 
+                   execute(&mut clients_map, TransactionId::default(), 
+                           Transaction::Chargeback(TransactionId(1))));
+        clients_map.unlock(ClientId(1), &config).unwrap();
 
-        // Add a new client with an empty account and ID 1
+        // a second chargeback, with or without a new dispute, does not change anything
+        for transaction in [Transaction::Chargeback(TransactionId(1)), 
+                            Transaction::Dispute(TransactionId(1)), 
+                            Transaction::Chargeback(TransactionId(1))] {
+            assert_eq!("50, 0, 50, false", 
+                       execute(&mut clients_map, TransactionId::default(), transaction));
+        }
+        assert!(!clients_map.get(&ClientId(1)).unwrap().is_disputed(&TransactionId(1)));
+    }
+    
+    #[test]
+    // the total disputed amount is the sum of the disputed amounts of all clients
+    fn total_disputed_amount_1() {
+        let mut clients_map = ClientMap::default();
+        let config = ProcessingConfig::default();
+        for (transaction_id, client_id, transaction) in [
+            (TransactionId(1), ClientId(1), Transaction::Deposit(100.)),
+            (TransactionId(2), ClientId(1), Transaction::Deposit(20.)),
+            (TransactionId(3), ClientId(2), Transaction::Deposit(50.)),
+            (TransactionId(4), ClientId(2), Transaction::Withdrawal(10.)),
+            (TransactionId::default(), ClientId(1), Transaction::Dispute(TransactionId(1))),
+            (TransactionId::default(), ClientId(1), Transaction::Dispute(TransactionId(2))),
+            (TransactionId::default(), ClientId(1), Transaction::Resolve(TransactionId(2))),
+            (TransactionId::default(), ClientId(2), Transaction::Dispute(TransactionId(3))),
+            (TransactionId::default(), ClientId(2), Transaction::Dispute(TransactionId(4)))] {
+            if !clients_map.contains_key(&client_id) {
+                clients_map.insert(client_id, Client::new(0., 0., false)).unwrap();
+            }
+            clients_map.execute_transaction(transaction_id, client_id, transaction, &config)
+                .unwrap();
+        }
+        assert_eq!(100., clients_map.get(&ClientId(1)).unwrap().disputed_amount());
+        assert_eq!(60., clients_map.get(&ClientId(2)).unwrap().disputed_amount());
+        assert_eq!(160., clients_map.total_disputed_amount());
+        assert_eq!((10., 160., 170.), clients_map.total_funds());
+    }
+    
+    #[test]
+    // merging two clients sums their funds and keeps the first transaction with a given ID
+    fn merge_clients_1() {
+        let mut clients_map = ClientMap::default();
+        let config = ProcessingConfig::default();
         clients_map.insert(ClientId(1), Client::new(0., 0., false)).unwrap();
-        
-        // Execute a transaction: deposit
+        clients_map.insert(ClientId(2), Client::new(0., 0., false)).unwrap();
+        for (transaction_id, client_id, transaction) in [
+            (TransactionId(1), ClientId(1), Transaction::Deposit(100.)),
+            (TransactionId(2), ClientId(2), Transaction::Deposit(30.)),
+            (TransactionId(1), ClientId(2), Transaction::Deposit(20.)),
+            (TransactionId::default(), ClientId(2), Transaction::Dispute(TransactionId(2)))] {
+            clients_map.execute_transaction(transaction_id, client_id, transaction, &config)
+                .unwrap();
+        }
+
+        assert!(clients_map.merge_clients(ClientId(1), ClientId(3)).is_err());
+        clients_map.merge_clients(ClientId(1), ClientId(2)).unwrap();
+        assert_eq!(1, clients_map.len());
+        let client = clients_map.get(&ClientId(1)).unwrap();
+        assert_eq!("120, 30, 150, false", format!("{}", client));
+        assert_eq!(4, client.n_transactions());
+        assert!(client.is_disputed(&TransactionId(2)));
+
+        // transaction 1 is the deposit of the first client, so a chargeback removes 100
+        clients_map.execute_transaction(TransactionId::default(), ClientId(1), 
+                                        Transaction::Dispute(TransactionId(1)), &config).unwrap();
+        clients_map.execute_transaction(TransactionId::default(), ClientId(1), 
+                                        Transaction::Chargeback(TransactionId(1)), &config)
+            .unwrap();
+        assert_eq!("20, 30, 50, true", format!("{}", clients_map.get(&ClientId(1)).unwrap()));
+    }
+    
+    #[test]
+    // interest is added to the positive available funds of unlocked accounts only
+    fn apply_interest_1() {
+        let mut clients_map = ClientMap::default();
+        clients_map.insert(ClientId(1), Client::new(100., 10., false)).unwrap();
+        clients_map.insert(ClientId(2), Client::new(100., 10., true)).unwrap();
+        clients_map.insert(ClientId(3), Client::new(-50., 0., false)).unwrap();
+        clients_map.apply_interest(0.1).unwrap();
+        assert_eq!("client, available, held, total, locked\n\
+                    1, 110, 10, 120, false\n\
+                    2, 100, 10, 110, true\n\
+                    3, -50, 0, -50, false\n", 
+                   format!("{}", clients_map));
+
+        // invalid rates are rejected without changing anything
+        for rate in [-0.1, f64::NAN, f64::INFINITY] {
+            assert!(clients_map.apply_interest(rate).is_err());
+        }
+        assert_eq!("110, 10, 120, false", format!("{}", clients_map.get(&ClientId(1)).unwrap()));
+    }
+    
+    #[test]
+    // deposits bringing the total funds beyond 2^53 are rejected if required
+    fn safe_integer_1() {
+        let config = ProcessingConfig { safe_integer: SafeIntegerPolicy::Reject, 
+                                        ..Default::default() };
+        let mut clients_map = ClientMap::default();
+        clients_map.insert(ClientId(1), Client::new(MAX_SAFE_AMOUNT - 10., 0., false)).unwrap();
+        let error = clients_map.execute_transaction(TransactionId(1), ClientId(1), 
+                                                    Transaction::Deposit(100.), &config)
+            .unwrap_err();
+        assert!(error.is::<UnsafeTotalError>());
+        clients_map.execute_transaction(TransactionId(2), ClientId(1), Transaction::Deposit(10.), 
+                                        &config).unwrap();
+        assert_eq!(MAX_SAFE_AMOUNT, clients_map.get(&ClientId(1)).unwrap().available());
+    }
+    
+    #[test]
+    // `Client::apply` only changes the funds and state of the client
+    fn apply_1() {
+        let config = ProcessingConfig::default();
+        let mut client = Client::new(100., 0., false);
+        let mut apply = |transaction_id, transaction| {
+            let applied = client.apply(transaction_id, &transaction, &config);
+            (applied, format!("{}", client))
+        };
+        assert_eq!((true, "150, 0, 150, false".to_string()), 
+                   apply(TransactionId(1), Transaction::Deposit(50.)));
+        assert_eq!((true, "130, 0, 130, false".to_string()), 
+                   apply(TransactionId(2), Transaction::Withdrawal(20.)));
+        assert_eq!((false, "130, 0, 130, false".to_string()), 
+                   apply(TransactionId(3), Transaction::Withdrawal(200.)));
+        assert_eq!((true, "80, 50, 130, false".to_string()), 
+                   apply(TransactionId::default(), Transaction::Dispute(TransactionId(1))));
+        assert_eq!((true, "130, 0, 130, false".to_string()), 
+                   apply(TransactionId::default(), Transaction::Resolve(TransactionId(1))));
+        assert_eq!((true, "80, 50, 130, false".to_string()), 
+                   apply(TransactionId::default(), Transaction::Dispute(TransactionId(1))));
+        assert_eq!((true, "80, 0, 80, true".to_string()), 
+                   apply(TransactionId::default(), Transaction::Chargeback(TransactionId(1))));
+
+        // unlike `ClientMap::execute_transaction`, the lock and duplicate IDs are not checked
+        assert_eq!((true, "90, 0, 90, true".to_string()), 
+                   apply(TransactionId(2), Transaction::Deposit(10.)));
+        assert_eq!((true, "90, 0, 90, true".to_string()), 
+                   apply(TransactionId::default(), Transaction::Close));
+        assert!(client.closed);
+        assert!(client.apply(TransactionId::default(), &Transaction::Open, &config));
+        assert!(!client.closed);
+        assert_eq!(9, client.n_transactions());
+    }
+    
+    #[test]
+    // the row of totals sums the funds of the displayed clients and counts the locked accounts
+    fn totals_row_1() {
+        let mut clients_map = ClientMap::default();
+        clients_map.insert(ClientId(1), Client::new(100., 10., false)).unwrap();
+        clients_map.insert(ClientId(2), Client::new(20.5, 0., true)).unwrap();
+        clients_map.insert(ClientId(3), Client::new(0., 0., false)).unwrap();
+        let config = OutputConfig { totals_row: true, ..Default::default() };
+        assert_eq!("client, available, held, total, locked\n\
+                    1, 100, 10, 110, false\n\
+                    2, 20.5, 0, 20.5, true\n\
+                    3, 0, 0, 0, false\n\
+                    TOTAL, 120.5, 10, 130.5, 1\n", 
+                   format!("{}", clients_map.display(&config)));
+        assert_eq!((120.5, 10., 130.5), clients_map.total_funds());
+
+        // only the displayed clients are counted
+        let config = OutputConfig { totals_row: true, locked_only: true, verbose: true, 
+                                    ..Default::default() };
+        assert_eq!(format!("{}\n\
+                            2, 20.5, 0, 20.5, true, 0, 20.5\n\
+                            TOTAL, 20.5, 0, 20.5, 1, 0, 20.5\n", 
+                           VERBOSE_CSV_HEADER), 
+                   format!("{}", clients_map.display(&config)));
+    }
+    
+    #[test]
+    // residuals of floating-point arithmetic are shown as zero
+    fn zero_residuals_1() {
+        let mut clients_map = ClientMap::default();
+        let config = ProcessingConfig::default();
+        clients_map.insert(ClientId(1), Client::new(0., 0., false)).unwrap();
+        for (transaction_id, transaction) in [
+            (TransactionId(1), Transaction::Deposit(0.1)),
+            (TransactionId(2), Transaction::Deposit(0.2)),
+            (TransactionId::default(), Transaction::Dispute(TransactionId(1))),
+            (TransactionId::default(), Transaction::Dispute(TransactionId(2))),
+            (TransactionId::default(), Transaction::Resolve(TransactionId(1))),
+            (TransactionId::default(), Transaction::Resolve(TransactionId(2))),
+            (TransactionId(3), Transaction::Withdrawal(0.3))] {
+            clients_map.execute_transaction(transaction_id, ClientId(1), transaction, &config)
+                .unwrap();
+        }
+
+        // the stored values are not exactly zero...
+        let client = clients_map.get(&ClientId(1)).unwrap();
+        assert!(client.available() != 0. || client.held() != 0.);
+
+        // ...but they are shown as such
+        assert_eq!("client, available, held, total, locked\n1, 0, 0, 0, false\n", 
+                   format!("{}", clients_map));
+        let config = OutputConfig { amount_format: AmountFormat::Fixed(4), ..Default::default() };
+        assert_eq!("client, available, held, total, locked\n1, 0.0000, 0.0000, 0.0000, false\n", 
+                   format!("{}", clients_map.display(&config)));
+    }
+    
+    #[test]
+    // the summary returned when applying a transaction is the state of the client afterwards
+    fn apply_transaction_checked_1() {
+        let mut clients_map = ClientMap::default();
+        let config = ProcessingConfig { quiet: true, ..Default::default() };
+        clients_map.insert(ClientId(1), Client::new(0., 0., false)).unwrap();
+        for (transaction_id, transaction, expected) in [
+            (TransactionId(1), Transaction::Deposit(10.), (10., 0., false)),
+            (TransactionId(2), Transaction::Withdrawal(3.), (7., 0., false)),
+            (TransactionId(2), Transaction::Deposit(5.), (7., 0., false)),
+            (TransactionId::default(), Transaction::Dispute(TransactionId(1)), (-3., 10., false)),
+            (TransactionId::default(), Transaction::Chargeback(TransactionId(1)), (-3., 0., true)),
+        ] {
+            let summary = clients_map
+                .apply_transaction_checked(transaction_id, ClientId(1), transaction, &config)
+                .unwrap();
+            assert_eq!(Some(summary), clients_map.get_summary(&ClientId(1)));
+            assert_eq!((ClientId(1), expected.0, expected.1, expected.0 + expected.1, expected.2), 
+                       (summary.client, summary.available, summary.held, summary.total, 
+                        summary.locked));
+        }
+
+        // errors are returned as by `execute_transaction`, wrapped in a `ProcessingError`
+        let error = clients_map.apply_transaction_checked(TransactionId(3), ClientId(1), 
+                                                          Transaction::Deposit(1.), &config)
+            .unwrap_err();
+        assert!(matches!(error, ProcessingError::Rejected(error) 
+                         if error.is::<LockedAccountError>()));
+        let error = clients_map.apply_transaction_checked(TransactionId(1), ClientId(2), 
+                                                          Transaction::Deposit(1.), &config)
+            .unwrap_err();
+        assert_eq!("Client 2 not found", error.to_string());
+        assert!(matches!(error, ProcessingError::Rejected(error) 
+                         if error.is::<ClientNotFoundError>()));
+    }
+    
+    // a store keeping the clients in a vector, in insertion order
+    #[derive(Default, Clone)]
+    struct VecStore(Vec<(ClientId, Client)>);
+
+    impl ClientStore for VecStore {
+
+        fn get(&self, id: &ClientId) -> Option<&Client> {
+            self.0.iter().find(|(client_id, _)| client_id == id).map(|(_, client)| client)
+        }
+
+        fn get_mut(&mut self, id: &ClientId) -> Option<&mut Client> {
+            self.0.iter_mut().find(|(client_id, _)| client_id == id).map(|(_, client)| client)
+        }
+
+        fn insert(&mut self, id: ClientId, client: Client) -> Option<Client> {
+            match self.get_mut(&id) {
+                Some(existing_client) => Some(std::mem::replace(existing_client, client)),
+                None => {
+                    self.0.push((id, client));
+                    None
+                }
+            }
+        }
+
+        fn remove(&mut self, id: &ClientId) -> Option<Client> {
+            let index = self.0.iter().position(|(client_id, _)| client_id == id)?;
+            Some(self.0.remove(index).1)
+        }
+
+        fn iter(&self) -> Box<dyn Iterator<Item = (&ClientId, &Client)> + '_> {
+            Box::new(self.0.iter().map(|(id, client)| (id, client)))
+        }
+    }
+
+    #[test]
+    // a map using another store behaves as the in-memory one
+    fn client_store_1() {
+        let config = ProcessingConfig { quiet: true, ..Default::default() };
+        let mut clients_map = ClientMap::with_store(VecStore::default());
+        let mut memory_clients_map = ClientMap::default();
+        for id in [3, 1, 2] {
+            clients_map.insert(ClientId(id), Client::new(0., 0., false)).unwrap();
+            memory_clients_map.insert(ClientId(id), Client::new(0., 0., false)).unwrap();
+        }
+        for (transaction_id, client_id, transaction) in [
+            (TransactionId(1), ClientId(1), Transaction::Deposit(10.)),
+            (TransactionId(2), ClientId(2), Transaction::Deposit(5.)),
+            (TransactionId(3), ClientId(1), Transaction::Withdrawal(2.5)),
+            (TransactionId::default(), ClientId(2), Transaction::Dispute(TransactionId(2))),
+            (TransactionId(4), ClientId(3), Transaction::Deposit(1.)),
+            (TransactionId::default(), ClientId(3), Transaction::Dispute(TransactionId(4))),
+            (TransactionId::default(), ClientId(3), Transaction::Chargeback(TransactionId(4))),
+        ] {
+            clients_map.execute_transaction(transaction_id, client_id, transaction, &config)
+                .unwrap();
+            memory_clients_map.execute_transaction(transaction_id, client_id, transaction, &config)
+                .unwrap();
+        }
+        assert_eq!(format!("{}", memory_clients_map), format!("{}", clients_map));
+        assert_eq!(memory_clients_map.total_funds(), clients_map.total_funds());
+        assert_eq!(vec![ClientId(3)], clients_map.locked_clients());
+
+        // the clients are in the store
+        clients_map.merge_clients(ClientId(1), ClientId(2)).unwrap();
+        assert_eq!(2, clients_map.len());
+        assert_eq!(vec![ClientId(3), ClientId(1)], 
+                   clients_map.store().0.iter().map(|(id, _)| *id).collect::<Vec<_>>());
+    }
+    
+    #[test]
+    // removed clients are no longer in the map, and can be inserted again afresh
+    fn remove_1() {
+        let mut clients_map = ClientMap::default();
+        let config = ProcessingConfig::default();
+        clients_map.insert(ClientId(1), Client::new(0., 0., false)).unwrap();
+        clients_map.insert(ClientId(2), Client::new(5., 0., false)).unwrap();
+        clients_map.execute_transaction(TransactionId(1), ClientId(1), Transaction::Deposit(10.), 
+                                        &config).unwrap();
+
+        let client = clients_map.remove(&ClientId(1)).unwrap();
+        assert_eq!(10., client.available());
+        assert!(!clients_map.contains_key(&ClientId(1)));
+        assert_eq!(1, clients_map.len());
+        assert!(clients_map.remove(&ClientId(1)).is_none());
+
+        // the transaction IDs of the removed client can be reused
+        clients_map.insert(ClientId(1), Client::new(0., 0., false)).unwrap();
+        clients_map.execute_transaction(TransactionId(1), ClientId(1), Transaction::Deposit(3.), 
+                                        &config).unwrap();
+        assert_eq!("client, available, held, total, locked\n\
+                    1, 3, 0, 3, false\n\
+                    2, 5, 0, 5, false\n", 
+                   format!("{}", clients_map));
+    }
+    
+    #[test]
+    // the funds held by a dispute can not be withdrawn
+    fn withdraw_held_funds_1() {
+        let mut clients_map = ClientMap::default();
+        let config = ProcessingConfig::default();
+        clients_map.insert(ClientId(1), Client::new(0., 0., false)).unwrap();
+        for (transaction_id, transaction) in [
+            (TransactionId(1), Transaction::Deposit(100.)),
+            (TransactionId::default(), Transaction::Dispute(TransactionId(1)))] {
+            clients_map.execute_transaction(transaction_id, ClientId(1), transaction, &config)
+                .unwrap();
+        }
+        clients_map.execute_transaction(TransactionId(2), ClientId(1), Transaction::Withdrawal(50.),
+                                        &config).unwrap_err();
+
+        // the withdrawal is ignored, leaving both the available and held funds unchanged
+        assert_eq!("client, available, held, total, locked\n1, 0, 100, 100, false\n", 
+                   format!("{}", clients_map));
+        assert_eq!(2, clients_map.get(&ClientId(1)).unwrap().n_transactions());
+
+        // once the dispute is resolved, the funds can be withdrawn
+        clients_map.execute_transaction(TransactionId::default(), ClientId(1), 
+                                        Transaction::Resolve(TransactionId(1)), &config).unwrap();
+        clients_map.execute_transaction(TransactionId(3), ClientId(1), 
+                                        Transaction::Withdrawal(50.), &config).unwrap();
+        assert_eq!("client, available, held, total, locked\n1, 50, 0, 50, false\n", 
+                   format!("{}", clients_map));
+    }
+    
+    #[test]
+    // a dispute after a duplicate deposit refers to the original one, which alone is held
+    fn dispute_after_duplicate_1() {
+        let mut clients_map = ClientMap::default();
+        let config = ProcessingConfig { quiet: true, ..Default::default() };
+        clients_map.insert(ClientId(1), Client::new(0., 0., false)).unwrap();
+        for (transaction_id, transaction) in [
+            (TransactionId(1), Transaction::Deposit(100.)),
+            (TransactionId(1), Transaction::Deposit(40.)),
+            (TransactionId::default(), Transaction::Dispute(TransactionId(1)))] {
+            clients_map.execute_transaction(transaction_id, ClientId(1), transaction, &config)
+                .unwrap();
+        }
+        assert_eq!("client, available, held, total, locked\n1, 0, 100, 100, false\n", 
+                   format!("{}", clients_map));
+
+        // a chargeback also removes the original amount only
+        clients_map.execute_transaction(TransactionId::default(), ClientId(1), 
+                                        Transaction::Chargeback(TransactionId(1)), &config)
+            .unwrap();
+        assert_eq!("client, available, held, total, locked\n1, 0, 0, 0, true\n", 
+                   format!("{}", clients_map));
+    }
+
+    #[test]
+    // clearing the withdrawals lets the clients withdraw up to the limit again
+    fn clear_withdrawals_1() {
+        let config = ProcessingConfig { withdrawal_limit: Some(100.), ..Default::default() };
+        let mut clients_map = ClientMap::default();
+        clients_map.insert(ClientId(1), Client::new(500., 0., false)).unwrap();
         clients_map.execute_transaction(TransactionId(1), ClientId(1), 
-                                        Transaction::Deposit(2_022.),
-                                        false).unwrap();
+                                        Transaction::Withdrawal(100.), &config).unwrap();
+        let error = clients_map.execute_transaction(TransactionId(2), ClientId(1), 
+                                                    Transaction::Withdrawal(1.), &config)
+            .unwrap_err();
+        assert!(error.is::<WithdrawalLimitError>());
+        assert_eq!(100., clients_map.get(&ClientId(1)).unwrap().withdrawn());
+        clients_map.clear_withdrawals();
+        clients_map.execute_transaction(TransactionId(2), ClientId(1), 
+                                        Transaction::Withdrawal(100.), &config).unwrap();
+        assert_eq!(300., clients_map.get(&ClientId(1)).unwrap().available());
+    }
 
-        // check the client info
-        if let Some(ref_to_client) = clients_map.get(&ClientId(1)) {
-            assert_eq!("2022, 0, 2022, false".to_string(), 
-                       format!("{}", ref_to_client));
-        } else {
-            panic!("Client not found!");
+    #[test]
+    // sorting by last activity shows the most recently active clients first, and those without
+    // any executed transaction last
+    fn sort_by_last_activity_1() {
+        let config = ProcessingConfig::default();
+        let mut clients_map = ClientMap::default();
+        for id in 1..=4 {
+            clients_map.insert(ClientId(id), Client::new(0., 0., false)).unwrap();
         }
+        for (transaction_id, client_id) in [(1, 2), (2, 1), (3, 3), (4, 2)] {
+            clients_map.execute_transaction(TransactionId(transaction_id), ClientId(client_id),
+                                            Transaction::Deposit(10.), &config).unwrap();
+        }
+
+        // a withdrawal exceeding the available funds is not executed and leaves the order as is
+        clients_map.execute_transaction(TransactionId(5), ClientId(1), 
+                                        Transaction::Withdrawal(100.), &config).unwrap_err();
+        let output = OutputConfig { sort_order: SortOrder::LastActivity, ..Default::default() };
+        assert_eq!("client, available, held, total, locked\n\
+                    2, 20, 0, 20, false\n\
+                    3, 10, 0, 10, false\n\
+                    1, 10, 0, 10, false\n\
+                    4, 0, 0, 0, false\n", 
+                   format!("{}", clients_map.display(&output)));
+        assert_eq!(None, clients_map.get(&ClientId(4)).unwrap().last_activity());
     }
-    
+
     #[test]
-    fn withdrawal_1() {
+    // the diff reports a client whose held funds differ and one which only exists in one map
+    fn diff_1() {
+        let mut first = ClientMap::default();
+        first.insert(ClientId(1), Client::new(10., 5., false)).unwrap();
+        first.insert(ClientId(2), Client::new(20., 0., false)).unwrap();
+        first.insert(ClientId(3), Client::new(30., 0., true)).unwrap();
+        let mut second = ClientMap::default();
+        second.insert(ClientId(1), Client::new(10., 4., false)).unwrap();
+        second.insert(ClientId(3), Client::new(30., 0., true)).unwrap();
+        assert_eq!(vec![ClientDiff::Changed { first: first.get_summary(&ClientId(1)).unwrap(), 
+                                              second: second.get_summary(&ClientId(1)).unwrap() },
+                        ClientDiff::OnlyInFirst(first.get_summary(&ClientId(2)).unwrap())], 
+                   first.diff(&second));
+        assert_eq!(vec![ClientDiff::Changed { first: second.get_summary(&ClientId(1)).unwrap(), 
+                                              second: first.get_summary(&ClientId(1)).unwrap() },
+                        ClientDiff::OnlyInSecond(first.get_summary(&ClientId(2)).unwrap())], 
+                   second.diff(&first));
+    }
 
-        // Create an empty ClientMap
+    #[test]
+    // exact duplicates are ignored with the usual warning, and duplicates with a different amount
+    // with a stronger one, or an error in strict mode
+    fn conflicting_duplicate_1() {
+        use std::rc::Rc;
+        use std::cell::RefCell;
+        use crate::config::WarningHook;
+        let warnings = Rc::new(RefCell::new(Vec::new()));
+        let warnings_in_hook = Rc::clone(&warnings);
+        let config = ProcessingConfig { 
+            warning_hook: Some(WarningHook::new(move |message| {
+                warnings_in_hook.borrow_mut().push(message.to_string())
+            })),
+            ..Default::default() 
+        };
         let mut clients_map = ClientMap::default();
-
-        // Add a new client with an empty account and ID 1
         clients_map.insert(ClientId(1), Client::new(0., 0., false)).unwrap();
-        
-        // Execute a transaction: deposit
+        for amount in [100., 100., 50.] {
+            clients_map.execute_transaction(TransactionId(1), ClientId(1), 
+                                            Transaction::Deposit(amount), &config).unwrap();
+        }
+        assert_eq!(vec!["Warning: More than one transaction with client ID 1 and transaction ID 1; all but the first will be ignored".to_string(), 
+                        "Warning: Transaction 1 of client 1 (deposit 50) has the same ID as an earlier one with different data (deposit 100); the duplicate will be ignored, but the data may be corrupted".to_string()], 
+                   *warnings.borrow());
+        assert_eq!(100., clients_map.get(&ClientId(1)).unwrap().available());
+
+        // in strict mode, only the conflicting duplicate is an error
+        let config = ProcessingConfig { quiet: true, ..ProcessingConfig::strict() };
         clients_map.execute_transaction(TransactionId(1), ClientId(1), 
-                                        Transaction::Deposit(12_022.),
-                                        false).unwrap();
-        
-        // Execute a transaction: withdrawal
-        clients_map.execute_transaction(TransactionId(2), ClientId(1), 
-                                        Transaction::Withdrawal(2_022.),
-                                        false).unwrap();
+                                        Transaction::Deposit(100.), &config).unwrap();
+        let error = clients_map.execute_transaction(TransactionId(1), ClientId(1), 
+                                                    Transaction::Withdrawal(100.), &config)
+            .unwrap_err();
+        assert!(error.is::<ConflictingDuplicateError>());
+        assert_eq!(100., clients_map.get(&ClientId(1)).unwrap().available());
+    }
 
-        // check the client info
-        if let Some(ref_to_client) = clients_map.get(&ClientId(1)) {
-            assert_eq!("10000, 0, 10000, false".to_string(), 
-                       format!("{}", ref_to_client));
-        } else {
-            panic!("Client not found!");
+    #[test]
+    // the top clients are those with the highest total funds, ties being broken by ascending ID
+    fn top_n_by_total_1() {
+        let mut clients_map = ClientMap::default();
+        for (id, available, held) in [(1, 10., 0.), (2, 30., 10.), (3, 40., 0.), (4, 5., 0.), 
+                                      (5, 0., 0.)] {
+            clients_map.insert(ClientId(id), Client::new(available, held, false)).unwrap();
         }
+        let top = clients_map.top_n_by_total(2);
+        assert_eq!(vec![ClientId(2), ClientId(3)], 
+                   top.iter().map(|summary| summary.client).collect::<Vec<_>>());
+        assert_eq!(clients_map.get_summary(&ClientId(2)), top.first().copied());
+        assert_eq!(5, clients_map.top_n_by_total(10).len());
+        assert!(clients_map.top_n_by_total(0).is_empty());
     }
-    
-    #[test]
-    fn withdrawal_2() {
 
-        // Create an empty ClientMap
+    #[test]
+    // through the API, a withdrawal exceeding the available funds returns an error with the
+    // requested amount and the available funds, and has no effect
+    fn insufficient_funds_1() {
+        let config = ProcessingConfig::default();
         let mut clients_map = ClientMap::default();
+        clients_map.insert(ClientId(3), Client::new(0., 0., false)).unwrap();
+        clients_map.set_overdraft_limit(ClientId(3), 5.).unwrap();
+        clients_map.execute_transaction(TransactionId(1), ClientId(3), Transaction::Deposit(10.), 
+                                        &config).unwrap();
+        let error = clients_map.execute_transaction(TransactionId(2), ClientId(3), 
+                                                    Transaction::Withdrawal(20.), &config)
+            .unwrap_err();
+        let error = error.downcast_ref::<InsufficientFundsError>().unwrap();
+        assert_eq!(ClientId(3), error.client_id);
+        assert_eq!(20., error.amount);
+        assert_eq!(15., error.available);
+        assert_eq!("client, available, held, total, locked\n3, 10, 0, 10, false\n", 
+                   format!("{}", clients_map));
 
-        // Add a new client with an empty account and ID 1
-        clients_map.insert(ClientId(1), Client::new(0., 0., false)).unwrap();
-        
-        // Execute a transaction: deposit
-        clients_map.execute_transaction(TransactionId(1), ClientId(1), 
-                                        Transaction::Deposit(2_022.),
-                                        false).unwrap();
-        
-        // Try to withdraw more funds than the client has available
-        clients_map.execute_transaction(TransactionId(2), ClientId(1), 
-                                        Transaction::Withdrawal(10_000.),
-                                        false).unwrap();
+        // a withdrawal of the available funds including the overdraft is executed
+        clients_map.execute_transaction(TransactionId(2), ClientId(3), 
+                                        Transaction::Withdrawal(15.), &config).unwrap();
+    }
 
-        // check the client info
-        if let Some(ref_to_client) = clients_map.get(&ClientId(1)) {
-            assert_eq!("2022, 0, 2022, false".to_string(), 
-                       format!("{}", ref_to_client));
-        } else {
-            panic!("Client not found!");
+    #[test]
+    // the summaries are in the order of the default output, with the same values
+    fn to_sorted_summaries_1() {
+        let mut clients_map = ClientMap::default();
+        clients_map.insert(ClientId(7), Client::new(1.5, 0., false)).unwrap();
+        clients_map.insert(ClientId(2), Client::new(20., 5., true)).unwrap();
+        clients_map.insert(ClientId(4), Client::new(0., 3., false)).unwrap();
+        assert_eq!(vec![ClientSummary { client: ClientId(2), available: 20., held: 5., total: 25., 
+                                        locked: true },
+                        ClientSummary { client: ClientId(4), available: 0., held: 3., total: 3., 
+                                        locked: false },
+                        ClientSummary { client: ClientId(7), available: 1.5, held: 0., total: 1.5, 
+                                        locked: false }], 
+                   clients_map.to_sorted_summaries());
+        let displayed: Vec<String> = format!("{}", clients_map).lines().skip(1)
+            .map(|line| line.split(", ").next().unwrap().to_string())
+            .collect();
+        assert_eq!(displayed, clients_map.to_sorted_summaries().iter()
+                   .map(|summary| summary.client.to_string()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    // disputing a withdrawal which emptied the account adds its funds back according to the
+    // policy, and flags it as disputed
+    fn withdrawal_dispute_2() {
+        for (policy, after_dispute) in [(WithdrawalDisputePolicy::Hold, "0, 100, 100, false"), 
+                                        (WithdrawalDisputePolicy::CreditAvailable, 
+                                         "100, 0, 100, false")] {
+            let config = ProcessingConfig { withdrawal_dispute: policy, ..Default::default() };
+            let mut clients_map = ClientMap::default();
+            clients_map.insert(ClientId(1), Client::new(0., 0., false)).unwrap();
+            for (transaction_id, transaction) in [
+                (TransactionId(1), Transaction::Deposit(100.)),
+                (TransactionId(2), Transaction::Withdrawal(100.)),
+                (TransactionId::default(), Transaction::Dispute(TransactionId(2)))] {
+                clients_map.execute_transaction(transaction_id, ClientId(1), transaction, &config)
+                    .unwrap();
+            }
+            let client = clients_map.get(&ClientId(1)).unwrap();
+            assert_eq!(after_dispute, format!("{}", client));
+            assert!(client.is_under_dispute(&TransactionId(2)));
+            assert!(client.is_consistent());
         }
     }
-    
+
     #[test]
-    fn dispute_1() {
+    #[cfg(feature = "serde")]
+    // each line of the JSON Lines output is a valid JSON object, in increasing order of the IDs
+    fn write_json_lines_1() {
+        let mut clients_map = ClientMap::default();
+        clients_map.insert(ClientId(9), Client::new(1.5, 0., false)).unwrap();
+        clients_map.insert(ClientId(3), Client::new(20., 5., true)).unwrap();
+        clients_map.insert(ClientId(5), Client::new(0., 0., false)).unwrap();
+        let mut output = Vec::new();
+        clients_map.write_json_lines(&mut output).unwrap();
+        let lines: Vec<serde_json::Value> = String::from_utf8(output).unwrap().lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(vec![3, 5, 9], 
+                   lines.iter().map(|line| line["client"].as_u64().unwrap()).collect::<Vec<_>>());
+        assert_eq!(serde_json::json!({ "client": 3, "available": 20., "held": 5., "total": 25., 
+                                       "locked": true }), 
+                   lines[0]);
+    }
 
-        // Create an empty ClientMap
+    #[test]
+    // a withdrawal exceeding the available funds by at most the tolerance drains them, and one
+    // exceeding them by more is rejected
+    fn withdrawal_tolerance_1() {
+        let config = ProcessingConfig { withdrawal_tolerance: Some(0.0001), ..Default::default() };
+        for (amount, executed) in [(100.0001, true), (100.00005, true), (100.0002, false)] {
+            let mut clients_map = ClientMap::default();
+            clients_map.insert(ClientId(1), Client::new(0., 0., false)).unwrap();
+            clients_map.execute_transaction(TransactionId(1), ClientId(1), 
+                                            Transaction::Deposit(100.), &config).unwrap();
+            let result = clients_map.execute_transaction(TransactionId(2), ClientId(1), 
+                                                         Transaction::Withdrawal(amount), &config);
+            let client = clients_map.get(&ClientId(1)).unwrap();
+            if executed {
+                result.unwrap();
+                assert_eq!("0, 0, 0, false", format!("{}", client));
+                assert_eq!(100., client.withdrawn());
+            } else {
+                assert!(result.unwrap_err().is::<InsufficientFundsError>());
+                assert_eq!("100, 0, 100, false", format!("{}", client));
+            }
+        }
+
+        // without a tolerance, the smallest excess is rejected
         let mut clients_map = ClientMap::default();
+        clients_map.insert(ClientId(1), Client::new(100., 0., false)).unwrap();
+        assert!(clients_map.execute_transaction(TransactionId(2), ClientId(1), 
+                                                Transaction::Withdrawal(100.00005), 
+                                                &ProcessingConfig::default()).is_err());
+    }
 
-        // Add a new client with an empty account and ID 1
-        clients_map.insert(ClientId(1), Client::new(0., 0., false)).unwrap();
-        
-        // Execute a transaction: deposit
-        clients_map.execute_transaction(TransactionId(1), ClientId(1), 
-                                        Transaction::Deposit(10_000.),
-                                        false).unwrap();
-        
-        // Dispute the transaction
-        clients_map.execute_transaction(TransactionId::default(), ClientId(1), 
-                                        Transaction::Dispute(TransactionId(1)),
-                                        false).unwrap();
+    #[test]
+    // `get_or_create` creates an empty client on the first call, and returns the existing one
+    // afterwards
+    fn get_or_create_1() {
+        let config = ProcessingConfig::default();
+        let mut clients_map = ClientMap::default();
+        let client = clients_map.get_or_create(ClientId(4));
+        assert_eq!("0, 0, 0, false", format!("{}", client));
+        assert!(client.apply(TransactionId(1), &Transaction::Deposit(100.), &config));
+        assert_eq!("100, 0, 100, false", format!("{}", clients_map.get_or_create(ClientId(4))));
+        assert_eq!(1, clients_map.len());
+    }
 
-        // check the client info
-        if let Some(ref_to_client) = clients_map.get(&ClientId(1)) {
-            assert_eq!("0, 10000, 10000, false".to_string(), 
-                       format!("{}", ref_to_client));
-        } else {
-            panic!("Client not found!");
+    #[test]
+    // a resolve after a chargeback is ignored with a warning by default, and returns an error in
+    // strict mode
+    fn resolve_after_chargeback_2() {
+        use std::rc::Rc;
+        use std::cell::RefCell;
+        use crate::config::WarningHook;
+
+        for strict in [false, true] {
+            let warnings = Rc::new(RefCell::new(Vec::new()));
+            let warnings_in_hook = Rc::clone(&warnings);
+            let mut config = ProcessingConfig { 
+                warning_hook: Some(WarningHook::new(move |message| {
+                    warnings_in_hook.borrow_mut().push(message.to_string())
+                })),
+                ..Default::default() 
+            };
+            config.set_strict(strict);
+            let mut clients_map = ClientMap::default();
+            clients_map.insert(ClientId(1), Client::new(0., 0., false)).unwrap();
+            for (transaction_id, transaction) in [
+                (TransactionId(1), Transaction::Deposit(100.)),
+                (TransactionId::default(), Transaction::Dispute(TransactionId(1))),
+                (TransactionId::default(), Transaction::Chargeback(TransactionId(1)))] {
+                clients_map.execute_transaction(transaction_id, ClientId(1), transaction, &config)
+                    .unwrap();
+            }
+            let result = clients_map.execute_transaction(TransactionId::default(), ClientId(1), 
+                                                         Transaction::Resolve(TransactionId(1)), 
+                                                         &config);
+            if strict {
+                let error = result.unwrap_err();
+                let error = error.downcast_ref::<ResolveAfterChargebackError>().unwrap();
+                assert_eq!((ClientId(1), TransactionId(1)), (error.client_id, error.transaction_id));
+                assert!(warnings.borrow().is_empty());
+            } else {
+                result.unwrap();
+                assert_eq!(vec!["Warning: Resolve for transaction 1 of client 1, which has already been charged back; it will be ignored".to_string()], 
+                           *warnings.borrow());
+            }
+
+            // the resolve has no effect either way
+            assert_eq!("0, 0, 0, true", format!("{}", clients_map.get(&ClientId(1)).unwrap()));
         }
     }
-    
-    #[test]
-    // disputing a non-existent transaction should not change the client information
-    fn dispute_2() {
 
-        // Create an empty ClientMap
+    #[test]
+    // the output shows the selected columns in the given order, with custom labels
+    fn custom_columns_1() {
         let mut clients_map = ClientMap::default();
+        clients_map.insert(ClientId(2), Client::new(20., 5., true)).unwrap();
+        clients_map.insert(ClientId(1), Client::new(10., 0., false)).unwrap();
+        let columns = vec![Column::Client, Column::Total, Column::Held, Column::Available];
+        let config = OutputConfig { 
+            columns: Some(columns),
+            column_labels: HashMap::from([(Column::Held, "on_hold".to_string())]),
+            totals_row: true,
+            ..Default::default() 
+        };
+        assert_eq!("client, total, on_hold, available\n\
+                    1, 10, 0, 10\n\
+                    2, 25, 5, 20\n\
+                    TOTAL, 35, 5, 30\n", 
+                   format!("{}", clients_map.display(&config)));
 
-        // Add a new client with an empty account and ID 1
-        clients_map.insert(ClientId(1), Client::new(0., 0., false)).unwrap();
-        
-        // Execute a transaction: deposit
-        clients_map.execute_transaction(TransactionId(1), ClientId(1), 
-                                        Transaction::Deposit(10_000.),
-                                        false).unwrap();
-        
-        // Dispute the transaction
-        clients_map.execute_transaction(TransactionId::default(), ClientId(1), 
-                                        Transaction::Dispute(TransactionId(2)),
-                                        false).unwrap();
+        // the pretty output is aligned in the same columns
+        let config = OutputConfig { pretty: true, ..config };
+        assert_eq!(concat!("client  total  on_hold  available\n",
+                           "     1     10        0         10\n",
+                           "     2     25        5         20\n",
+                           " TOTAL     35        5         30\n"), 
+                   format!("{}", clients_map.display(&config)));
+    }
 
-        // check the client info
-        if let Some(ref_to_client) = clients_map.get(&ClientId(1)) {
-            assert_eq!("10000, 0, 10000, false".to_string(), 
-                       format!("{}", ref_to_client));
-        } else {
-            panic!("Client not found!");
+    #[test]
+    // a client locked by a chargeback with remaining funds is reported, but not one without funds
+    fn locked_with_funds_1() {
+        let config = ProcessingConfig::default();
+        let mut clients_map = ClientMap::default();
+        for (transaction_id, client_id, transaction) in [
+            (1, 1, Transaction::Deposit(100.)),
+            (2, 1, Transaction::Deposit(40.)),
+            (0, 1, Transaction::Dispute(TransactionId(1))),
+            (0, 1, Transaction::Chargeback(TransactionId(1))),
+            (3, 2, Transaction::Deposit(50.)),
+            (0, 2, Transaction::Dispute(TransactionId(3))),
+            (0, 2, Transaction::Chargeback(TransactionId(3))),
+            (4, 3, Transaction::Deposit(10.))] {
+            clients_map.get_or_create(ClientId(client_id));
+            clients_map.execute_transaction(TransactionId(transaction_id), ClientId(client_id),
+                                            transaction, &config).unwrap();
         }
+        assert_eq!(vec![ClientId(1), ClientId(2)], clients_map.locked_clients());
+        assert_eq!(vec![ClientSummary { client: ClientId(1), available: 40., held: 0., total: 40., 
+                                        locked: true }], 
+                   clients_map.locked_with_funds());
     }
-    
+
     #[test]
-    fn resolve_1() {
+    // a dispute expires after the given number of further transactions, and its funds return to
+    // the available ones; a dispute resolved and opened again is only aged from the new one
+    fn dispute_ttl_1() {
+        use std::rc::Rc;
+        use std::cell::RefCell;
+        use crate::config::WarningHook;
 
-        // Create an empty ClientMap
+        let warnings = Rc::new(RefCell::new(Vec::new()));
+        let warnings_in_hook = Rc::clone(&warnings);
+        let config = ProcessingConfig { 
+            dispute_ttl: Some(2),
+            warning_hook: Some(WarningHook::new(move |message| {
+                warnings_in_hook.borrow_mut().push(message.to_string())
+            })),
+            ..Default::default() 
+        };
         let mut clients_map = ClientMap::default();
-
-        // Add a new client with an empty account and ID 1
         clients_map.insert(ClientId(1), Client::new(0., 0., false)).unwrap();
-        
-        // Execute a transaction: deposit
-        clients_map.execute_transaction(TransactionId(1), ClientId(1), 
-                                        Transaction::Deposit(10_000.),
-                                        false).unwrap();
-        
-        // Dispute the transaction
-        clients_map.execute_transaction(TransactionId::default(), ClientId(1), 
-                                        Transaction::Dispute(TransactionId(1)),
-                                        false).unwrap();
-        
-        // Resolve the transaction
-        clients_map.execute_transaction(TransactionId::default(), ClientId(1), 
-                                        Transaction::Resolve(TransactionId(1)),
-                                        false).unwrap();
+        clients_map.insert(ClientId(2), Client::new(0., 0., false)).unwrap();
+        let mut execute = |transaction_id, client_id, transaction| {
+            clients_map.execute_transaction(TransactionId(transaction_id), ClientId(client_id),
+                                            transaction, &config).unwrap();
+            clients_map.get(&ClientId(1)).unwrap().to_string()
+        };
+        execute(1, 1, Transaction::Deposit(100.));
+        execute(2, 1, Transaction::Deposit(10.));
+        assert_eq!("10, 100, 110, false", execute(0, 1, Transaction::Dispute(TransactionId(1))));
+        assert_eq!("10, 100, 110, false", execute(3, 2, Transaction::Deposit(5.)));
+        assert!(warnings.borrow().is_empty());
+        assert_eq!("110, 0, 110, false", execute(4, 2, Transaction::Deposit(5.)));
+        assert_eq!(vec!["Warning: The dispute of transaction 1 of client 1 has expired after 2 transactions; it has been resolved".to_string()], 
+                   *warnings.borrow());
 
-        // check the client info
-        if let Some(ref_to_client) = clients_map.get(&ClientId(1)) {
-            assert_eq!("10000, 0, 10000, false".to_string(), 
-                       format!("{}", ref_to_client));
-        } else {
-            panic!("Client not found!");
+        // dispute the deposit again, resolve it, and dispute it once more: the first entry
+        // reaches its expiry before the last dispute, which is still held
+        execute(0, 1, Transaction::Dispute(TransactionId(2)));
+        execute(0, 1, Transaction::Resolve(TransactionId(2)));
+        assert_eq!("100, 10, 110, false", execute(0, 1, Transaction::Dispute(TransactionId(2))));
+        assert_eq!("100, 10, 110, false", execute(5, 2, Transaction::Deposit(5.)));
+        assert_eq!("110, 0, 110, false", execute(6, 2, Transaction::Deposit(5.)));
+        assert_eq!(2, warnings.borrow().len());
+    }
+
+    #[test]
+    // removing a client forgets its open disputes, which do not expire afterwards
+    fn remove_2() {
+        let mut clients_map = ClientMap::default();
+        let config = ProcessingConfig { dispute_ttl: Some(2), ..Default::default() };
+        clients_map.insert(ClientId(1), Client::new(0., 0., false)).unwrap();
+        clients_map.insert(ClientId(2), Client::new(0., 0., false)).unwrap();
+        for (transaction_id, client_id, transaction) in [
+            (TransactionId(1), ClientId(1), Transaction::Deposit(10.)),
+            (TransactionId(2), ClientId(2), Transaction::Deposit(5.)),
+            (TransactionId::default(), ClientId(1), Transaction::Dispute(TransactionId(1))),
+            (TransactionId::default(), ClientId(2), Transaction::Dispute(TransactionId(2)))] {
+            clients_map.execute_transaction(transaction_id, client_id, transaction, &config)
+                .unwrap();
         }
+
+        let client = clients_map.remove(&ClientId(1)).unwrap();
+        assert_eq!(vec![ClientId(2)], clients_map.dispute_ages.queue.iter()
+                   .map(|(_, client_id, _)| *client_id).collect::<Vec<_>>());
+        assert_eq!(1, clients_map.dispute_ages.opened.len());
+
+        // a client inserted again keeps its dispute open
+        clients_map.insert(ClientId(1), client).unwrap();
+        clients_map.execute_transaction(TransactionId(3), ClientId(2), Transaction::Deposit(1.), 
+                                        &config).unwrap();
+        assert_eq!("client, available, held, total, locked\n\
+                    1, 0, 10, 10, false\n\
+                    2, 1, 5, 6, false\n", 
+                   format!("{}", clients_map));
     }
-    
+
     #[test]
-    // resolving a transaction which is not disputed should not change the client info
-    fn resolve_2() {
+    // a transaction replaying the ID of a deposit evicted from the history is still ignored as a
+    // duplicate
+    fn evict_history_2() {
+        use std::rc::Rc;
+        use std::cell::RefCell;
+        use crate::config::WarningHook;
 
-        // Create an empty ClientMap
+        let warnings = Rc::new(RefCell::new(Vec::new()));
+        let warnings_in_hook = Rc::clone(&warnings);
+        let config = ProcessingConfig { 
+            warning_hook: Some(WarningHook::new(move |message| {
+                warnings_in_hook.borrow_mut().push(message.to_string())
+            })),
+            history_capacity: Some(1),
+            ..Default::default() 
+        };
         let mut clients_map = ClientMap::default();
-
-        // Add a new client with an empty account and ID 1
         clients_map.insert(ClientId(1), Client::new(0., 0., false)).unwrap();
-        
-        // Execute a transaction: deposit
-        clients_map.execute_transaction(TransactionId(1), ClientId(1), 
-                                        Transaction::Deposit(10_000.),
-                                        false).unwrap();
-        
-        // Execute a transaction: deposit
-        clients_map.execute_transaction(TransactionId(2), ClientId(1), 
-                                        Transaction::Deposit(5_000.),
-                                        false).unwrap();
-        
-        // Dispute the first transaction
-        clients_map.execute_transaction(TransactionId::default(), ClientId(1), 
-                                        Transaction::Dispute(TransactionId(1)),
-                                        false).unwrap();
-        
-        // Resolve the second transaction
-        clients_map.execute_transaction(TransactionId::default(), ClientId(1), 
-                                        Transaction::Resolve(TransactionId(2)),
-                                        false).unwrap();
+        for (transaction_id, amount) in [(1, 10.), (2, 5.)] {
+            clients_map.execute_transaction(TransactionId(transaction_id), ClientId(1), 
+                                            Transaction::Deposit(amount), &config).unwrap();
+        }
+        assert!(!clients_map.get(&ClientId(1)).unwrap().is_in_history(&TransactionId(1)));
 
-        // check the client info
-        if let Some(ref_to_client) = clients_map.get(&ClientId(1)) {
-            assert_eq!("5000, 10000, 15000, false".to_string(), 
-                       format!("{}", ref_to_client));
-        } else {
-            panic!("Client not found!");
+        clients_map.execute_transaction(TransactionId(1), ClientId(1), Transaction::Deposit(10.), 
+                                        &config).unwrap();
+        assert_eq!("15, 0, 15, false", format!("{}", clients_map.get(&ClientId(1)).unwrap()));
+        assert_eq!(vec!["Warning: More than one transaction with client ID 1 and transaction ID 1; all but the first will be ignored".to_string()], 
+                   *warnings.borrow());
+    }
+
+    #[test]
+    // in flag-only mode, reversing a disputed deposit removes its funds from the available ones,
+    // where they have remained during the dispute
+    fn reverse_disputed_deposit_flag_only_1() {
+        // either way, the client ends up with the funds they had before the deposit
+        for dispute_fund_mode in [DisputeFundMode::MoveToHeld, DisputeFundMode::FlagOnly] {
+            let config = ProcessingConfig { 
+                dispute_fund_mode, 
+                over_withdrawal: crate::config::OverWithdrawalPolicy::ReverseDeposit,
+                ..Default::default() 
+            };
+            let mut clients_map = ClientMap::default();
+            clients_map.insert(ClientId(1), Client::new(0., 0., false)).unwrap();
+            for (transaction_id, transaction) in [
+                (TransactionId(1), Transaction::Deposit(100.)),
+                (TransactionId(2), Transaction::Withdrawal(80.)),
+                (TransactionId::default(), Transaction::Dispute(TransactionId(1)))] {
+                clients_map.execute_transaction(transaction_id, ClientId(1), transaction, &config)
+                    .unwrap();
+            }
+            clients_map.execute_transaction(TransactionId(3), ClientId(1), 
+                                            Transaction::Withdrawal(100.), &config).unwrap();
+            let client = clients_map.get(&ClientId(1)).unwrap();
+            assert_eq!("-80, 0, -80, false", format!("{}", client));
+            assert!(client.is_consistent());
         }
     }
-    
+
     #[test]
-    fn chargeback_1() {
+    // a withdrawal reversing a disputed deposit is recorded as a transaction, and replaying it or
+    // the reversed deposit has no effect
+    fn reverse_disputed_deposit_1() {
+        use std::rc::Rc;
+        use std::cell::RefCell;
+        use crate::config::WarningHook;
 
-        // Create an empty ClientMap
+        let warnings = Rc::new(RefCell::new(Vec::new()));
+        let warnings_in_hook = Rc::clone(&warnings);
+        let config = ProcessingConfig { 
+            over_withdrawal: OverWithdrawalPolicy::ReverseDeposit,
+            warning_hook: Some(WarningHook::new(move |message| {
+                warnings_in_hook.borrow_mut().push(message.to_string())
+            })),
+            ..Default::default() 
+        };
         let mut clients_map = ClientMap::default();
-
-        // Add a new client with an empty account and ID 1
         clients_map.insert(ClientId(1), Client::new(0., 0., false)).unwrap();
-        
-        // Execute a transaction: deposit
-        clients_map.execute_transaction(TransactionId(1), ClientId(1), 
-                                        Transaction::Deposit(10_000.),
-                                        false).unwrap();
-        
-        // Dispute the transaction
-        clients_map.execute_transaction(TransactionId::default(), ClientId(1), 
-                                        Transaction::Dispute(TransactionId(1)),
-                                        false).unwrap();
-        
-        // Chargeback
-        clients_map.execute_transaction(TransactionId::default(), ClientId(1), 
-                                        Transaction::Chargeback(TransactionId(1)),
-                                        false).unwrap();
+        for (transaction_id, transaction) in [
+            (TransactionId(1), Transaction::Deposit(100.)),
+            (TransactionId(2), Transaction::Deposit(30.)),
+            (TransactionId::default(), Transaction::Dispute(TransactionId(1))),
+            (TransactionId(3), Transaction::Withdrawal(100.))] {
+            clients_map.execute_transaction(transaction_id, ClientId(1), transaction, &config)
+                .unwrap();
+        }
+        let client = clients_map.get(&ClientId(1)).unwrap();
+        assert_eq!("30, 0, 30, false", format!("{}", client));
+        assert_eq!(4, client.n_transactions);
+        assert_eq!(Some(3), client.last_activity);
+        assert_eq!(vec!["Warning: Withdrawal 3 exceeds the available funds of client 1; deposit 1 has been reversed instead".to_string()], 
+                   *warnings.borrow());
 
-        // check the client info
-        if let Some(ref_to_client) = clients_map.get(&ClientId(1)) {
-            assert_eq!("0, 0, 0, true".to_string(), 
-                       format!("{}", ref_to_client));
-        } else {
-            panic!("Client not found!");
+        // replaying the deposit or the withdrawal is ignored as a duplicate
+        for (transaction_id, transaction) in [(TransactionId(1), Transaction::Deposit(100.)),
+                                              (TransactionId(3), Transaction::Withdrawal(100.))] {
+            clients_map.execute_transaction(transaction_id, ClientId(1), transaction, &config)
+                .unwrap();
         }
+        let client = clients_map.get(&ClientId(1)).unwrap();
+        assert_eq!("30, 0, 30, false", format!("{}", client));
+        assert_eq!(4, client.n_transactions);
+        assert_eq!(3, warnings.borrow().len());
     }
-    
-    #[test]
-    // chargeback on a transaction which is not disputed should not change the client info
-    fn chargeback_2() {
 
-        // Create an empty ClientMap
+    #[test]
+    // a map with another store can be merged, and its processed files are then skipped
+    fn merge_4() {
+        let config = ProcessingConfig::default();
         let mut clients_map = ClientMap::default();
+        clients_map.insert(ClientId(1), Client::new(10., 0., false)).unwrap();
+        let mut other_clients_map = ClientMap::with_store(VecStore::default());
+        other_clients_map.insert(ClientId(2), Client::new(0., 0., false)).unwrap();
+        for transaction_id in 1..=3 {
+            other_clients_map.execute_transaction(TransactionId(transaction_id), ClientId(2), 
+                                                  Transaction::Deposit(1.), &config).unwrap();
+        }
+        other_clients_map.mark_processed(42);
 
-        // Add a new client with an empty account and ID 1
-        clients_map.insert(ClientId(1), Client::new(0., 0., false)).unwrap();
-        
-        // Execute a transaction: deposit
-        clients_map.execute_transaction(TransactionId(1), ClientId(1), 
-                                        Transaction::Deposit(10_000.),
-                                        false).unwrap();
-        
-        // Execute a transaction: deposit
-        clients_map.execute_transaction(TransactionId(2), ClientId(1), 
-                                        Transaction::Deposit(5_000.),
-                                        false).unwrap();
-        
-        // Dispute the first transaction
-        clients_map.execute_transaction(TransactionId::default(), ClientId(1), 
-                                        Transaction::Dispute(TransactionId(1)),
-                                        false).unwrap();
-        
-        // Resolve the second transaction
-        clients_map.execute_transaction(TransactionId::default(), ClientId(1), 
-                                        Transaction::Chargeback(TransactionId(2)),
-                                        false).unwrap();
+        clients_map.merge(other_clients_map).unwrap();
+        assert_eq!("client, available, held, total, locked\n\
+                    1, 10, 0, 10, false\n\
+                    2, 3, 0, 3, false\n", 
+                   format!("{}", clients_map));
+        assert!(clients_map.is_processed(42));
 
-        // check the client info
-        if let Some(ref_to_client) = clients_map.get(&ClientId(1)) {
-            assert_eq!("5000, 10000, 15000, false".to_string(), 
-                       format!("{}", ref_to_client));
-        } else {
-            panic!("Client not found!");
+        // transactions after the merge are more recent than those of both maps
+        clients_map.execute_transaction(TransactionId(1), ClientId(1), Transaction::Deposit(1.), 
+                                        &config).unwrap();
+        assert_eq!(Some(3), clients_map.get(&ClientId(1)).unwrap().last_activity);
+    }
+
+    #[test]
+    // the disputes of merged clients and maps still expire, after the same number of transactions
+    fn dispute_ttl_2() {
+        let config = ProcessingConfig { dispute_ttl: Some(2), quiet: true, ..Default::default() };
+        let new_map = |n_clients| {
+            let mut clients_map = ClientMap::default();
+            for client_id in 1..=n_clients {
+                clients_map.insert(ClientId(client_id), Client::new(0., 0., false)).unwrap();
+            }
+            clients_map
+        };
+        let execute = |clients_map: &mut ClientMap, transaction_id, client_id, transaction| {
+            clients_map.execute_transaction(TransactionId(transaction_id), ClientId(client_id),
+                                            transaction, &config).unwrap();
+        };
+
+        // merging clients: the dispute of client 2 is now one of client 1
+        let mut clients_map = new_map(2);
+        execute(&mut clients_map, 1, 2, Transaction::Deposit(10.));
+        execute(&mut clients_map, 0, 2, Transaction::Dispute(TransactionId(1)));
+        clients_map.merge_clients(ClientId(1), ClientId(2)).unwrap();
+        execute(&mut clients_map, 2, 1, Transaction::Deposit(5.));
+        assert_eq!("5, 10, 15, false", format!("{}", clients_map.get(&ClientId(1)).unwrap()));
+        execute(&mut clients_map, 3, 1, Transaction::Deposit(5.));
+        assert_eq!("20, 0, 20, false", format!("{}", clients_map.get(&ClientId(1)).unwrap()));
+
+        // merging maps: the map with the dispute has executed fewer transactions than the other
+        let mut clients_map = new_map(1);
+        for transaction_id in 1..=5 {
+            execute(&mut clients_map, transaction_id, 1, Transaction::Deposit(1.));
         }
+        let mut other_clients_map = new_map(2);
+        execute(&mut other_clients_map, 1, 2, Transaction::Deposit(10.));
+        execute(&mut other_clients_map, 0, 2, Transaction::Dispute(TransactionId(1)));
+        clients_map.merge(other_clients_map).unwrap();
+        execute(&mut clients_map, 6, 1, Transaction::Deposit(1.));
+        assert_eq!("0, 10, 10, false", format!("{}", clients_map.get(&ClientId(2)).unwrap()));
+        execute(&mut clients_map, 7, 1, Transaction::Deposit(1.));
+        assert_eq!("10, 0, 10, false", format!("{}", clients_map.get(&ClientId(2)).unwrap()));
+    }
+
+    #[test]
+    // files can be processed into a map with another store, calling the commit hook with it
+    fn custom_store_read_csv_1() {
+        use std::rc::Rc;
+        use std::cell::RefCell;
+        use crate::config::CommitHook;
+        use crate::read_csv::execute_transactions_from_bytes;
+
+        let commits = Rc::new(RefCell::new(Vec::new()));
+        let commits_in_hook = Rc::clone(&commits);
+        let hook = CommitHook::new(2, move |clients_map| {
+            commits_in_hook.borrow_mut().push(clients_map.len())
+        });
+        let config = ProcessingConfig { commit_hook: Some(hook), ..Default::default() };
+        let mut clients_map = ClientMap::with_store(VecStore::default());
+        let report = execute_transactions_from_bytes(&mut clients_map, 
+                                                     b"deposit, 2, 1, 5\n\
+                                                       deposit, 1, 2, 10\n\
+                                                       deposit, 3, 3, 1\n\
+                                                       deposit, 3, 4, 1\n", 
+                                                     &config).unwrap();
+        assert_eq!(4, report.stats.applied);
+        assert_eq!(vec![2, 3], *commits.borrow());
+        assert_eq!(vec![ClientId(2), ClientId(1), ClientId(3)], 
+                   clients_map.clients.0.iter().map(|(id, _)| *id).collect::<Vec<_>>());
+
+        // all-or-nothing mode copies the store
+        let config = ProcessingConfig { all_or_nothing: true, quiet: true, ..Default::default() };
+        assert!(execute_transactions_from_bytes(&mut clients_map, b"deposit, 4, 5, 1\nfoo\n", 
+                                                &config).is_err());
+        assert_eq!(3, clients_map.len());
     }
 }
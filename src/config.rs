@@ -0,0 +1,860 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use crate::client::{ ClientMap, ClientStore, COLUMN_CLIENT, COLUMN_AVAILABLE, COLUMN_HELD, 
+                     COLUMN_TOTAL, COLUMN_LOCKED, COLUMN_OVERDRAFT_LIMIT, 
+                     COLUMN_AVAILABLE_INCLUDING_OVERDRAFT, COLUMN_NOTES };
+use crate::style::{ warning_style, stderr_is_term };
+use crate::parse::ParseOptions;
+use crate::amount::{ currency_decimal_places, STANDARD_DECIMAL_PLACES };
+pub use crate::parse::ExcessDecimalsPolicy;
+
+
+/// configuration of the transaction processing
+///
+/// Each policy has a lenient variant (the default), where the offending line is skipped with a
+/// warning, and a strict variant, where processing stops with an error.
+///
+/// # Example
+///
+/// ```
+/// use banking_exercise::config::ProcessingConfig;
+///
+/// // start from the strict configuration...
+/// let mut config = ProcessingConfig::strict();
+///
+/// // ...but only warn about transactions on locked accounts
+/// config.error_on_locked = false;
+/// ```
+#[derive(Debug)]
+pub struct ProcessingConfig {
+
+    /// character separating the fields of a line
+    pub delimiter: char,
+
+    /// skip the header lines, wherever they appear (see `[crate::read_csv::is_header]`)
+    pub detect_header: bool,
+
+    /// number of data lines (i.e., non-empty lines which are not headers) to skip at the start of
+    /// a file
+    pub skip_lines: usize,
+
+    /// maximum number of data lines to process after the skipped ones (unbounded if `None`)
+    pub take_lines: Option<usize>,
+
+    /// return an error (instead of a warning) on deposits or withdrawals with a negative amount
+    pub reject_negative_amounts: bool,
+
+    /// return an error (instead of a warning) on amounts with more significant digits than can be
+    /// represented
+    pub reject_precision_loss: bool,
+
+    /// return an error on client or transaction IDs with leading zeros, such as `01` (these are
+    /// accepted otherwise)
+    pub reject_leading_zero_ids: bool,
+
+    /// return an error on amounts ending with a decimal point, such as `100.`, as they may have
+    /// been truncated (these are accepted otherwise, as `100` and `100.0`)
+    pub reject_trailing_decimal_point: bool,
+
+    /// return an error on amounts with an explicit leading `+`, such as `+100.00` (these are
+    /// accepted otherwise, as `100.00`)
+    pub reject_leading_plus: bool,
+
+    /// return an error (instead of a warning) on lines with an unknown transaction type
+    pub error_on_unknown_type: bool,
+
+    /// return an error (instead of a warning) on transactions for a locked account
+    pub error_on_locked: bool,
+
+    /// return an error on disputes, resolves, or chargebacks which would not change anything
+    /// (these are silently ignored otherwise)
+    pub error_on_noop_dispute: bool,
+
+    /// return an error (instead of a warning) on deposits or withdrawals with the same ID as an
+    /// earlier one of the same client but a different type or amount (exact duplicates are
+    /// always ignored with a warning)
+    pub error_on_conflicting_duplicate: bool,
+
+    /// return an error (instead of a warning) on resolves for a transaction which has already
+    /// been charged back, which suggests contradictory events from the upstream system
+    pub error_on_resolve_after_chargeback: bool,
+
+    /// return an error (instead of a warning) on deposits or withdrawals whose ID is lower than
+    /// that of an earlier one for the same client (only checked if `check_id_order` is set)
+    pub error_on_out_of_order_id: bool,
+
+    /// warn about deposits or withdrawals whose ID is lower than that of an earlier one for the
+    /// same client, which suggests that the transactions have been reordered; they are executed
+    /// nevertheless
+    pub check_id_order: bool,
+
+    /// apply the transactions only if no line would be skipped; otherwise, return an error listing
+    /// all such lines and leave the clients unchanged
+    ///
+    /// The `[ProcessingConfig::commit_hook]` is then not called during processing, but once after
+    /// the transactions have been applied (if any was).
+    pub all_or_nothing: bool,
+
+    /// record the outcome of each data line, applied or not, in
+    /// `[crate::read_csv::ProcessingReport::line_results]`
+    pub record_line_results: bool,
+
+    /// stop at the first line which would be skipped with a warning, returning an error instead
+    /// (processing keeps going by default)
+    pub fail_fast: bool,
+
+    /// require clients to be created by an explicit `open` transaction (transactions on other
+    /// clients are skipped with a warning)
+    pub require_explicit_open: bool,
+
+    /// skip, with a warning, the files which have already been processed into the same
+    /// `[ClientMap]`, identified by a fingerprint of their content (see
+    /// `[crate::read_csv::fingerprint]`), so that processing a file twice has no effect
+    ///
+    /// This only applies to `[crate::read_csv::execute_transactions_from_csv]` and
+    /// `[crate::read_csv::execute_transactions_from_bytes]`; files are recorded once processed
+    /// without error.
+    pub skip_processed_files: bool,
+
+    /// defer the disputes, resolves, and chargebacks referring to a transaction which is not (yet)
+    /// in the history of their client, up to the given number of lines at a time (not deferred if
+    /// `None`)
+    ///
+    /// Deferred lines are retried, in order, after each later deposit or withdrawal of the same
+    /// client, and a last time at the end of the file, where those still referring to an unknown
+    /// transaction have no effect. Lines following a deferred one for the same transaction are
+    /// deferred as well, to keep their order. Once the bound is reached, lines are executed
+    /// immediately, as without deferral.
+    pub defer_unknown_disputes: Option<usize>,
+
+    /// queue the transactions on locked accounts (instead of skipping them), to be executed when
+    /// the account is unlocked (see `[ClientMap::unlock]`)
+    pub queue_when_locked: bool,
+
+    /// currency symbols or codes allowed before or after the amounts (for instance `$` for
+    /// `$100.00`, or `USD` for `100.00 USD`), which are stripped before parsing them
+    pub currency_symbols: Vec<String>,
+
+    /// prefixes of the comment lines, which are skipped (after removing the leading whitespace)
+    pub comment_prefixes: Vec<String>,
+
+    /// exchange rates to a base currency, by currency code (for instance `EUR` to `1.08` for a
+    /// US dollar base, the base currency having a rate of `1`)
+    ///
+    /// If not empty, the field after the amount is the currency of the amount, and the amounts
+    /// of deposits and withdrawals are multiplied by its rate before being applied; lines with a
+    /// currency which is not in the table are skipped with a warning. The currency field of
+    /// disputes, resolves, and chargebacks, if any, is ignored.
+    pub rates: HashMap<String, f64>,
+
+    /// thousands separator allowed in the amounts (for instance `,` for `1,000.00`), which is
+    /// removed before parsing them
+    ///
+    /// If it is the same as `delimiter`, the amounts containing it must be quoted, as in
+    /// `"1,000.00"`.
+    pub grouping_separator: Option<char>,
+
+    /// number of decimal places to which the amounts of deposits and withdrawals are rounded
+    /// before being applied (not rounded if `None`)
+    ///
+    /// Unlike `[OutputConfig::decimal_places]`, which only rounds the printed balances, this
+    /// changes the balances themselves: rounding errors do not accumulate across transactions.
+    pub round_amounts: Option<u32>,
+
+    /// maximum number of undisputed deposits and withdrawals kept in the history of each client
+    /// (unbounded if `None`)
+    ///
+    /// The least recently used ones are evicted first. Evicted transactions can not be disputed
+    /// afterwards, but their IDs are kept, so that a transaction replaying one of them is still
+    /// ignored as a duplicate. The memory used by a client thus still grows with the number of its
+    /// transactions, by one ID per evicted transaction instead of a full history entry.
+    pub history_capacity: Option<usize>,
+
+    /// what to do with lines containing more fields than expected (unless they are kept as notes)
+    pub extra_data: ExtraDataPolicy,
+
+    /// keep the fields after the expected ones as free-form notes on the transaction, such as
+    /// `paid by card` in `deposit, 1, 2, 100, paid by card`, instead of handling them as extra
+    /// data; the notes of the last transaction of each client can be shown in the output (see
+    /// `[OutputConfig::show_notes]`)
+    pub notes_column: bool,
+
+    /// what to do with withdrawals exceeding the available funds
+    pub over_withdrawal: OverWithdrawalPolicy,
+
+    /// how disputes affect the funds of a client
+    pub dispute_fund_mode: DisputeFundMode,
+
+    /// how disputes of withdrawals affect the funds of a client (if `dispute_fund_mode` is
+    /// `[DisputeFundMode::MoveToHeld]`)
+    pub withdrawal_dispute: WithdrawalDisputePolicy,
+
+    /// number of decimal places allowed in the amounts, `[crate::amount::STANDARD_DECIMAL_PLACES]`
+    /// by default (see also `[ProcessingConfig::set_currency]`)
+    pub max_decimal_places: usize,
+
+    /// what to do with amounts having more than `max_decimal_places` decimal places
+    pub excess_decimals: ExcessDecimalsPolicy,
+
+    /// what to do when the total funds of a client go beyond `[crate::amount::MAX_SAFE_AMOUNT]`
+    pub safe_integer: SafeIntegerPolicy,
+
+    /// maximum held funds per client (unbounded if `None`); disputes which would bring the held
+    /// funds above it are skipped with a warning, leaving the funds available
+    pub max_held: Option<f64>,
+
+    /// maximum cumulative withdrawals per client (unbounded if `None`), such as a daily ATM limit;
+    /// withdrawals which would bring them above it are skipped with a warning, and the totals are
+    /// reset with `[ClientMap::clear_withdrawals]`
+    pub withdrawal_limit: Option<f64>,
+
+    /// amount by which a withdrawal may exceed the available funds (including the overdraft),
+    /// such as a rounding residual (none if `None`); such a withdrawal drains the available funds
+    /// instead of being rejected
+    pub withdrawal_tolerance: Option<f64>,
+
+    /// maximum number of clients (unbounded if `None`), to guard against a corrupted client ID
+    /// column creating many accounts; transactions which would create a client beyond it are
+    /// skipped with a warning
+    pub max_clients: Option<usize>,
+
+    /// number of processed transactions after which a dispute which has not been resolved or
+    /// charged back expires (never if `None`); expired disputes are resolved with a warning,
+    /// returning their funds to the available ones
+    pub dispute_ttl: Option<u64>,
+
+    /// whether `stderr` is a terminal, used to style the warnings
+    pub stderr_is_term: bool,
+
+    /// do not print the warnings
+    pub quiet: bool,
+
+    /// a callback invoked periodically during processing, for instance to save the data
+    pub commit_hook: Option<CommitHook>,
+
+    /// a callback receiving the warnings instead of `stderr` (if `None`, they are printed to
+    /// `stderr`)
+    pub warning_hook: Option<WarningHook>,
+}
+
+
+impl ProcessingConfig {
+
+    /// Create a configuration with the strict variant of each policy
+    pub fn strict() -> Self {
+        let mut config = ProcessingConfig::default();
+        config.set_strict(true);
+        config
+    }
+
+    /// set all the policies to their strict (`true`) or lenient (`false`) variant
+    pub fn set_strict(&mut self, strict: bool) {
+        self.reject_negative_amounts = strict;
+        self.reject_precision_loss = strict;
+        self.reject_leading_zero_ids = strict;
+        self.reject_trailing_decimal_point = strict;
+        self.reject_leading_plus = strict;
+        self.error_on_unknown_type = strict;
+        self.error_on_locked = strict;
+        self.error_on_noop_dispute = strict;
+        self.error_on_conflicting_duplicate = strict;
+        self.error_on_resolve_after_chargeback = strict;
+        self.error_on_out_of_order_id = strict;
+    }
+
+    /// print a warning to `stderr`, or pass it to the warning hook if set, unless in quiet mode
+    pub fn warn(&self, message: String) {
+        if self.quiet { return; }
+        match &self.warning_hook {
+            Some(hook) => (hook.callback.borrow_mut())(&message),
+            None => eprintln!("{}", warning_style(message, self.stderr_is_term)),
+        }
+    }
+
+    /// only allow the decimal places of a currency, given by its ISO 4217 code (see
+    /// `[crate::amount::currency_decimal_places]`), rejecting amounts with more of them
+    ///
+    /// # Errors
+    ///
+    /// This function returns an `[UnknownCurrencyError]` if the code is not made of three
+    /// letters; the configuration is then unchanged.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use banking_exercise::config::{ ProcessingConfig, ExcessDecimalsPolicy };
+    ///
+    /// let mut config = ProcessingConfig::default();
+    /// config.set_currency("JPY").unwrap();
+    /// assert_eq!(0, config.max_decimal_places);
+    /// assert_eq!(ExcessDecimalsPolicy::Reject, config.excess_decimals);
+    /// ```
+    pub fn set_currency(&mut self, code: &str) -> Result<(), UnknownCurrencyError> {
+        let places = currency_decimal_places(code)
+            .ok_or_else(|| UnknownCurrencyError(code.to_string()))?;
+        self.max_decimal_places = places;
+        self.excess_decimals = ExcessDecimalsPolicy::Reject;
+        Ok(())
+    }
+
+    /// the options used to parse each line (see `[crate::parse::parse_record]`)
+    pub fn parse_options(&self) -> ParseOptions<'_> {
+        ParseOptions {
+            delimiter: self.delimiter,
+            currency_symbols: &self.currency_symbols,
+            grouping_separator: self.grouping_separator,
+            max_decimal_places: self.max_decimal_places,
+            excess_decimals: self.excess_decimals,
+            reject_precision_loss: self.reject_precision_loss,
+            reject_leading_zero_ids: self.reject_leading_zero_ids,
+            reject_trailing_decimal_point: self.reject_trailing_decimal_point,
+            reject_leading_plus: self.reject_leading_plus,
+        }
+    }
+}
+
+
+impl Default for ProcessingConfig {
+    fn default() -> Self {
+        ProcessingConfig {
+            delimiter: ',',
+            detect_header: true,
+            skip_lines: 0,
+            take_lines: None,
+            reject_negative_amounts: false,
+            reject_precision_loss: false,
+            reject_leading_zero_ids: false,
+            reject_trailing_decimal_point: false,
+            reject_leading_plus: false,
+            error_on_unknown_type: false,
+            error_on_locked: false,
+            error_on_noop_dispute: false,
+            error_on_conflicting_duplicate: false,
+            error_on_resolve_after_chargeback: false,
+            error_on_out_of_order_id: false,
+            check_id_order: false,
+            all_or_nothing: false,
+            record_line_results: false,
+            fail_fast: false,
+            require_explicit_open: false,
+            skip_processed_files: false,
+            defer_unknown_disputes: None,
+            queue_when_locked: false,
+            currency_symbols: Vec::new(),
+            comment_prefixes: vec!["#".to_string()],
+            rates: HashMap::new(),
+            grouping_separator: None,
+            round_amounts: None,
+            history_capacity: None,
+            extra_data: ExtraDataPolicy::WarnAndApply,
+            notes_column: false,
+            over_withdrawal: OverWithdrawalPolicy::Reject,
+            dispute_fund_mode: DisputeFundMode::MoveToHeld,
+            withdrawal_dispute: WithdrawalDisputePolicy::Hold,
+            safe_integer: SafeIntegerPolicy::Warn,
+            max_decimal_places: STANDARD_DECIMAL_PLACES,
+            excess_decimals: ExcessDecimalsPolicy::Accept,
+            max_held: None,
+            withdrawal_limit: None,
+            withdrawal_tolerance: None,
+            max_clients: None,
+            dispute_ttl: None,
+            stderr_is_term: stderr_is_term(),
+            quiet: false,
+            commit_hook: None,
+            warning_hook: None,
+        }
+    }
+}
+
+
+/// policy for transactions bringing the total funds of a client beyond
+/// `[crate::amount::MAX_SAFE_AMOUNT]` (2^53), above which integer amounts lose precision
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SafeIntegerPolicy {
+
+    /// execute the transaction silently
+    Ignore,
+
+    /// execute the transaction with a warning
+    #[default]
+    Warn,
+
+    /// reject deposits which would bring the total funds beyond the limit, returning an
+    /// `[crate::client::UnsafeTotalError]` (skipped with a warning when reading a file); other
+    /// transactions, which can only cross it in unusual cases (such as disputes of withdrawals),
+    /// are executed with a warning
+    Reject,
+}
+
+
+/// policy for lines containing more fields than expected, such as `deposit, 1, 2, 100, junk`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExtraDataPolicy {
+
+    /// print a warning and execute the transaction, ignoring the additional fields
+    #[default]
+    WarnAndApply,
+
+    /// skip the line with a warning, as other invalid lines
+    WarnAndSkip,
+
+    /// execute the transaction silently, ignoring the additional fields
+    Silent,
+}
+
+
+/// policy for withdrawals exceeding the available funds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverWithdrawalPolicy {
+
+    /// ignore the withdrawal
+    #[default]
+    Reject,
+
+    /// interpret the withdrawal as the reversal of a disputed deposit with the same amount
+    ///
+    /// The deposit is removed from the history and its funds from the held ones (or from the
+    /// available ones with `[DisputeFundMode::FlagOnly]`), closing the dispute. If several
+    /// disputed deposits match, the one with the lowest ID is reversed. Since the funds of an
+    /// undisputed deposit are in the available ones, such a deposit could only be reversed if the
+    /// available funds are sufficient, in which case the withdrawal is executed normally; the
+    /// withdrawal is thus ignored if no disputed deposit matches.
+    ReverseDeposit,
+}
+
+
+/// how disputes affect the funds of a client
+///
+/// The mode should not change while a dispute is open: a chargeback removes the funds from where
+/// the dispute put them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DisputeFundMode {
+
+    /// move the disputed funds from the available to the held ones until the dispute is resolved
+    /// or charged back
+    #[default]
+    MoveToHeld,
+
+    /// only flag the transaction as disputed, without moving any funds; a chargeback removes the
+    /// funds of a disputed deposit from the available ones
+    FlagOnly,
+}
+
+
+/// how disputes of withdrawals affect the funds of a client
+///
+/// Unlike the funds of a disputed deposit, those of a disputed withdrawal have left the account;
+/// the dispute thus adds them back, either to the held funds or to the available ones. This
+/// applies even if the withdrawal emptied the account: after a deposit of 100 and a withdrawal of
+/// 100, disputing the withdrawal gives 0 available and 100 held funds by default, or 100 available
+/// and 0 held funds when crediting them, the withdrawal being flagged as disputed in both cases.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WithdrawalDisputePolicy {
+
+    /// add the funds to the held ones; they are moved to the available ones if the dispute is
+    /// resolved, and remain held if it is charged back
+    #[default]
+    Hold,
+
+    /// credit the funds to the available ones pending resolution; they are removed if the dispute
+    /// is resolved, and remain available if it is charged back
+    CreditAvailable,
+}
+
+
+/// a callback invoked with the current `[ClientMap]` every `commit_interval` applied transactions
+///
+/// # Example
+///
+/// ```
+/// use banking_exercise::config::{ ProcessingConfig, CommitHook };
+///
+/// // print the number of clients every 1,000 transactions
+/// let mut config = ProcessingConfig::default();
+/// config.commit_hook = Some(CommitHook::new(1_000, |clients_map| {
+///     eprintln!("{} clients so far", clients_map.len());
+/// }));
+/// ```
+pub struct CommitHook {
+    commit_interval: usize,
+    callback: RefCell<CommitCallback>,
+}
+
+
+/// type of the callback used by `[CommitHook]`
+///
+/// The callback receives the map whatever its `[ClientStore]`, viewed as a
+/// `ClientMap<dyn ClientStore>`.
+pub type CommitCallback = Box<dyn FnMut(&ClientMap<dyn ClientStore + '_>)>;
+
+impl CommitHook {
+
+    /// Create a new `[CommitHook]`
+    ///
+    /// # Panics
+    ///
+    /// This function panics if `commit_interval` is zero.
+    pub fn new(commit_interval: usize, 
+               callback: impl FnMut(&ClientMap<dyn ClientStore + '_>) + 'static) -> Self 
+    {
+        assert!(commit_interval > 0, "the commit interval must be positive");
+        CommitHook { commit_interval, callback: RefCell::new(Box::new(callback)) }
+    }
+
+    /// the number of applied transactions between two calls to the callback
+    pub fn commit_interval(&self) -> usize {
+        self.commit_interval
+    }
+
+    // call the callback if the number of applied transactions is a multiple of the interval
+    pub(crate) fn notify(&self, n_applied: usize, clients_map: &ClientMap<dyn ClientStore + '_>) {
+        if n_applied.is_multiple_of(self.commit_interval) {
+            self.call(clients_map);
+        }
+    }
+
+    // call the callback unconditionally
+    pub(crate) fn call(&self, clients_map: &ClientMap<dyn ClientStore + '_>) {
+        (self.callback.borrow_mut())(clients_map);
+    }
+}
+
+impl std::fmt::Debug for CommitHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "CommitHook {{ commit_interval: {} }}", self.commit_interval)
+    }
+}
+
+
+/// a callback receiving each warning, without styling, instead of `stderr`
+///
+/// This can be used to collect the warnings, or to write them to the same stream as the output so
+/// that their order relative to it is deterministic.
+///
+/// # Example
+///
+/// ```
+/// use std::rc::Rc;
+/// use std::cell::RefCell;
+/// use banking_exercise::config::{ ProcessingConfig, WarningHook };
+///
+/// // collect the warnings
+/// let warnings = Rc::new(RefCell::new(Vec::new()));
+/// let warnings_in_hook = Rc::clone(&warnings);
+/// let config = ProcessingConfig { 
+///     warning_hook: Some(WarningHook::new(move |message| {
+///         warnings_in_hook.borrow_mut().push(message.to_string());
+///     })), 
+///     ..Default::default() 
+/// };
+/// config.warn("Warning: something happened".to_string());
+/// assert_eq!(vec!["Warning: something happened".to_string()], *warnings.borrow());
+/// ```
+pub struct WarningHook {
+    callback: RefCell<WarningCallback>,
+}
+
+
+/// type of the callback used by `[WarningHook]`
+pub type WarningCallback = Box<dyn FnMut(&str)>;
+
+impl WarningHook {
+
+    /// Create a new `[WarningHook]`
+    pub fn new(callback: impl FnMut(&str) + 'static) -> Self {
+        WarningHook { callback: RefCell::new(Box::new(callback)) }
+    }
+}
+
+impl std::fmt::Debug for WarningHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "WarningHook")
+    }
+}
+
+
+/// how the amounts are formatted in the output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AmountFormat {
+
+    /// the shortest representation identifying the stored value, after rounding to
+    /// `[OutputConfig::decimal_places]` if set
+    #[default]
+    Shortest,
+
+    /// a fixed number of decimal places, padded with zeros if needed
+    Fixed(u32),
+
+    /// the exact stored value, with all its digits (see `[crate::amount::exact_value]`); 
+    /// `[OutputConfig::decimal_places]` is ignored
+    ExactValue,
+}
+
+
+/// order of the clients in the output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortOrder {
+
+    /// increasing order of the client IDs
+    #[default]
+    ClientId,
+
+    /// the clients whose last transaction was executed most recently first, and those without
+    /// any executed transaction last (see `[crate::client::Client::last_activity]`)
+    LastActivity,
+}
+
+
+/// configuration of the output
+#[derive(Debug, Clone, Default)]
+pub struct OutputConfig {
+
+    /// only show the clients with available or held funds, or a locked account
+    pub non_empty_only: bool,
+
+    /// only show the clients with a locked account
+    pub locked_only: bool,
+
+    /// show additional columns (overdraft limit and available funds including the overdraft)
+    pub verbose: bool,
+
+    /// number of decimal places to which the amounts are rounded in the output (not rounded if
+    /// `None`)
+    pub decimal_places: Option<u32>,
+
+    /// how the amounts are formatted in the output
+    pub amount_format: AmountFormat,
+
+    /// show the output as a table with aligned columns rather than as CSV
+    pub pretty: bool,
+
+    /// do not show the header line
+    pub omit_header: bool,
+
+    /// add a row with the totals over the displayed clients, with `TOTAL` in the first column and
+    /// the number of locked accounts in the `locked` one
+    pub totals_row: bool,
+
+    /// add a last column with the notes of the last transaction of each client (see
+    /// `[ProcessingConfig::notes_column]`), quoted if they contain a comma or a double quote
+    pub show_notes: bool,
+
+    /// order of the clients
+    pub sort_order: SortOrder,
+
+    /// columns to show, in order, overriding `verbose` and `show_notes` (the default columns if
+    /// `None`)
+    pub columns: Option<Vec<Column>>,
+
+    /// labels shown in the header instead of the names of some columns
+    pub column_labels: HashMap<Column, String>,
+}
+
+
+impl OutputConfig {
+
+    /// the header of the output with this configuration
+    pub fn header(&self) -> String {
+        self.shown_columns().into_iter().map(|column| self.column_label(column))
+            .collect::<Vec<_>>().join(", ")
+    }
+
+    /// the columns shown with this configuration, in order
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use banking_exercise::config::{ Column, OutputConfig };
+    ///
+    /// let config = OutputConfig { columns: Some(vec![Column::Client, Column::Total]), 
+    ///                             ..Default::default() };
+    /// assert_eq!(vec![Column::Client, Column::Total], config.shown_columns());
+    /// ```
+    pub fn shown_columns(&self) -> Vec<Column> {
+        if let Some(columns) = &self.columns {
+            return columns.clone();
+        }
+        let columns: &[Column] = if self.verbose { &Column::VERBOSE } else { &Column::DEFAULT };
+        columns.iter().copied()
+            .chain(self.show_notes.then_some(Column::Notes))
+            .collect()
+    }
+
+    /// names of the columns shown with this configuration, in order
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use banking_exercise::config::OutputConfig;
+    ///
+    /// let config = OutputConfig { show_notes: true, ..Default::default() };
+    /// assert_eq!(vec!["client", "available", "held", "total", "locked", "notes"], 
+    ///            config.column_names());
+    /// ```
+    pub fn column_names(&self) -> Vec<&'static str> {
+        self.shown_columns().into_iter().map(Column::name).collect()
+    }
+
+    /// label of a column in the header: the one set in `column_labels`, or its name
+    pub fn column_label(&self, column: Column) -> &str {
+        self.column_labels.get(&column).map_or(column.name(), String::as_str)
+    }
+}
+
+
+/// a column of the output
+///
+/// Columns are parsed from their names, such as `available_including_overdraft`.
+///
+/// # Example
+///
+/// ```
+/// use banking_exercise::config::Column;
+///
+/// assert_eq!(Ok(Column::OverdraftLimit), "overdraft_limit".parse());
+/// assert!("balance".parse::<Column>().is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Column {
+
+    /// the client ID (or `TOTAL` in the row of totals)
+    Client,
+
+    /// the available funds
+    Available,
+
+    /// the held funds
+    Held,
+
+    /// the total funds
+    Total,
+
+    /// whether the account is locked (or the number of locked accounts in the row of totals)
+    Locked,
+
+    /// the overdraft limit (shown by default in the verbose output)
+    OverdraftLimit,
+
+    /// the available funds including the overdraft (shown by default in the verbose output)
+    AvailableIncludingOverdraft,
+
+    /// the notes of the last transaction (shown by default with `[OutputConfig::show_notes]`)
+    Notes,
+}
+
+impl Column {
+
+    /// all the columns, in the order of the verbose output followed by the notes
+    pub const ALL: [Column; 8] = [Column::Client, Column::Available, Column::Held, Column::Total, 
+                                  Column::Locked, Column::OverdraftLimit, 
+                                  Column::AvailableIncludingOverdraft, Column::Notes];
+
+    /// the columns shown by default, in order
+    pub const DEFAULT: [Column; 5] = [Column::Client, Column::Available, Column::Held, 
+                                      Column::Total, Column::Locked];
+
+    /// the columns shown by default in the verbose output, in order
+    pub const VERBOSE: [Column; 7] = [Column::Client, Column::Available, Column::Held, 
+                                      Column::Total, Column::Locked, Column::OverdraftLimit, 
+                                      Column::AvailableIncludingOverdraft];
+
+    /// name of the column, shown in the header unless it has a label
+    pub const fn name(self) -> &'static str {
+        match self {
+            Column::Client => COLUMN_CLIENT,
+            Column::Available => COLUMN_AVAILABLE,
+            Column::Held => COLUMN_HELD,
+            Column::Total => COLUMN_TOTAL,
+            Column::Locked => COLUMN_LOCKED,
+            Column::OverdraftLimit => COLUMN_OVERDRAFT_LIMIT,
+            Column::AvailableIncludingOverdraft => COLUMN_AVAILABLE_INCLUDING_OVERDRAFT,
+            Column::Notes => COLUMN_NOTES,
+        }
+    }
+}
+
+impl std::str::FromStr for Column {
+    type Err = UnknownColumnError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Column::ALL.into_iter().find(|column| column.name() == name)
+            .ok_or_else(|| UnknownColumnError(name.to_string()))
+    }
+}
+
+impl std::fmt::Display for Column {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+
+/// an error raised when parsing the name of a column which does not exist
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct UnknownColumnError(pub String);
+
+impl std::fmt::Display for UnknownColumnError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Unknown column {}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownColumnError {}
+
+
+/// an error raised when a currency code is not valid
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct UnknownCurrencyError(pub String);
+
+impl std::fmt::Display for UnknownCurrencyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Unknown currency code {}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownCurrencyError {}
+
+
+/// an error raised when a line violates one of the strict policies
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct StrictModeError {
+    pub n_line: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for StrictModeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} (line {})", self.message, self.n_line)
+    }
+}
+
+impl std::error::Error for StrictModeError {}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn strict_overrides() {
+
+        // the default configuration is lenient
+        let config = ProcessingConfig::default();
+        assert!(!config.reject_negative_amounts && !config.error_on_unknown_type
+                && !config.error_on_locked && !config.error_on_noop_dispute);
+
+        // the strict configuration enables all the policies...
+        let mut config = ProcessingConfig::strict();
+        assert!(config.reject_negative_amounts && config.error_on_unknown_type
+                && config.error_on_locked && config.error_on_noop_dispute);
+
+        // ...which can still be disabled individually
+        config.error_on_locked = false;
+        assert!(config.reject_negative_amounts && !config.error_on_locked);
+    }
+}
@@ -1,3 +1,18 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+// the parsing of lines only needs `alloc`; reading files and executing transactions need `std`
+extern crate alloc;
+
+#[cfg(feature = "std")]
 pub mod style;
+pub mod amount;
+#[cfg(feature = "std")]
 pub mod client;
+#[cfg(feature = "std")]
+pub mod config;
 pub mod transaction;
+pub mod parse;
+#[cfg(feature = "std")]
+pub mod read_csv;
+#[cfg(feature = "test-util")]
+pub mod test_util;
@@ -1,29 +1,458 @@
-mod client;
-mod style;
-mod transaction;
-mod read_csv;
-
 use std::env;
-use client::ClientMap;
-use read_csv::execute_transactions_from_csv;
+use std::fs::File;
+use std::io::{ prelude::*, BufReader };
+use std::time::Instant;
+use banking_exercise::client::ClientMap;
+use banking_exercise::config::{ ProcessingConfig, OutputConfig, OverWithdrawalPolicy, 
+                                DisputeFundMode, WithdrawalDisputePolicy, AmountFormat, 
+                                ExtraDataPolicy, SafeIntegerPolicy, ExcessDecimalsPolicy, 
+                                SortOrder, WarningHook, Column };
+use banking_exercise::read_csv::{ execute_transactions_from_csv, parse_line, is_header, 
+                                  is_comment, explain_transaction, ProcessingReport, 
+                                  ProcessingError, LineResult, EXIT_USAGE, EXIT_IO_ERROR };
+use banking_exercise::transaction::TransactionId;
+#[cfg(feature = "reqwest")]
+use banking_exercise::read_csv::execute_transactions_from_url;
 
 fn main() {
     
-    // get an iterator to the command-line arguments
-    let mut args = env::args();
+    // get an iterator to the command-line arguments, skipping the first one
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    // print the version and exit
+    if args.iter().any(|arg| arg == "--version") {
+        println!("{}", version());
+        return;
+    }
+
+    // `parse` subcommand: only parse the file
+    if args.first().map(String::as_str) == Some("parse") {
+        let Some(file_name) = args.get(1) else {
+            eprintln!("ERROR: Usage: banking_exercise parse <file>");
+            std::process::exit(EXIT_USAGE);
+        };
+        if let Err(error) = print_parsed_transactions(file_name) {
+            eprintln!("ERROR: {}", error);
+            std::process::exit(EXIT_IO_ERROR);
+        }
+        return;
+    }
 
-    // skip the first one
-    args.next();
+    // `explain` subcommand: trace the effect of a transaction
+    if args.first().map(String::as_str) == Some("explain") {
+        let (file_name, transaction_id) = match (args.get(1), args.get(2)) {
+            (Some(file_name), Some(transaction_id)) => (file_name, transaction_id),
+            _ => {
+                eprintln!("ERROR: Usage: banking_exercise explain <file> <transaction ID>");
+                std::process::exit(EXIT_USAGE);
+            }
+        };
+        if let Err(error) = print_transaction_trace(file_name, transaction_id) {
+            eprintln!("ERROR: {}", error);
+            std::process::exit(1);
+        }
+        return;
+    }
 
-    // get the file name, or panic if it is not provided
-    let file_name = args.next().expect("ERROR: No file name provided");
+    // get the file name and the configuration
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("ERROR: {}", message);
+            std::process::exit(EXIT_USAGE);
+        }
+    };
 
     // create a new empty list of clients
     let mut client_list = ClientMap::default();
 
-    // execute the transactions from the file
-    execute_transactions_from_csv(&mut client_list, &file_name).unwrap();
+    // execute the transactions from the file, measuring the time it takes
+    let start = Instant::now();
+    let report = match execute_transactions(&mut client_list, &options.file_name, 
+                                            &options.processing) {
+        Ok(report) => report,
+        Err(error) => {
+            eprintln!("ERROR: {}", error);
+            std::process::exit(error.exit_code());
+        }
+    };
+    let elapsed = start.elapsed().as_secs_f64();
+
+    // print the timing information if required
+    let stats = report.stats;
+    if options.timing {
+        let n_transactions = stats.applied + stats.skipped;
+        eprintln!("Processed {} transactions ({} applied, {} skipped) in {:.3} s ({:.0} transactions per second)", 
+                  n_transactions, stats.applied, stats.skipped, elapsed, 
+                  n_transactions as f64 / elapsed);
+    }
+
+    // print a summary of the funds of the bank if required
+    if options.summary {
+        let clients_map = report.clients_map;
+        let (available, held, total) = clients_map.total_funds();
+        eprintln!("{} clients ({} locked): {} available, {} held ({} under dispute), {} total", 
+                  clients_map.len(), clients_map.locked_clients().len(), available, held, 
+                  clients_map.total_disputed_amount(), total);
+    }
+
+    // report the clients with a high number of transactions if required
+    if let Some(threshold) = options.high_activity_threshold {
+        for client_id in report.clients_map.high_activity_clients(threshold) {
+            eprintln!("Client {} has more than {} transactions", client_id, threshold);
+        }
+    }
+
+    // report the locked clients with remaining funds if required
+    if options.report_stranded {
+        for summary in report.clients_map.locked_with_funds() {
+            eprintln!("Client {} is locked with {} available and {} held funds", 
+                      summary.client, summary.available, summary.held);
+        }
+    }
+
+    // write the outcome of each line if required
+    if let Some(file_name) = &options.line_results_file {
+        if let Err(error) = write_line_results(file_name, &report.line_results) {
+            eprintln!("ERROR: Could not write the line results to {}: {}", file_name, error);
+            std::process::exit(EXIT_IO_ERROR);
+        }
+    }
+
+    // stream the client data as JSON Lines if required (only possible with the `serde` feature)
+    if options.json_lines {
+        #[cfg(feature = "serde")]
+        if let Err(error) = report.clients_map
+            .write_json_lines(std::io::BufWriter::new(std::io::stdout().lock())) {
+            eprintln!("ERROR: Could not write the client data: {}", error);
+            std::process::exit(EXIT_IO_ERROR);
+        }
+        return;
+    }
 
     // print the client data
-    print!("{}", client_list);
+    print!("{}", report.clients_map.display(&options.output));
+}
+
+
+// execute the transactions from a local file or, with the `reqwest` feature, from a URL
+fn execute_transactions<'a>(client_list: &'a mut ClientMap, file_name: &str, 
+                            config: &ProcessingConfig) 
+    -> Result<ProcessingReport<'a>, ProcessingError> 
+{
+    #[cfg(feature = "reqwest")]
+    if file_name.starts_with("http://") || file_name.starts_with("https://") {
+        return execute_transactions_from_url(client_list, file_name, config);
+    }
+    execute_transactions_from_csv(client_list, file_name, config)
+}
+
+
+// write the outcome of each data line to a file, one per line
+fn write_line_results(file_name: &str, line_results: &[LineResult]) -> std::io::Result<()> {
+    let mut file = std::io::BufWriter::new(File::create(file_name)?);
+    for line_result in line_results {
+        writeln!(file, "{}", line_result)?;
+    }
+    file.flush()
+}
+
+
+// version of the crate, with the hash of the git commit it was built from if available
+fn version() -> String {
+    let version = format!("banking_exercise {}", env!("CARGO_PKG_VERSION"));
+    match option_env!("BANKING_EXERCISE_GIT_HASH") {
+        Some(hash) => format!("{} ({})", version, hash),
+        None => version
+    }
+}
+
+
+// execute the transactions in a file, printing the steps of the history of one of them to
+// `stdout`
+fn print_transaction_trace(file_name: &str, transaction_id: &str) 
+    -> Result<(), Box<dyn std::error::Error>> 
+{
+    let transaction_id = match transaction_id.parse() {
+        Ok(id) => TransactionId(id),
+        Err(_) => return Err(format!("Invalid transaction ID {}", transaction_id).into()),
+    };
+    let config = ProcessingConfig { quiet: true, ..Default::default() };
+    let reader = BufReader::new(File::open(file_name)?);
+    let steps = explain_transaction(&mut ClientMap::default(), reader, transaction_id, &config)?;
+    if steps.is_empty() {
+        println!("Transaction {} not found", transaction_id.0);
+    }
+    for step in steps {
+        println!("{}", step);
+    }
+    Ok(())
+}
+
+
+// parse the transactions in a file without executing them, printing each transaction or parsing
+// error with its line number to `stdout`
+fn print_parsed_transactions(file_name: &str) -> std::io::Result<()> {
+    let config = ProcessingConfig::default();
+    let reader = BufReader::new(File::open(file_name)?);
+    for (n_line, line) in (1..).zip(reader.lines()) {
+        let line = line?;
+        if line.is_empty() || is_comment(&line, &config.comment_prefixes) { continue; }
+
+        // skip the headers, if any
+        if is_header(&line, config.delimiter) { continue; }
+
+        match parse_line(&line, n_line, &config) {
+            Ok((transaction_id, client_id, transaction)) => 
+                println!("line {}: client {}, transaction {}: {}", 
+                         n_line, client_id, transaction_id.0, transaction),
+            Err(warning) => println!("line {}: error: {}", n_line, warning),
+        }
+    }
+    Ok(())
+}
+
+
+// options passed on the command line
+struct Options {
+    file_name: String,
+    processing: ProcessingConfig,
+    output: OutputConfig,
+    timing: bool,
+    summary: bool,
+    high_activity_threshold: Option<usize>,
+    report_stranded: bool,
+    line_results_file: Option<String>,
+    json_lines: bool,
+}
+
+
+// parse the command-line arguments
+//
+// The `--strict` flag is applied first, so that the individual flags can override it whatever
+// their position.
+fn parse_args(args: &[String]) -> Result<Options, String> {
+
+    let mut processing = ProcessingConfig::default();
+    if args.iter().any(|arg| arg == "--strict") {
+        processing.set_strict(true);
+    }
+    let mut output = OutputConfig::default();
+    let mut timing = false;
+    let mut summary = false;
+    let mut high_activity_threshold = None;
+    let mut report_stranded = false;
+    let mut line_results_file = None;
+    let mut json_lines = false;
+
+    let mut file_name = None;
+    for arg in args {
+        match arg.as_str() {
+            "--strict" => (),
+            "--reject-negative" => processing.reject_negative_amounts = true,
+            "--no-reject-negative" => processing.reject_negative_amounts = false,
+            "--reject-precision-loss" => processing.reject_precision_loss = true,
+            "--no-reject-precision-loss" => processing.reject_precision_loss = false,
+            "--reject-leading-zero-ids" => processing.reject_leading_zero_ids = true,
+            "--no-reject-leading-zero-ids" => processing.reject_leading_zero_ids = false,
+            "--reject-trailing-decimal-point" => processing.reject_trailing_decimal_point = true,
+            "--no-reject-trailing-decimal-point" => 
+                processing.reject_trailing_decimal_point = false,
+            "--reject-leading-plus" => processing.reject_leading_plus = true,
+            "--no-reject-leading-plus" => processing.reject_leading_plus = false,
+            "--error-on-unknown-type" => processing.error_on_unknown_type = true,
+            "--no-error-on-unknown-type" => processing.error_on_unknown_type = false,
+            "--error-on-locked" => processing.error_on_locked = true,
+            "--no-error-on-locked" => processing.error_on_locked = false,
+            "--error-on-noop-dispute" => processing.error_on_noop_dispute = true,
+            "--no-error-on-noop-dispute" => processing.error_on_noop_dispute = false,
+            "--error-on-conflicting-duplicate" => processing.error_on_conflicting_duplicate = true,
+            "--no-error-on-conflicting-duplicate" => 
+                processing.error_on_conflicting_duplicate = false,
+            "--error-on-resolve-after-chargeback" => 
+                processing.error_on_resolve_after_chargeback = true,
+            "--no-error-on-resolve-after-chargeback" => 
+                processing.error_on_resolve_after_chargeback = false,
+            "--error-on-out-of-order-id" => processing.error_on_out_of_order_id = true,
+            "--no-error-on-out-of-order-id" => processing.error_on_out_of_order_id = false,
+            "--check-id-order" => processing.check_id_order = true,
+            "--no-check-id-order" => processing.check_id_order = false,
+            "--no-header-detection" => processing.detect_header = false,
+            "--require-explicit-open" => processing.require_explicit_open = true,
+            "--reverse-over-withdrawals" => 
+                processing.over_withdrawal = OverWithdrawalPolicy::ReverseDeposit,
+            "--dispute-flag-only" => processing.dispute_fund_mode = DisputeFundMode::FlagOnly,
+            "--credit-disputed-withdrawals" => 
+                processing.withdrawal_dispute = WithdrawalDisputePolicy::CreditAvailable,
+            "--fail-fast" => processing.fail_fast = true,
+            "--all-or-nothing" => processing.all_or_nothing = true,
+            "--keep-going" => processing.fail_fast = false,
+            "--non-empty" => output.non_empty_only = true,
+            "--locked-only" => output.locked_only = true,
+            "--verbose" => output.verbose = true,
+            "--pretty" => output.pretty = true,
+            flag if flag.starts_with("--columns=") => {
+                let names = flag["--columns=".len()..].split(',').map(str::trim);
+                match names.map(str::parse).collect() {
+                    Ok(columns) => output.columns = Some(columns),
+                    Err(error) => return Err(format!("{} in {}", error, flag)),
+                }
+            },
+            flag if flag.starts_with("--column-label=") => {
+                let column_label = flag["--column-label=".len()..].split_once(':')
+                    .and_then(|(name, label)| Some((name.parse::<Column>().ok()?, label)));
+                match column_label {
+                    Some((column, label)) => 
+                        output.column_labels.insert(column, label.to_string()),
+                    None => return Err(format!("Invalid column label in {}", flag)),
+                };
+            },
+            "--no-header" => output.omit_header = true,
+            "--totals-row" => output.totals_row = true,
+            "--sort=client" => output.sort_order = SortOrder::ClientId,
+            "--sort=last-activity" => output.sort_order = SortOrder::LastActivity,
+            "--notes" => {
+                processing.notes_column = true;
+                output.show_notes = true;
+            },
+            "--timing" => timing = true,
+            "--summary" => summary = true,
+            "--report-stranded" => report_stranded = true,
+            "--quiet" => processing.quiet = true,
+            "--warnings-to-stdout" => processing.warning_hook = 
+                Some(WarningHook::new(|message| println!("{}", message))),
+            flag if flag.starts_with("--max-held=") => {
+                match flag["--max-held=".len()..].parse() {
+                    Ok(max_held) => processing.max_held = Some(max_held),
+                    Err(_) => return Err(format!("Invalid amount in {}", flag)),
+                }
+            },
+            flag if flag.starts_with("--withdrawal-limit=") => {
+                match flag["--withdrawal-limit=".len()..].parse() {
+                    Ok(limit) => processing.withdrawal_limit = Some(limit),
+                    Err(_) => return Err(format!("Invalid amount in {}", flag)),
+                }
+            },
+            flag if flag.starts_with("--withdrawal-tolerance=") => {
+                match flag["--withdrawal-tolerance=".len()..].parse() {
+                    Ok(tolerance) => processing.withdrawal_tolerance = Some(tolerance),
+                    Err(_) => return Err(format!("Invalid amount in {}", flag)),
+                }
+            },
+            flag if flag.starts_with("--max-clients=") => {
+                match flag["--max-clients=".len()..].parse() {
+                    Ok(max_clients) => processing.max_clients = Some(max_clients),
+                    Err(_) => return Err(format!("Invalid number of clients in {}", flag)),
+                }
+            },
+            flag if flag.starts_with("--dispute-ttl=") => {
+                match flag["--dispute-ttl=".len()..].parse() {
+                    Ok(ttl) => processing.dispute_ttl = Some(ttl),
+                    Err(_) => return Err(format!("Invalid number of transactions in {}", flag)),
+                }
+            },
+            flag if flag.starts_with("--defer-disputes=") => {
+                match flag["--defer-disputes=".len()..].parse() {
+                    Ok(bound) => processing.defer_unknown_disputes = Some(bound),
+                    Err(_) => return Err(format!("Invalid number of lines in {}", flag)),
+                }
+            },
+            flag if flag.starts_with("--high-activity=") => {
+                match flag["--high-activity=".len()..].parse() {
+                    Ok(threshold) => high_activity_threshold = Some(threshold),
+                    Err(_) => return Err(format!("Invalid number of transactions in {}", flag)),
+                }
+            },
+            flag if flag.starts_with("--line-results=") => {
+                line_results_file = Some(flag["--line-results=".len()..].to_string());
+                processing.record_line_results = true;
+            },
+            "--extra-data=apply" => processing.extra_data = ExtraDataPolicy::WarnAndApply,
+            "--extra-data=skip" => processing.extra_data = ExtraDataPolicy::WarnAndSkip,
+            "--extra-data=silent" => processing.extra_data = ExtraDataPolicy::Silent,
+            flag if flag.starts_with("--skip=") => 
+                processing.skip_lines = parse_number_of_lines(flag)?,
+            flag if flag.starts_with("--take=") => 
+                processing.take_lines = Some(parse_number_of_lines(flag)?),
+            "--large-totals=ignore" => processing.safe_integer = SafeIntegerPolicy::Ignore,
+            "--large-totals=warn" => processing.safe_integer = SafeIntegerPolicy::Warn,
+            "--large-totals=reject" => processing.safe_integer = SafeIntegerPolicy::Reject,
+            flag if flag.starts_with("--currency=") => processing
+                .set_currency(&flag["--currency=".len()..])
+                .map_err(|error| error.to_string())?,
+            "--excess-decimals=accept" => processing.excess_decimals = ExcessDecimalsPolicy::Accept,
+            "--excess-decimals=truncate" => 
+                processing.excess_decimals = ExcessDecimalsPolicy::Truncate,
+            "--excess-decimals=round" => processing.excess_decimals = ExcessDecimalsPolicy::Round,
+            "--excess-decimals=reject" => processing.excess_decimals = ExcessDecimalsPolicy::Reject,
+            flag if flag.starts_with("--round-amounts=") => 
+                processing.round_amounts = Some(parse_decimal_places(flag)?),
+            flag if flag.starts_with("--decimal-places=") => 
+                output.decimal_places = Some(parse_decimal_places(flag)?),
+            flag if flag.starts_with("--fixed-decimal-places=") => 
+                output.amount_format = AmountFormat::Fixed(parse_decimal_places(flag)?),
+            "--exact-amounts" => output.amount_format = AmountFormat::ExactValue,
+            "--format=csv" => json_lines = false,
+            "--format=jsonl" if cfg!(feature = "serde") => json_lines = true,
+            "--format=jsonl" => 
+                return Err("The JSON Lines output requires the serde feature".to_string()),
+            flag if flag.starts_with("--currency-symbol=") => processing.currency_symbols
+                .push(flag["--currency-symbol=".len()..].to_string()),
+            flag if flag.starts_with("--rate=") => {
+                match flag["--rate=".len()..].split_once(':') {
+                    Some((code, rate)) if rate.parse::<f64>().is_ok_and(f64::is_finite) => {
+                        processing.rates.insert(code.to_string(), rate.parse().unwrap());
+                    },
+                    _ => return Err(format!("Invalid rate in {}", flag)),
+                }
+            },
+            flag if flag.starts_with("--comment-prefix=") => processing.comment_prefixes
+                .push(flag["--comment-prefix=".len()..].to_string()),
+            flag if flag.starts_with("--grouping-separator=") => {
+                let mut chars = flag["--grouping-separator=".len()..].chars();
+                match (chars.next(), chars.next()) {
+                    (Some(separator), None) => processing.grouping_separator = Some(separator),
+                    _ => return Err(format!("Invalid grouping separator in {}", flag)),
+                }
+            },
+            flag if flag.starts_with("--delimiter=") => {
+                let mut chars = flag["--delimiter=".len()..].chars();
+                match (chars.next(), chars.next()) {
+                    (Some(delimiter), None) => processing.delimiter = delimiter,
+                    _ => return Err(format!("Invalid delimiter in {}", flag)),
+                }
+            },
+            flag if flag.starts_with("--") => return Err(format!("Unknown option {}", flag)),
+            name => {
+                if file_name.is_some() {
+                    return Err(format!("Unexpected argument {}", name));
+                }
+                file_name = Some(name.to_string());
+            }
+        }
+    }
+
+    match file_name {
+        Some(file_name) => Ok(Options { file_name, processing, output, timing, summary, 
+                                            high_activity_threshold, report_stranded, 
+                                            line_results_file, 
+                                            json_lines }),
+        None => Err("No file name provided".to_string())
+    }
+}
+
+
+// parse the number of decimal places in an option of the form `--name=N`
+fn parse_decimal_places(flag: &str) -> Result<u32, String> {
+    flag.split_once('=')
+        .and_then(|(_, value)| value.parse().ok())
+        .ok_or(format!("Invalid number of decimal places in {}", flag))
+}
+
+
+// parse the number of lines in an option of the form `--name=N`
+fn parse_number_of_lines(flag: &str) -> Result<usize, String> {
+    flag.split_once('=')
+        .and_then(|(_, value)| value.parse().ok())
+        .ok_or(format!("Invalid number of lines in {}", flag))
 }
@@ -0,0 +1,477 @@
+use alloc::string::{ String, ToString };
+use alloc::vec::Vec;
+use crate::transaction::{ ClientId, Transaction, TransactionId };
+use crate::amount::{ loses_precision, decimal_places, truncate_decimal, round_to,
+                     STANDARD_DECIMAL_PLACES };
+
+
+/// a warning type for an invalid line
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum InvalidTransactionLineWarning {
+
+    /// the transaction type is not recognised
+    UnknownType(String),
+
+    /// the line has fewer fields than required by the transaction type (including the type)
+    TooFewFields { found: usize, expected: usize },
+
+    /// the amount has more significant digits than can be represented (only returned if
+    /// `[ParseOptions::reject_precision_loss]` is set)
+    PrecisionLoss(String),
+
+    /// a client or transaction ID has leading zeros (only returned if
+    /// `[ParseOptions::reject_leading_zero_ids]` is set)
+    LeadingZeroId(String),
+
+    /// an amount ends with a decimal point (only returned if
+    /// `[ParseOptions::reject_trailing_decimal_point]` is set)
+    TrailingDecimalPoint(String),
+
+    /// an amount starts with an explicit `+` (only returned if
+    /// `[ParseOptions::reject_leading_plus]` is set)
+    LeadingPlus(String),
+
+    /// the amount has more than `[ParseOptions::max_decimal_places]` decimal places, which are
+    /// given (only returned if `[ParseOptions::excess_decimals]` is
+    /// `[ExcessDecimalsPolicy::Reject]`)
+    ExcessDecimalPlaces { amount: String, max_decimal_places: usize },
+
+    /// the amount is not a finite number, such as `NaN`, `inf`, or `1e400` (which exceeds the
+    /// largest representable amount)
+    NonFiniteAmount(String),
+
+    /// the currency of the amount is not in the rate table (only returned by
+    /// `[crate::read_csv::parse_line]` if `[crate::config::ProcessingConfig::rates]` is not empty)
+    UnknownCurrency(String),
+
+    /// the line contains more fields than expected (only returned by
+    /// `[crate::read_csv::parse_line]` if `[crate::config::ProcessingConfig::extra_data]` is
+    /// `[crate::config::ExtraDataPolicy::WarnAndSkip]`)
+    ExtraData,
+
+    /// a required field is empty or contains only whitespace; the name of the field (`client`,
+    /// `tx`, `amount`, or `currency`) is given
+    EmptyField(&'static str),
+
+    /// the fields do not match the transaction type
+    InvalidFields,
+}
+
+impl core::fmt::Display for InvalidTransactionLineWarning {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            InvalidTransactionLineWarning::UnknownType(name) =>
+                write!(f, "unknown transaction type `{}`", name),
+            InvalidTransactionLineWarning::TooFewFields { found, expected } =>
+                write!(f, "too few fields: found {}, expected {}", found, expected),
+            InvalidTransactionLineWarning::PrecisionLoss(amount) =>
+                write!(f, "amount {} can not be represented exactly", amount),
+            InvalidTransactionLineWarning::LeadingZeroId(id) =>
+                write!(f, "ID {} has leading zeros", id),
+            InvalidTransactionLineWarning::TrailingDecimalPoint(amount) =>
+                write!(f, "amount {} ends with a decimal point", amount),
+            InvalidTransactionLineWarning::LeadingPlus(amount) =>
+                write!(f, "amount {} has an explicit leading `+`", amount),
+            InvalidTransactionLineWarning::ExcessDecimalPlaces { amount, max_decimal_places } =>
+                write!(f, "amount {} has more than {} decimal places",
+                       amount, max_decimal_places),
+            InvalidTransactionLineWarning::NonFiniteAmount(amount) =>
+                write!(f, "amount {} is not a finite number", amount),
+            InvalidTransactionLineWarning::UnknownCurrency(code) =>
+                write!(f, "unknown currency `{}`", code),
+            InvalidTransactionLineWarning::ExtraData =>
+                write!(f, "additional data after the expected fields"),
+            InvalidTransactionLineWarning::EmptyField(name) => write!(f, "empty {} field", name),
+            InvalidTransactionLineWarning::InvalidFields =>
+                write!(f, "invalid transaction line encountered"),
+        }
+    }
+}
+
+
+/// policy for amounts with more than the allowed number of decimal places (by default
+/// `[crate::amount::STANDARD_DECIMAL_PLACES]`), such as `100.123456`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExcessDecimalsPolicy {
+
+    /// keep all the decimal places
+    #[default]
+    Accept,
+
+    /// drop the excess decimal places, so that `100.123456` becomes `100.1234`
+    Truncate,
+
+    /// round the amount half away from zero, so that `100.123456` becomes `100.1235`
+    Round,
+
+    /// skip the line with a warning, as other invalid lines
+    Reject,
+}
+
+
+/// the options used to parse a line, usually obtained from a
+/// `[crate::config::ProcessingConfig]` with its `parse_options` method
+///
+/// The default options use commas as delimiters and accept any amount which can be parsed.
+#[derive(Debug, Clone, Copy)]
+pub struct ParseOptions<'a> {
+
+    /// character separating the fields
+    pub delimiter: char,
+
+    /// currency symbols or codes allowed before or after the amounts, which are stripped before
+    /// parsing them
+    pub currency_symbols: &'a [String],
+
+    /// thousands separator allowed in the amounts, which is removed before parsing them
+    pub grouping_separator: Option<char>,
+
+    /// number of decimal places allowed in the amounts
+    pub max_decimal_places: usize,
+
+    /// how to handle amounts with more than `max_decimal_places` decimal places
+    pub excess_decimals: ExcessDecimalsPolicy,
+
+    /// reject the amounts which can not be represented exactly
+    pub reject_precision_loss: bool,
+
+    /// reject the client and transaction IDs with leading zeros, such as `007`
+    pub reject_leading_zero_ids: bool,
+
+    /// reject the amounts ending with a decimal point, such as `100.`
+    pub reject_trailing_decimal_point: bool,
+
+    /// reject the amounts with an explicit leading `+`, such as `+100.00`
+    pub reject_leading_plus: bool,
+}
+
+impl Default for ParseOptions<'_> {
+    fn default() -> Self {
+        ParseOptions {
+            delimiter: ',',
+            currency_symbols: &[],
+            grouping_separator: None,
+            max_decimal_places: STANDARD_DECIMAL_PLACES,
+            excess_decimals: ExcessDecimalsPolicy::Accept,
+            reject_precision_loss: false,
+            reject_leading_zero_ids: false,
+            reject_trailing_decimal_point: false,
+            reject_leading_plus: false,
+        }
+    }
+}
+
+
+/// a line parsed by `[parse_record]`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedRecord<'a> {
+
+    /// the ID of the transaction (`TransactionId(0)` for transactions without their own ID)
+    pub transaction_id: TransactionId,
+
+    /// the ID of the client
+    pub client_id: ClientId,
+
+    /// the transaction
+    pub transaction: Transaction,
+
+    /// the fields found after the expected ones, if any, left for the caller to handle
+    pub extra_fields: Vec<&'a str>,
+
+    /// the text of the amount if it can not be represented exactly, so that the caller can warn
+    /// about it (only set if `[ParseOptions::reject_precision_loss]` is not)
+    pub inexact_amount: Option<String>,
+}
+
+
+/// parse a line into a transaction ID, a client ID, and a transaction
+///
+/// This function only depends on `core` and `alloc`, and does not print anything: additional
+/// fields and amounts which can not be represented exactly are reported in the returned record.
+///
+/// # Errors
+///
+/// This function returns an `[InvalidTransactionLineWarning]` if the line does not represent a
+/// valid transaction.
+///
+/// # Example
+///
+/// ```
+/// use banking_exercise::parse::{ parse_record, ParseOptions };
+/// use banking_exercise::transaction::*;
+///
+/// let record = parse_record("deposit, 1, 2, 100, note", &ParseOptions::default()).unwrap();
+/// assert_eq!(TransactionId(2), record.transaction_id);
+/// assert_eq!(ClientId(1), record.client_id);
+/// assert_eq!(Transaction::Deposit(100.), record.transaction);
+/// assert_eq!(vec![" note"], record.extra_fields);
+/// ```
+pub fn parse_record<'a>(line: &'a str, options: &ParseOptions)
+    -> Result<ParsedRecord<'a>, InvalidTransactionLineWarning>
+{
+    // split the line
+    let mut fields = line.split(options.delimiter);
+
+    // check the number of fields
+    let kind = unquote(line.split(options.delimiter).next().unwrap_or_default());
+    if let Some(expected) = expected_number_of_fields(kind) {
+        let found = line.split(options.delimiter).count();
+        if found < expected {
+            return Err(InvalidTransactionLineWarning::TooFewFields { found, expected });
+        }
+    }
+
+    // parse the transaction
+    let mut inexact_amount = None;
+    let (transaction_id, client_id, transaction) = match fields.next().map(unquote) {
+        Some("deposit") => {
+            let (transaction_id, client_id) = parse_ids(&mut fields, options)?;
+            let amount = parse_amount(&mut fields, options, &mut inexact_amount)?;
+            (transaction_id, client_id, Transaction::Deposit(amount))
+        },
+        Some("withdrawal") => {
+            let (transaction_id, client_id) = parse_ids(&mut fields, options)?;
+            let amount = parse_amount(&mut fields, options, &mut inexact_amount)?;
+            (transaction_id, client_id, Transaction::Withdrawal(amount))
+        },
+        Some("dispute") => {
+            let (transaction_id, client_id) = parse_ids(&mut fields, options)?;
+            (TransactionId::default(), client_id, Transaction::Dispute(transaction_id))
+        },
+        Some("resolve") => {
+            let (transaction_id, client_id) = parse_ids(&mut fields, options)?;
+            (TransactionId::default(), client_id, Transaction::Resolve(transaction_id))
+        },
+        Some("chargeback") => {
+            let (transaction_id, client_id) = parse_ids(&mut fields, options)?;
+            (TransactionId::default(), client_id, Transaction::Chargeback(transaction_id))
+        },
+        Some("open") =>
+            (TransactionId::default(), parse_client_id(&mut fields, options)?, Transaction::Open),
+        Some("close") =>
+            (TransactionId::default(), parse_client_id(&mut fields, options)?, Transaction::Close),
+        Some(name) => return Err(InvalidTransactionLineWarning::UnknownType(name.trim().to_string())),
+        None => return Err(InvalidTransactionLineWarning::InvalidFields)
+    };
+
+    Ok(ParsedRecord {
+        transaction_id,
+        client_id,
+        transaction,
+        extra_fields: fields.collect(),
+        inexact_amount,
+    })
+}
+
+
+// number of fields expected for each transaction type (including the type itself), or `None` if
+// the type is unknown
+fn expected_number_of_fields(kind: &str) -> Option<usize> {
+    match kind {
+        "deposit" | "withdrawal" => Some(4),
+        "dispute" | "resolve" | "chargeback" => Some(3),
+        "open" | "close" => Some(2),
+        _ => None
+    }
+}
+
+
+// parse an amount, checking that no precision is lost
+//
+// Integer amounts, such as `100`, are accepted, as are amounts ending with a decimal point, such
+// as `100.`, and amounts with an explicit leading `+`, unless rejected explicitly. Amounts with
+// more than `options.max_decimal_places` decimal places are handled according to
+// `options.excess_decimals`. If the amount can not be represented exactly and this is not
+// rejected, its text is stored in `inexact_amount`.
+fn parse_amount(fields: &mut core::str::Split<char>, options: &ParseOptions,
+                inexact_amount: &mut Option<String>)
+    -> Result<f64, InvalidTransactionLineWarning>
+{
+    let field = non_empty_field(fields.next(), "amount")?;
+    let unquoted = read_quoted_field(field, fields, options.delimiter)?;
+    let field = strip_currency_symbol(&unquoted, options.currency_symbols);
+    let ungrouped;
+    let field = match options.grouping_separator {
+        Some(separator) => {
+            ungrouped = field.replace(separator, "");
+            ungrouped.as_str()
+        },
+        None => field,
+    };
+    if options.reject_trailing_decimal_point && field.ends_with('.') {
+        return Err(InvalidTransactionLineWarning::TrailingDecimalPoint(field.to_string()));
+    }
+    if options.reject_leading_plus && field.starts_with('+') {
+        return Err(InvalidTransactionLineWarning::LeadingPlus(field.to_string()));
+    }
+    let max_decimal_places = options.max_decimal_places;
+    let excess_decimals = decimal_places(field).is_some_and(|places| places > max_decimal_places);
+    let field = match (excess_decimals, options.excess_decimals) {
+        (true, ExcessDecimalsPolicy::Reject) =>
+            return Err(InvalidTransactionLineWarning::ExcessDecimalPlaces {
+                amount: field.to_string(),
+                max_decimal_places,
+            }),
+        (true, ExcessDecimalsPolicy::Truncate) => truncate_decimal(field, max_decimal_places),
+        _ => field,
+    };
+    let amount = field.parse::<f64>().map_err(|_| InvalidTransactionLineWarning::InvalidFields)?;
+    if !amount.is_finite() {
+        return Err(InvalidTransactionLineWarning::NonFiniteAmount(field.to_string()));
+    }
+
+    // rounded amounts are not checked for precision loss, as the excess digits are dropped anyway
+    if excess_decimals && options.excess_decimals == ExcessDecimalsPolicy::Round {
+        return Ok(round_to(amount, max_decimal_places as u32));
+    }
+    if loses_precision(field, amount) {
+        if options.reject_precision_loss {
+            return Err(InvalidTransactionLineWarning::PrecisionLoss(field.to_string()));
+        }
+        *inexact_amount = Some(field.to_string());
+    }
+    Ok(amount)
+}
+
+
+// read a field which may be enclosed in double quotes, removing them; if the opening quote is not
+// closed in the same field, as in `"1,000.00"` with comma delimiters, the following fields are
+// joined to it up to the closing quote
+fn read_quoted_field(field: &str, fields: &mut core::str::Split<char>, delimiter: char)
+    -> Result<String, InvalidTransactionLineWarning>
+{
+    if !field.starts_with('"') { return Ok(field.to_string()); }
+    let mut quoted = field.to_string();
+    while quoted.len() < 2 || !quoted.ends_with('"') {
+        let next = fields.next().ok_or(InvalidTransactionLineWarning::InvalidFields)?;
+        quoted.push(delimiter);
+        quoted.push_str(next.trim_end());
+    }
+    Ok(quoted[1..quoted.len() - 1].trim().to_string())
+}
+
+
+// remove the double quotes enclosing a field, if any, as in `"deposit"`
+pub(crate) fn unquote(field: &str) -> &str {
+    field.strip_prefix('"').and_then(|field| field.strip_suffix('"')).unwrap_or(field)
+}
+
+
+// remove the first currency symbol found before or after an amount, if any
+fn strip_currency_symbol<'a>(field: &'a str, currency_symbols: &[String]) -> &'a str {
+    for symbol in currency_symbols {
+        if let Some(stripped) = field.strip_prefix(symbol.as_str())
+            .or_else(|| field.strip_suffix(symbol.as_str())) {
+            return stripped.trim();
+        }
+    }
+    field
+}
+
+
+// trim a field, checking that it is present and not empty
+fn non_empty_field<'a>(field: Option<&'a str>, name: &'static str)
+    -> Result<&'a str, InvalidTransactionLineWarning>
+{
+    match field.map(str::trim) {
+        None => Err(InvalidTransactionLineWarning::InvalidFields),
+        Some("") => Err(InvalidTransactionLineWarning::EmptyField(name)),
+        Some(field) => Ok(field),
+    }
+}
+
+
+// parse a numeric ID, checking for leading zeros if required
+fn parse_id<T: core::str::FromStr>(field: Option<&str>, name: &'static str,
+                                   options: &ParseOptions)
+    -> Result<T, InvalidTransactionLineWarning>
+{
+    let field = non_empty_field(field, name)?;
+    let id = field.parse::<T>().map_err(|_| InvalidTransactionLineWarning::InvalidFields)?;
+    if options.reject_leading_zero_ids && field.len() > 1 && field.starts_with('0') {
+        return Err(InvalidTransactionLineWarning::LeadingZeroId(field.to_string()));
+    }
+    Ok(id)
+}
+
+
+fn parse_client_id(fields: &mut core::str::Split<char>, options: &ParseOptions)
+    -> Result<ClientId, InvalidTransactionLineWarning>
+{
+    Ok(ClientId(parse_id(fields.next(), "client", options)?))
+}
+
+
+fn parse_ids(fields: &mut core::str::Split<char>, options: &ParseOptions)
+    -> Result<(TransactionId, ClientId), InvalidTransactionLineWarning>
+{
+    let client_id = parse_client_id(fields, options)?;
+    let transaction_id = TransactionId(parse_id(fields.next(), "tx", options)?);
+    Ok((transaction_id, client_id))
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use alloc::{ format, vec };
+
+    #[test]
+    // additional fields and inexact amounts are reported to the caller rather than warned about
+    fn parse_record_1() {
+        let record = parse_record("deposit, 1, 2, 100.00000000000000001, a, b",
+                                  &ParseOptions::default()).unwrap();
+        assert_eq!(Transaction::Deposit(100.), record.transaction);
+        assert_eq!(vec![" a", " b"], record.extra_fields);
+        assert_eq!(Some("100.00000000000000001".to_string()), record.inexact_amount);
+
+        let record = parse_record("dispute, 1, 2", &ParseOptions::default()).unwrap();
+        assert_eq!(Transaction::Dispute(TransactionId(2)), record.transaction);
+        assert!(record.extra_fields.is_empty());
+        assert_eq!(None, record.inexact_amount);
+    }
+
+    #[test]
+    // the options are applied without a `ProcessingConfig`
+    fn parse_record_2() {
+        let currency_symbols = ["$".to_string()];
+        let options = ParseOptions {
+            delimiter: ';',
+            currency_symbols: &currency_symbols,
+            grouping_separator: Some(','),
+            excess_decimals: ExcessDecimalsPolicy::Round,
+            ..Default::default()
+        };
+        let record = parse_record("withdrawal; 1; 2; $1,000.123456", &options).unwrap();
+        assert_eq!(Transaction::Withdrawal(1000.1235), record.transaction);
+
+        let options = ParseOptions { reject_leading_zero_ids: true, ..Default::default() };
+        assert_eq!(Err(InvalidTransactionLineWarning::LeadingZeroId("01".to_string())),
+                   parse_record("open, 01", &options));
+    }
+
+    #[test]
+    // the type may be enclosed in double quotes, as in some exports
+    fn parse_record_3() {
+        let options = ParseOptions::default();
+        let record = parse_record("\"deposit\", 1, 2, 100", &options).unwrap();
+        assert_eq!((TransactionId(2), ClientId(1), Transaction::Deposit(100.)),
+                   (record.transaction_id, record.client_id, record.transaction));
+        assert_eq!(Err(InvalidTransactionLineWarning::TooFewFields { found: 2, expected: 3 }),
+                   parse_record("\"dispute\", 1", &options));
+        assert_eq!(Err(InvalidTransactionLineWarning::UnknownType("transfer".to_string())),
+                   parse_record("\"transfer\", 1, 2, 100", &options));
+    }
+
+    #[test]
+    // amounts which are not finite numbers are rejected
+    fn parse_record_4() {
+        let options = ParseOptions::default();
+        for amount in ["NaN", "inf", "-infinity", "1e400"] {
+            assert_eq!(Err(InvalidTransactionLineWarning::NonFiniteAmount(amount.to_string())),
+                       parse_record(&format!("deposit, 1, 2, {}", amount), &options));
+        }
+    }
+}
@@ -1,172 +1,1038 @@
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::{ prelude::*, BufReader };
-#[cfg(feature = "atty")]
-use atty::Stream;
 use crate::client::*;
+use crate::config::{ ProcessingConfig, StrictModeError, ExtraDataPolicy };
 use crate::transaction::*;
-use crate::style::warning_style;
+use crate::parse::{ parse_record, unquote };
+use itertools::Itertools;
 
 
 /// Open a csv file and execute all the transactions
-pub fn execute_transactions_from_csv(clients_map: &mut ClientMap, file_name: &str) 
-    -> Result<(), Box<dyn std::error::Error>>
+///
+/// # Return type
+///
+/// This function returns a `[ProcessingReport]` with the populated `[ClientMap]`, some
+/// `[ProcessingStats]` about the lines of the file, and the reasons why lines were skipped.
+///
+/// # Errors
+///
+/// This function returns a `[ProcessingError]` if the file can not be read, if a line violates
+/// one of the strict policies enabled in `config` (see `[ProcessingConfig]`), or if a transaction
+/// returns an error which is not turned into a warning.
+///
+/// # Example
+///
+/// ```no_run
+/// use banking_exercise::client::ClientMap;
+/// use banking_exercise::config::ProcessingConfig;
+/// use banking_exercise::read_csv::execute_transactions_from_csv;
+///
+/// let mut clients_map = ClientMap::default();
+/// let report = execute_transactions_from_csv(&mut clients_map, "transactions.csv", 
+///                                            &ProcessingConfig::default()).unwrap();
+/// for line_error in &report.line_errors {
+///     eprintln!("{}", line_error);
+/// }
+/// print!("{}", report.clients_map);
+/// ```
+pub fn execute_transactions_from_csv<'a, S>(clients_map: &'a mut ClientMap<S>, file_name: &str, 
+                                            config: &ProcessingConfig) 
+    -> Result<ProcessingReport<'a, S>, ProcessingError>
+    where S: ClientStore + Clone
 {
 
-    // check if stderr is a terminal
-    let stderr_is_term = atty::is(Stream::Stderr);
-
     // open the file using a buffer
     let reader = BufReader::new(File::open(file_name)?);
+    if !config.skip_processed_files {
+        return execute_transactions_from_reader(clients_map, reader, config);
+    }
+
+    // read the file a first time to compute its fingerprint
+    let mut hash = FINGERPRINT_SEED;
+    for line in reader.lines() {
+        hash = fingerprint_update(hash, line?.as_bytes());
+    }
+    let reader = BufReader::new(File::open(file_name)?);
+    execute_transactions_once(clients_map, hash, reader, config)
+}
+
+
+/// a fingerprint of the content of a file, used to detect files which have already been processed
+/// (see `[ProcessingConfig::skip_processed_files]`)
+///
+/// The fingerprint is a 64-bit FNV-1a hash of the lines, without their line endings, so that it
+/// does not depend on the platform.
+///
+/// # Example
+///
+/// ```
+/// use banking_exercise::read_csv::fingerprint;
+///
+/// assert_eq!(fingerprint(b"deposit, 1, 1, 100\n"), fingerprint(b"deposit, 1, 1, 100\r\n"));
+/// assert_ne!(fingerprint(b"deposit, 1, 1, 100\n"), fingerprint(b"deposit, 1, 1, 10\n"));
+/// ```
+pub fn fingerprint(bytes: &[u8]) -> u64 {
+    bytes.split(|&byte| byte == b'\n')
+        .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+        .fold(FINGERPRINT_SEED, fingerprint_update)
+}
+
+
+// FNV-1a offset basis and prime
+const FINGERPRINT_SEED: u64 = 0xcbf2_9ce4_8422_2325;
+const FINGERPRINT_PRIME: u64 = 0x0100_0000_01b3;
+
+
+// add a line to a fingerprint, followed by a newline so that the line boundaries count; empty
+// lines, which are not processed, are ignored, so that a final newline makes no difference
+fn fingerprint_update(hash: u64, line: &[u8]) -> u64 {
+    if line.is_empty() { return hash; }
+    line.iter().chain(b"\n")
+        .fold(hash, |hash, &byte| (hash ^ byte as u64).wrapping_mul(FINGERPRINT_PRIME))
+}
+
+
+// execute the transactions of a file with a given fingerprint, unless it has already been
+// processed into the map, and record it once processed
+fn execute_transactions_once<'a, S>(clients_map: &'a mut ClientMap<S>, fingerprint: u64, 
+                                    reader: impl BufRead, config: &ProcessingConfig) 
+    -> Result<ProcessingReport<'a, S>, ProcessingError>
+    where S: ClientStore + Clone
+{
+    if clients_map.is_processed(fingerprint) {
+        config.warn(format!("Warning: A file with the same content (fingerprint {:016x}) has already been processed; it will be skipped", 
+                            fingerprint));
+        return Ok(ProcessingReport { clients_map, stats: ProcessingStats::default(), 
+                                     line_errors: Vec::new(), line_results: Vec::new() });
+    }
+    let ProcessingReport { stats, line_errors, line_results, .. } = 
+        execute_transactions_from_reader(clients_map, reader, config)?;
+    clients_map.mark_processed(fingerprint);
+    Ok(ProcessingReport { clients_map, stats, line_errors, line_results })
+}
+
+
+/// Download a csv file and execute all the transactions
+///
+/// The body of the response is streamed through the same path as a local file in
+/// `[execute_transactions_from_csv]`, with the same return value and errors. Files in object
+/// storage such as S3 can be read through a pre-signed URL.
+///
+/// # Errors
+///
+/// In addition to those of `[execute_transactions_from_csv]`, this function returns a
+/// `[ProcessingError::Http]` if the request fails or the server returns an error status.
+///
+/// # Example
+///
+/// ```no_run
+/// use banking_exercise::client::ClientMap;
+/// use banking_exercise::config::ProcessingConfig;
+/// use banking_exercise::read_csv::execute_transactions_from_url;
+///
+/// let mut clients_map = ClientMap::default();
+/// let report = execute_transactions_from_url(&mut clients_map, 
+///                                            "https://example.com/transactions.csv", 
+///                                            &ProcessingConfig::default()).unwrap();
+/// print!("{}", report.clients_map);
+/// ```
+#[cfg(feature = "reqwest")]
+pub fn execute_transactions_from_url<'a, S>(clients_map: &'a mut ClientMap<S>, url: &str, 
+                                            config: &ProcessingConfig) 
+    -> Result<ProcessingReport<'a, S>, ProcessingError>
+    where S: ClientStore + Clone
+{
+    let response = reqwest::blocking::get(url)
+        .and_then(|response| response.error_for_status())
+        .map_err(ProcessingError::Http)?;
+    execute_transactions_from_reader(clients_map, BufReader::new(response), config)
+}
+
+
+/// Execute all the transactions in a csv file held in memory
+///
+/// The bytes are processed as the content of a file in `[execute_transactions_from_csv]`, with
+/// the same return value and errors (an I/O error being returned if they are not valid UTF-8).
+/// This does not require a filesystem.
+///
+/// # Example
+///
+/// ```
+/// use banking_exercise::client::ClientMap;
+/// use banking_exercise::config::ProcessingConfig;
+/// use banking_exercise::read_csv::execute_transactions_from_bytes;
+///
+/// let mut clients_map = ClientMap::default();
+/// let report = execute_transactions_from_bytes(&mut clients_map, 
+///                                              b"type, client, tx, amount\ndeposit, 1, 1, 100\n", 
+///                                              &ProcessingConfig::default()).unwrap();
+/// assert_eq!("client, available, held, total, locked\n1, 100, 0, 100, false\n", 
+///            format!("{}", report.clients_map));
+/// ```
+pub fn execute_transactions_from_bytes<'a, S>(clients_map: &'a mut ClientMap<S>, bytes: &[u8], 
+                                              config: &ProcessingConfig) 
+    -> Result<ProcessingReport<'a, S>, ProcessingError>
+    where S: ClientStore + Clone
+{
+    if config.skip_processed_files {
+        return execute_transactions_once(clients_map, fingerprint(bytes), bytes, config);
+    }
+    execute_transactions_from_reader(clients_map, std::io::Cursor::new(bytes), config)
+}
+
+
+/// Execute all the transactions read from a buffer
+///
+/// This is the path used by `[execute_transactions_from_csv]`, `[execute_transactions_from_bytes]`,
+/// and `[execute_transactions_from_url]` (with the `reqwest` feature), with the same return value
+/// and errors; it can be used for other sources, such as `stdin`.
+///
+/// Like them, it works with a map using any `[ClientStore]`, which must implement `Clone` to be
+/// copied in `[ProcessingConfig::all_or_nothing]` mode.
+pub fn execute_transactions_from_reader<'a, S>(clients_map: &'a mut ClientMap<S>, 
+                                               reader: impl BufRead, config: &ProcessingConfig) 
+    -> Result<ProcessingReport<'a, S>, ProcessingError>
+    where S: ClientStore + Clone
+{
+    if !config.all_or_nothing {
+        return execute_lines(clients_map, reader, config, false);
+    }
+
+    // in all-or-nothing mode, execute the transactions on a copy of the map, which replaces it
+    // only if no line was skipped; the commit hook is not called on the copy, but once on the
+    // map after it has been replaced
+    let mut scratch_map = clients_map.clone();
+    let report = execute_lines(&mut scratch_map, reader, config, true)?;
+    if !report.line_errors.is_empty() {
+        return Err(ProcessingError::InvalidLines(report.line_errors));
+    }
+    let (stats, line_results) = (report.stats, report.line_results);
+    *clients_map = scratch_map;
+    if let (Some(hook), true) = (&config.commit_hook, stats.applied > 0) {
+        hook.call(clients_map);
+    }
+    Ok(ProcessingReport { clients_map, stats, line_errors: Vec::new(), line_results })
+}
 
-    // iterate over the lines
-    for (n_line, line) in reader.lines().enumerate() {
+
+// execute all the transactions read from a buffer, skipping the invalid lines, and calling the
+// commit hook (if any) unless `suppress_commit_hook` is `true`
+fn execute_lines<'a, S: ClientStore>(clients_map: &'a mut ClientMap<S>, reader: impl BufRead, 
+                                     config: &ProcessingConfig, suppress_commit_hook: bool) 
+    -> Result<ProcessingReport<'a, S>, ProcessingError>
+{
+    let mut tally = LineTally { suppress_commit_hook, ..Default::default() };
+    let mut deferred = Vec::new();
+    let mut n_headers = 0;
+
+    // iterate over the lines, numbered from 1 as in text editors, without the empty lines, the
+    // comments, and the headers (which may appear anywhere in concatenated files), and keeping
+    // only the range of data lines given by `skip_lines` and `take_lines`; read errors are kept,
+    // so that they are reported even in the skipped range
+    let end = config.take_lines.map_or(usize::MAX, |take| config.skip_lines.saturating_add(take));
+    let data_lines = (1..).zip(reader.lines())
+        .filter(|(_, line)| match line {
+            Ok(line) if line.is_empty() || is_comment(line, &config.comment_prefixes) => false,
+            Ok(line) if config.detect_header && is_header(line, config.delimiter) => {
+                n_headers += 1;
+                false
+            },
+            _ => true
+        })
+        .enumerate()
+        .skip_while(|(i, (_, line))| *i < config.skip_lines && line.is_ok())
+        .take_while(|(i, _)| *i < end)
+        .map(|(_, numbered_line)| numbered_line);
+
+    for (n_line, line) in data_lines {
 
         let line = line?;
+        tally.stats.lines += 1;
 
-        // if the line i empty, go to the next one
-        if line.is_empty() { continue; }
+        // execute the transaction, unless it is deferred, and update the statistics
+        let (outcome, parsed) = match parse_line_with_notes(&line, n_line, config) {
+            Ok((parsed, notes)) => {
+                if defers(clients_map, &deferred, &parsed, config) {
+                    deferred.push((n_line, parsed, notes));
+                    continue;
+                }
+                (execute_parsed_line(clients_map, parsed, notes, n_line, config), Some(parsed))
+            },
+            Err(warning) => (handle_invalid_line(warning, n_line, config), None),
+        };
+        let outcome = outcome.map_err(|error| ProcessingError::from_line_error(error, n_line))?;
+        let retry = outcome == LineOutcome::Applied;
+        tally.record(n_line, outcome, clients_map, config);
 
-        // parse the line, printing a warning if it is invalid
-        if let Ok((transaction_id, client_id, transaction)) = parse_line(&line, n_line, stderr_is_term) {
+        // a deposit or withdrawal may be the transaction referred to by deferred lines
+        if let (true, Some((_, client_id, Transaction::Deposit(_) | Transaction::Withdrawal(_)))) 
+            = (retry, parsed) {
+            retry_deferred(clients_map, &mut deferred, Some(client_id), &mut tally, config)?;
+        }
+    }
 
-            // if the client is not already in clients_map, add it
-            if !(clients_map.contains_key(&client_id)) {
+    // retry the remaining deferred lines a last time, and restore the order of the lines, which
+    // deferred lines break
+    retry_deferred(clients_map, &mut deferred, None, &mut tally, config)?;
+    if config.defer_unknown_disputes.is_some() {
+        tally.line_errors.sort_by_key(|line_error| line_error.n_line);
+        tally.line_results.sort_by_key(|line_result| line_result.n_line);
+    }
+    let LineTally { mut stats, line_errors, line_results, .. } = tally;
+    stats.lines += n_headers;
+    Ok(ProcessingReport { clients_map, stats, line_errors, line_results })
+}
 
-                // We know that the map does not contain this client ID, so the insert function
-                // will not return an error
-                clients_map.insert(client_id, Client::default()).unwrap();
-            }
 
-            // execute the transaction
-            clients_map.execute_transaction(transaction_id, client_id, transaction, stderr_is_term)?;
-        } else {
-            // print the warning if the line number is not zero
-            if n_line > 0 {
-                let warning = format!("{} (line {})", InvalidTransactionLineWarning {}, n_line);
-                eprintln!("{}", warning_style(warning, stderr_is_term));
-            }
+// the statistics and outcomes of the data lines processed so far
+#[derive(Default)]
+struct LineTally {
+    stats: ProcessingStats,
+    line_errors: Vec<LineError>,
+    line_results: Vec<LineResult>,
+    suppress_commit_hook: bool,
+}
+
+impl LineTally {
+
+    // record the outcome of a line, calling the commit hook if the transaction was applied (and
+    // the hook is not suppressed)
+    fn record<S: ClientStore>(&mut self, n_line: usize, outcome: LineOutcome, 
+                              clients_map: &ClientMap<S>, config: &ProcessingConfig) 
+    {
+        if config.record_line_results {
+            self.line_results.push(LineResult { n_line, outcome: outcome.clone() });
+        }
+        match outcome {
+            LineOutcome::Applied => {
+                self.stats.applied += 1;
+                if let (Some(hook), false) = (&config.commit_hook, self.suppress_commit_hook) {
+                    hook.notify(self.stats.applied, clients_map);
+                }
+            },
+            LineOutcome::Skipped(message) => {
+                self.stats.skipped += 1;
+                self.line_errors.push(LineError { n_line, message });
+            },
+            LineOutcome::Queued => self.stats.queued += 1,
         }
     }
-    Ok(())
 }
 
 
-/// a warning type for an invalid line
-#[derive(Debug, PartialEq, Eq)]
-pub struct InvalidTransactionLineWarning {}
+// a deferred line: its number, the parsed transaction, and its notes
+type DeferredLine = (usize, ParsedLine, Option<String>);
 
-impl std::fmt::Display for InvalidTransactionLineWarning {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "invalid transaction line encountered")
+
+// the transaction referred to by a dispute, resolve, or chargeback
+fn referred_transaction(transaction: &Transaction) -> Option<TransactionId> {
+    match transaction {
+        Transaction::Dispute(id) | Transaction::Resolve(id) | Transaction::Chargeback(id) => 
+            Some(*id),
+        _ => None
     }
 }
 
 
-fn parse_line(line: &str, n_line: usize, stderr_is_term: bool) 
-    -> Result<(TransactionId, ClientId, Transaction), InvalidTransactionLineWarning> 
+// check if a parsed line should be deferred (see `ProcessingConfig::defer_unknown_disputes`):
+// it refers to a transaction which is not in the history of its client, or which an earlier
+// deferred line refers to
+fn defers<S: ClientStore>(clients_map: &ClientMap<S>, deferred: &[DeferredLine], 
+          (_, client_id, transaction): &ParsedLine, config: &ProcessingConfig) -> bool 
 {
-    // split the line
-    let mut fields = line.split(',');
-
-    // parse the transaction
-    let parsed = match fields.next() {
-        Some("deposit") => parse_deposit(&mut fields)?,
-        Some("withdrawal") => parse_withdrawal(&mut fields)?,
-        Some("dispute") => parse_dispute(&mut fields)?,
-        Some("resolve") => parse_resolve(&mut fields)?,
-        Some("chargeback") => parse_chargeback(&mut fields)?,
-        _ => return Err(InvalidTransactionLineWarning {})
+    let (Some(bound), Some(id)) = (config.defer_unknown_disputes, referred_transaction(transaction)) 
+    else {
+        return false;
     };
+    deferred.len() < bound 
+        && (!clients_map.has_transaction(client_id, &id) 
+            || deferred.iter().any(|(_, (_, other_client_id, other_transaction), _)| 
+                                   other_client_id == client_id 
+                                   && referred_transaction(other_transaction) == Some(id)))
+}
+
 
-    // print a warning if there is more data on the same line
-    if fields.next().is_some() {
-        let warning = format!("Additional data on line {}", n_line);
-        eprintln!("{}", warning_style(warning, stderr_is_term));
+// execute, in order, the deferred lines of a client (or of all clients if `client_id` is `None`)
+// whose transaction is now in the history, unless an earlier deferred line refers to it; if
+// `client_id` is `None`, all the deferred lines are executed
+fn retry_deferred<S: ClientStore>(clients_map: &mut ClientMap<S>, 
+                                  deferred: &mut Vec<DeferredLine>, client_id: Option<ClientId>, 
+                                  tally: &mut LineTally, config: &ProcessingConfig) 
+    -> Result<(), ProcessingError>
+{
+    let mut i = 0;
+    while i < deferred.len() {
+        let (_, (_, line_client_id, transaction), _) = &deferred[i];
+        let id = referred_transaction(transaction);
+        let ready = match client_id {
+            None => true,
+            Some(client_id) => *line_client_id == client_id 
+                && id.is_some_and(|id| clients_map.has_transaction(line_client_id, &id))
+                && !deferred[..i].iter().any(|(_, (_, other_client_id, other_transaction), _)| 
+                                             other_client_id == line_client_id 
+                                             && referred_transaction(other_transaction) == id),
+        };
+        if !ready {
+            i += 1;
+            continue;
+        }
+        let (n_line, parsed, notes) = deferred.remove(i);
+        let outcome = execute_parsed_line(clients_map, parsed, notes, n_line, config)
+            .map_err(|error| ProcessingError::from_line_error(error, n_line))?;
+        tally.record(n_line, outcome, clients_map, config);
     }
+    Ok(())
+}
+
 
-    Ok(parsed)
+/// the result of the processing of a file
+#[derive(Debug)]
+pub struct ProcessingReport<'a, S = HashMap<ClientId, Client>> {
+
+    /// the clients, after executing the transactions
+    pub clients_map: &'a ClientMap<S>,
+
+    /// statistics about the lines of the file
+    pub stats: ProcessingStats,
+
+    /// the lines skipped with a warning, in order
+    pub line_errors: Vec<LineError>,
+
+    /// the outcome of each data line, in order (only recorded if
+    /// `[ProcessingConfig::record_line_results]` is set)
+    pub line_results: Vec<LineResult>,
 }
 
 
-fn parse_dispute(fields: &mut std::str::Split<char>) 
-    -> Result<(TransactionId, ClientId, Transaction), InvalidTransactionLineWarning> 
+/// the outcome of a data line
+///
+/// It is displayed as the line number and the outcome separated by `", "`, such as `3, applied`
+/// or `4, skipped: invalid transaction line encountered`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineResult {
+    pub n_line: usize,
+    pub outcome: LineOutcome,
+}
+
+impl std::fmt::Display for LineResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}, {}", self.n_line, self.outcome)
+    }
+}
+
+
+/// the reason why a line was skipped
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineError {
+    pub n_line: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for LineError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} (line {})", self.message, self.n_line)
+    }
+}
+
+
+/// exit code for an invalid command line, following `sysexits.h`
+pub const EXIT_USAGE: i32 = 64;
+
+/// exit code for invalid input data, following `sysexits.h`
+pub const EXIT_DATA_ERROR: i32 = 65;
+
+/// exit code for an unavailable remote service, following `sysexits.h`
+pub const EXIT_UNAVAILABLE: i32 = 69;
+
+/// exit code for an input or output error, following `sysexits.h`
+pub const EXIT_IO_ERROR: i32 = 74;
+
+
+/// an error stopping the processing of a file
+#[derive(Debug)]
+pub enum ProcessingError {
+
+    /// the file could not be read
+    Io(std::io::Error),
+
+    /// a line violates one of the strict policies
+    Strict(StrictModeError),
+
+    /// a transaction returned an error
+    Transaction { n_line: usize, error: Box<dyn std::error::Error> },
+
+    /// a single transaction, applied outside of any file, returned an error (see
+    /// `[ClientMap::apply_transaction_checked]`)
+    Rejected(Box<dyn std::error::Error>),
+
+    /// some lines would have been skipped in all-or-nothing mode (see
+    /// `ProcessingConfig::all_or_nothing`)
+    InvalidLines(Vec<LineError>),
+
+    /// the file could not be downloaded
+    #[cfg(feature = "reqwest")]
+    Http(reqwest::Error),
+}
+
+impl std::fmt::Display for ProcessingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ProcessingError::Io(error) => write!(f, "{}", error),
+            ProcessingError::Strict(error) => write!(f, "{}", error),
+            ProcessingError::Transaction { n_line, error } => 
+                write!(f, "{} (line {})", error, n_line),
+            ProcessingError::Rejected(error) => write!(f, "{}", error),
+            ProcessingError::InvalidLines(line_errors) => {
+                write!(f, "{} invalid line(s), no transaction was applied: ", line_errors.len())?;
+                write!(f, "{}", line_errors.iter().join("; "))
+            },
+            #[cfg(feature = "reqwest")]
+            ProcessingError::Http(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for ProcessingError {}
+
+impl ProcessingError {
+
+    /// exit code of the executable for this error, following `sysexits.h`: `[EXIT_IO_ERROR]` if
+    /// the file could not be read, `[EXIT_UNAVAILABLE]` if it could not be downloaded, and
+    /// `[EXIT_DATA_ERROR]` if its content stopped the processing
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use banking_exercise::read_csv::{ ProcessingError, EXIT_IO_ERROR };
+    ///
+    /// let error = ProcessingError::Io(std::io::Error::from(std::io::ErrorKind::NotFound));
+    /// assert_eq!(EXIT_IO_ERROR, error.exit_code());
+    /// ```
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ProcessingError::Io(_) => EXIT_IO_ERROR,
+            ProcessingError::Strict(_) 
+                | ProcessingError::Transaction { .. } 
+                | ProcessingError::Rejected(_)
+                | ProcessingError::InvalidLines(_) => EXIT_DATA_ERROR,
+            #[cfg(feature = "reqwest")]
+            ProcessingError::Http(_) => EXIT_UNAVAILABLE,
+        }
+    }
+
+    // convert an error returned by `execute_line`
+    fn from_line_error(error: Box<dyn std::error::Error>, n_line: usize) -> Self {
+        match error.downcast::<StrictModeError>() {
+            Ok(error) => ProcessingError::Strict(*error),
+            Err(error) => ProcessingError::Transaction { n_line, error },
+        }
+    }
+}
+
+impl From<std::io::Error> for ProcessingError {
+    fn from(error: std::io::Error) -> Self {
+        ProcessingError::Io(error)
+    }
+}
+
+
+impl<S: ClientStore> ClientMap<S> {
+
+    /// parse a single line and execute the transaction, creating the client if needed (unless
+    /// `[ProcessingConfig::require_explicit_open]` is set)
+    ///
+    /// Invalid lines are handled as in `[execute_transactions_from_csv]`: they are skipped with a
+    /// warning, or return an error depending on `config`. Warnings and errors refer to the line as
+    /// line 1. Headers are not detected.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use banking_exercise::client::*;
+    /// use banking_exercise::config::ProcessingConfig;
+    ///
+    /// let config = ProcessingConfig::default();
+    /// let mut clients_map = ClientMap::default();
+    /// clients_map.apply_csv_line("deposit, 1, 1, 100", &config).unwrap();
+    /// assert_eq!("client, available, held, total, locked\n1, 100, 0, 100, false\n", 
+    ///            format!("{}", clients_map));
+    /// ```
+    pub fn apply_csv_line(&mut self, line: &str, config: &ProcessingConfig) 
+        -> Result<(), ProcessingError> 
+    {
+        execute_line(self, line, 1, config)
+            .map(|_| ())
+            .map_err(|error| ProcessingError::from_line_error(error, 1))
+    }
+}
+
+
+/// Execute all the transactions read from a buffer, tracing the effect of one of them on its
+/// client
+///
+/// The lines are executed as in `[execute_transactions_from_reader]`, with the same errors, except
+/// that all the data lines are processed (ignoring `[ProcessingConfig::skip_lines]`,
+/// `[ProcessingConfig::take_lines]`, and `[ProcessingConfig::all_or_nothing]`) and the commit hook
+/// is not called. This function returns the steps of the history of the transaction with ID
+/// `transaction_id`, in order: the deposits or withdrawals with this ID, and the disputes,
+/// resolves, and chargebacks referring to it; once one of the former has been applied, only the
+/// lines of the same client are kept.
+///
+/// # Example
+///
+/// ```
+/// use banking_exercise::client::ClientMap;
+/// use banking_exercise::config::ProcessingConfig;
+/// use banking_exercise::read_csv::explain_transaction;
+/// use banking_exercise::transaction::TransactionId;
+///
+/// let mut clients_map = ClientMap::default();
+/// let steps = explain_transaction(&mut clients_map, 
+///                                 &b"deposit, 1, 1, 100\ndeposit, 1, 2, 50\ndispute, 1, 1\n"[..], 
+///                                 TransactionId(1), &ProcessingConfig::default()).unwrap();
+/// assert_eq!("line 3: dispute 1 by client 1: applied, the transaction is under dispute \
+///             (available 50, held 100, total 150)", 
+///            steps[1].to_string());
+/// ```
+pub fn explain_transaction<S: ClientStore>(clients_map: &mut ClientMap<S>, reader: impl BufRead, 
+                                           transaction_id: TransactionId, 
+                                           config: &ProcessingConfig) 
+    -> Result<Vec<TraceStep>, ProcessingError>
 {
-    let (transaction_id, client_id) = parse_ids(fields)?;
-    Ok((TransactionId::default(), client_id, Transaction::Dispute(transaction_id)))
+    let mut steps = Vec::new();
+    let mut owner = None;
+    for (n_line, line) in (1..).zip(reader.lines()) {
+        let line = line?;
+        if line.is_empty() || is_comment(&line, &config.comment_prefixes) 
+            || (config.detect_header && is_header(&line, config.delimiter)) { 
+            continue; 
+        }
+        let (parsed, notes) = match parse_line_with_notes(&line, n_line, config) {
+            Ok(parsed) => parsed,
+            Err(warning) => {
+                handle_invalid_line(warning, n_line, config)
+                    .map_err(|error| ProcessingError::from_line_error(error, n_line))?;
+                continue;
+            }
+        };
+
+        // check if the line involves the transaction
+        let (id, client_id, transaction) = parsed;
+        let refers_to_transaction = match transaction {
+            Transaction::Deposit(_) | Transaction::Withdrawal(_) => id == transaction_id,
+            Transaction::Dispute(id) | Transaction::Resolve(id) | Transaction::Chargeback(id) => 
+                id == transaction_id,
+            Transaction::Open | Transaction::Close => false,
+        };
+        let involved = refers_to_transaction && match owner {
+            Some(owner) => owner == client_id,
+            None => true,
+        };
+
+        // execute the line, recording the state of the client before and after it
+        let state = |clients_map: &ClientMap<S>| clients_map.get(&client_id)
+            .map(|client| (client.n_transactions(), client.is_disputed(&transaction_id)));
+        let before = state(clients_map);
+        let outcome = execute_parsed_line(clients_map, parsed, notes, n_line, config)
+            .map_err(|error| ProcessingError::from_line_error(error, n_line))?;
+        if !involved { continue; }
+        let after = state(clients_map);
+
+        // describe the effect of the line: deposits and withdrawals are applied if they are added
+        // to the history, and disputes, resolves, and chargebacks if they change the dispute
+        // status of the transaction
+        let effect = match outcome {
+            LineOutcome::Skipped(message) => format!("skipped: {}", message),
+            LineOutcome::Queued => "queued on the locked account".to_string(),
+            LineOutcome::Applied => {
+                let applied = match transaction {
+                    Transaction::Deposit(_) | Transaction::Withdrawal(_) => 
+                        before.map(|state| state.0) != after.map(|state| state.0),
+                    _ => before.map(|state| state.1) != after.map(|state| state.1),
+                };
+                let status = match transaction {
+                    Transaction::Dispute(_) => "applied, the transaction is under dispute",
+                    Transaction::Resolve(_) => "applied, the dispute is resolved",
+                    Transaction::Chargeback(_) => 
+                        "applied, the transaction is charged back and the account locked",
+                    _ => "applied",
+                };
+                match clients_map.get_summary(&client_id) {
+                    Some(summary) if applied => {
+                        owner = Some(client_id);
+                        format!("{} (available {}, held {}, total {})", 
+                                status, summary.available, summary.held, summary.total)
+                    },
+                    _ => "no effect".to_string(),
+                }
+            },
+        };
+        steps.push(TraceStep { 
+            n_line, 
+            description: format!("{} by client {}: {}", transaction, client_id, effect),
+        });
+    }
+    Ok(steps)
 }
 
 
-fn parse_resolve(fields: &mut std::str::Split<char>) 
-    -> Result<(TransactionId, ClientId, Transaction), InvalidTransactionLineWarning> 
+/// a step in the history of a transaction (see `[explain_transaction]`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceStep {
+    pub n_line: usize,
+    pub description: String,
+}
+
+impl std::fmt::Display for TraceStep {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.n_line, self.description)
+    }
+}
+
+
+/// statistics about the processing of a file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ProcessingStats {
+
+    /// number of non-empty lines, including the headers but not the data lines outside the range
+    /// given by `[ProcessingConfig::skip_lines]` and `[ProcessingConfig::take_lines]`
+    pub lines: usize,
+
+    /// number of transactions applied
+    pub applied: usize,
+
+    /// number of lines skipped with a warning
+    pub skipped: usize,
+
+    /// number of transactions queued on locked accounts
+    pub queued: usize,
+}
+
+
+/// outcome of the processing of a single data line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LineOutcome {
+
+    /// the transaction was executed (possibly without effect, as a dispute of an unknown
+    /// transaction)
+    Applied,
+
+    /// the line was skipped with a warning, for the given reason
+    Skipped(String),
+
+    /// the transaction was queued on a locked account (see
+    /// `[ProcessingConfig::queue_when_locked]`)
+    Queued,
+}
+
+impl std::fmt::Display for LineOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            LineOutcome::Applied => write!(f, "applied"),
+            LineOutcome::Skipped(message) => write!(f, "skipped: {}", message),
+            LineOutcome::Queued => write!(f, "queued"),
+        }
+    }
+}
+
+
+// parse and execute a single non-empty line
+fn execute_line<S: ClientStore>(clients_map: &mut ClientMap<S>, line: &str, n_line: usize, 
+                config: &ProcessingConfig) 
+    -> Result<LineOutcome, Box<dyn std::error::Error>>
 {
-    let (transaction_id, client_id) = parse_ids(fields)?;
-    Ok((TransactionId::default(), client_id, Transaction::Resolve(transaction_id)))
+
+    match parse_line_with_notes(line, n_line, config) {
+        Ok((parsed, notes)) => execute_parsed_line(clients_map, parsed, notes, n_line, config),
+        Err(warning) => handle_invalid_line(warning, n_line, config),
+    }
 }
 
 
-fn parse_chargeback(fields: &mut std::str::Split<char>) 
-    -> Result<(TransactionId, ClientId, Transaction), InvalidTransactionLineWarning> 
+// skip an invalid line with a warning or, for the categories of lines rejected by the strict
+// policies, return an error
+fn handle_invalid_line(warning: InvalidTransactionLineWarning, n_line: usize, 
+                       config: &ProcessingConfig) 
+    -> Result<LineOutcome, Box<dyn std::error::Error>>
 {
-    let (transaction_id, client_id) = parse_ids(fields)?;
-    Ok((TransactionId::default(), client_id, Transaction::Chargeback(transaction_id)))
+    let strict_error = match warning {
+        InvalidTransactionLineWarning::UnknownType(_) => config.error_on_unknown_type,
+        InvalidTransactionLineWarning::PrecisionLoss(_) 
+            | InvalidTransactionLineWarning::LeadingZeroId(_) 
+            | InvalidTransactionLineWarning::TrailingDecimalPoint(_) 
+            | InvalidTransactionLineWarning::LeadingPlus(_) => true,
+        _ => false
+    };
+    if strict_error {
+        return Err(Box::new(StrictModeError { n_line, message: warning.to_string() }));
+    }
+    let warning_message = format!("{} (line {})", warning, n_line);
+    skip_line(config, n_line, warning.to_string(), warning_message)
 }
 
 
-fn parse_deposit(fields: &mut std::str::Split<char>) 
-    -> Result<(TransactionId, ClientId, Transaction), InvalidTransactionLineWarning> 
+// execute the transaction of a parsed line
+fn execute_parsed_line<S: ClientStore>(clients_map: &mut ClientMap<S>, 
+                                       (transaction_id, client_id, transaction): ParsedLine, 
+                                       notes: Option<String>, 
+                                       n_line: usize, 
+                                       config: &ProcessingConfig) 
+    -> Result<LineOutcome, Box<dyn std::error::Error>>
 {
-    let (transaction_id, client_id) = parse_ids(fields)?;
-    let amount: f64;
-    match fields.next() {
-        Some(s) => match s.trim().parse::<f64>() {
-            Ok(n) => amount = n,
-            Err(_) => return Err(InvalidTransactionLineWarning {})
+    // check that the amount is not negative
+    if let Transaction::Deposit(amount) | Transaction::Withdrawal(amount) = transaction {
+        if amount < 0. {
+            let message = format!("Negative amount {} for transaction {}", 
+                                  amount, transaction_id.0);
+            if config.reject_negative_amounts {
+                return Err(Box::new(StrictModeError { n_line, message }));
+            }
+            return skip_transaction(config, n_line, message);
+        }
+    }
+
+    // if the client is not already in clients_map, add it, unless clients must be opened
+    // explicitly or the maximum number of clients is reached
+    if !(clients_map.contains_key(&client_id)) {
+        if config.require_explicit_open && transaction != Transaction::Open {
+            let message = format!("Client {} has not been opened", client_id);
+            return skip_transaction(config, n_line, message);
+        }
+
+        // check that a new client would not bring the number of clients above the maximum
+        if let Some(max_clients) = config.max_clients {
+            if clients_map.len() >= max_clients {
+                let message = format!("Client {} would bring the number of clients above the maximum of {}", 
+                                      client_id, max_clients);
+                return skip_transaction(config, n_line, message);
+            }
+        }
+        clients_map.get_or_create(client_id);
+    }
+
+    // if required, check that the IDs of the deposits and withdrawals of each client increase
+    if config.check_id_order 
+        && matches!(transaction, Transaction::Deposit(_) | Transaction::Withdrawal(_)) {
+        if let Some(max_id) = clients_map.record_transaction_id(&client_id, transaction_id) {
+            let message = format!("Transaction {} of client {} has a lower ID than transaction {}; the transactions may be out of order", 
+                                  transaction_id.0, client_id, max_id.0);
+            if config.error_on_out_of_order_id {
+                return Err(Box::new(StrictModeError { n_line, message }));
+            }
+            config.warn(format!("Warning: {} (line {})", message, n_line));
+        }
+    }
+
+    // check that disputes, resolves, and chargebacks refer to a suitable transaction
+    if config.error_on_noop_dispute && !clients_map.dispute_applies(&client_id, &transaction) {
+        let message = format!("{:?} for client {} would have no effect", transaction, client_id);
+        return Err(Box::new(StrictModeError { n_line, message }));
+    }
+
+    // if required, queue the transactions on locked accounts
+    if config.queue_when_locked && clients_map.is_locked(&client_id) {
+        clients_map.queue_transaction(transaction_id, client_id, transaction)?;
+        return Ok(LineOutcome::Queued);
+    }
+
+    // execute the transaction
+    match clients_map.execute_transaction(transaction_id, client_id, transaction, config) {
+        Ok(()) => {
+            if config.notes_column {
+                clients_map.set_last_notes(&client_id, notes);
+            }
+            Ok(LineOutcome::Applied)
+        },
+        // a withdrawal exceeding the available funds is skipped (its error names the client)
+        Err(error) if error.is::<InsufficientFundsError>() => {
+            skip_transaction(config, n_line, error.to_string())
         },
-        None => return Err(InvalidTransactionLineWarning {})
+        Err(error) => {
+            let skip = error.is::<ClosedAccountError>() || error.is::<MaxHeldExceededError>() 
+                || error.is::<WithdrawalLimitError>() || error.is::<UnsafeTotalError>() 
+                || error.is::<AmountOverflowError>() 
+                || (error.is::<LockedAccountError>() && !config.error_on_locked);
+            if !skip {
+                return Err(error);
+            }
+            skip_transaction(config, n_line, format!("{} (client {})", error, client_id))
+        }
     }
-    Ok((transaction_id, client_id, Transaction::Deposit(amount)))
 }
 
 
-fn parse_withdrawal(fields: &mut std::str::Split<char>) 
-    -> Result<(TransactionId, ClientId, Transaction), InvalidTransactionLineWarning> 
+// skip a line with a warning or, in fail-fast mode, return an error
+fn skip_line(config: &ProcessingConfig, n_line: usize, message: String, warning: String) 
+    -> Result<LineOutcome, Box<dyn std::error::Error>>
 {
-    let (transaction_id, client_id) = parse_ids(fields)?;
-    let amount: f64;
-    match fields.next() {
-        Some(s) => match s.trim().parse::<f64>() {
-            Ok(n) => amount = n,
-            Err(_) => return Err(InvalidTransactionLineWarning {})
-        },
-        None => return Err(InvalidTransactionLineWarning {})
+    if config.fail_fast {
+        return Err(Box::new(StrictModeError { n_line, message }));
     }
-    Ok((transaction_id, client_id, Transaction::Withdrawal(amount)))
+    config.warn(warning);
+    Ok(LineOutcome::Skipped(message))
 }
 
-fn parse_ids(fields: &mut std::str::Split<char>) 
-    -> Result<(TransactionId, ClientId), InvalidTransactionLineWarning>
+
+// skip a parsed transaction with a warning or, in fail-fast mode, return an error
+fn skip_transaction(config: &ProcessingConfig, n_line: usize, message: String) 
+    -> Result<LineOutcome, Box<dyn std::error::Error>>
 {
+    let warning = format!("Warning: {}; the transaction will be ignored (line {})", 
+                          message, n_line);
+    skip_line(config, n_line, message, warning)
+}
 
-    let transaction_id: TransactionId;
-    let client_id: ClientId;
-    
-    match fields.next() {
-        Some(s) => match s.trim().parse::<u16>() {
-            Ok(id) => client_id = ClientId(id),
-            Err(_) => return Err(InvalidTransactionLineWarning {})
-        },
-        None => return Err(InvalidTransactionLineWarning {})
+
+pub use crate::parse::InvalidTransactionLineWarning;
+
+
+/// check if a line is a header, i.e., if its first field (up to `delimiter`) is `type`, possibly
+/// quoted
+///
+/// # Example
+///
+/// ```
+/// use banking_exercise::read_csv::is_header;
+///
+/// assert!(is_header("type, client, tx, amount", ','));
+/// assert!(is_header("\"type\", \"client\", \"tx\", \"amount\"", ','));
+/// assert!(!is_header("deposit, 1, 1, 100", ','));
+/// ```
+pub fn is_header(line: &str, delimiter: char) -> bool {
+    line.split(delimiter).next()
+        .is_some_and(|field| unquote(field.trim()).eq_ignore_ascii_case("type"))
+}
+
+
+/// check if a line is a comment, i.e., if it starts with one of `prefixes` after removing the
+/// leading whitespace
+///
+/// # Example
+///
+/// ```
+/// use banking_exercise::read_csv::is_comment;
+///
+/// let prefixes = ["#".to_string(), "//".to_string()];
+/// assert!(is_comment("  // end of day", &prefixes));
+/// assert!(!is_comment("deposit, 1, 1, 100", &prefixes));
+/// ```
+pub fn is_comment(line: &str, prefixes: &[String]) -> bool {
+    let line = line.trim_start();
+    prefixes.iter().any(|prefix| line.starts_with(prefix.as_str()))
+}
+
+
+/// parse a boolean field, such as the `locked` column of client balances, accepting `true`,
+/// `false`, `1`, `0`, `yes`, and `no` (ignoring case and surrounding whitespace)
+///
+/// This function returns `None` for other values, which the caller should report.
+///
+/// # Example
+///
+/// ```
+/// use banking_exercise::read_csv::parse_bool;
+///
+/// assert_eq!(Some(true), parse_bool(" Yes"));
+/// assert_eq!(Some(false), parse_bool("0"));
+/// assert_eq!(None, parse_bool("locked"));
+/// ```
+pub fn parse_bool(field: &str) -> Option<bool> {
+    match field.trim().to_ascii_lowercase().as_str() {
+        "true" | "1" | "yes" => Some(true),
+        "false" | "0" | "no" => Some(false),
+        _ => None
     }
+}
 
-    match fields.next() {
-        Some(s) => match s.trim().parse::<u32>() {
-            Ok(id) => transaction_id = TransactionId(id),
-            Err(_) => return Err(InvalidTransactionLineWarning {})
-        },
-        None => return Err(InvalidTransactionLineWarning {})
+
+/// parse a line into a transaction ID, a client ID, and a transaction
+///
+/// A warning is printed if the line contains more fields than expected; `n_line` is the line
+/// number (starting from 1) used in this warning.
+///
+/// # Errors
+///
+/// This function returns an `[InvalidTransactionLineWarning]` if the line does not represent a
+/// valid transaction.
+///
+/// # Example
+///
+/// ```
+/// use banking_exercise::client::ClientId;
+/// use banking_exercise::config::ProcessingConfig;
+/// use banking_exercise::read_csv::parse_line;
+/// use banking_exercise::transaction::*;
+///
+/// let parsed = parse_line("deposit, 1, 2, 100", 1, &ProcessingConfig::default());
+/// assert_eq!(Ok((TransactionId(2), ClientId(1), Transaction::Deposit(100.))), parsed);
+/// ```
+pub fn parse_line(line: &str, n_line: usize, config: &ProcessingConfig) 
+    -> Result<(TransactionId, ClientId, Transaction), InvalidTransactionLineWarning> 
+{
+    parse_line_with_notes(line, n_line, config).map(|(parsed, _)| parsed)
+}
+
+
+// a transaction ID, a client ID, and a transaction, as returned by `parse_line`
+type ParsedLine = (TransactionId, ClientId, Transaction);
+
+
+// parse a line as `parse_line`, also returning the notes after the expected fields if
+// `config.notes_column` is set and they are not empty
+fn parse_line_with_notes(line: &str, n_line: usize, config: &ProcessingConfig) 
+    -> Result<(ParsedLine, Option<String>), InvalidTransactionLineWarning> 
+{
+    let mut record = parse_record(line, &config.parse_options())?;
+    if let (Some(text), Transaction::Deposit(amount) | Transaction::Withdrawal(amount)) 
+        = (&record.inexact_amount, record.transaction) {
+        config.warn(format!("Warning: Amount {} can not be represented exactly and will be rounded to {} (line {})", 
+                            text, amount, n_line));
     }
-    
-    Ok((transaction_id, client_id))
+
+    // convert the amount to the base currency if required, using the field after it
+    if !config.rates.is_empty() {
+        let fields = &mut record.extra_fields;
+        let currency = if fields.is_empty() { "" } else { fields.remove(0) };
+        record.transaction = convert_to_base_currency(record.transaction, currency.trim(), 
+                                                      &config.rates)?;
+    }
+    let parsed = (record.transaction_id, record.client_id, record.transaction);
+
+    // keep additional data on the same line as notes if required, or handle it as extra data
+    if config.notes_column {
+        let notes = record.extra_fields.join(&config.delimiter.to_string()).trim().to_string();
+        return Ok((parsed, Some(notes).filter(|notes| !notes.is_empty())));
+    }
+    if !record.extra_fields.is_empty() {
+        match config.extra_data {
+            ExtraDataPolicy::WarnAndApply => 
+                config.warn(format!("Additional data on line {}", n_line)),
+            ExtraDataPolicy::WarnAndSkip => return Err(InvalidTransactionLineWarning::ExtraData),
+            ExtraDataPolicy::Silent => (),
+        }
+    }
+
+    Ok((parsed, None))
+}
+
+
+// multiply the amount of a deposit or withdrawal by the rate of its currency; other transactions
+// are returned unchanged
+fn convert_to_base_currency(transaction: Transaction, currency: &str, 
+                            rates: &HashMap<String, f64>) 
+    -> Result<Transaction, InvalidTransactionLineWarning>
+{
+    let rate = || match rates.get(currency) {
+        _ if currency.is_empty() => Err(InvalidTransactionLineWarning::EmptyField("currency")),
+        Some(rate) => Ok(*rate),
+        None => Err(InvalidTransactionLineWarning::UnknownCurrency(currency.to_string())),
+    };
+    Ok(match transaction {
+        Transaction::Deposit(amount) => Transaction::Deposit(amount * rate()?),
+        Transaction::Withdrawal(amount) => Transaction::Withdrawal(amount * rate()?),
+        _ => transaction
+    })
 }
 
 
@@ -174,11 +1040,19 @@ fn parse_ids(fields: &mut std::str::Split<char>)
 mod tests {
     
     use super::*;
+    use crate::config::{ ExcessDecimalsPolicy, OutputConfig, OverWithdrawalPolicy };
+
+    // write some content to a temporary file and return its name
+    fn write_temp_file(name: &str, content: &str) -> String {
+        let path = std::env::temp_dir().join(format!("banking_exercise_{}.csv", name));
+        std::fs::write(&path, content).unwrap();
+        path.to_str().unwrap().to_string()
+    }
 
     #[test]
     fn parse_line_1() {
         let line = "deposit, 1, 2, 10000";
-        let parsed_line = parse_line(line, 0, false);
+        let parsed_line = parse_line(line, 0, &ProcessingConfig::default());
         assert_eq!(Ok((TransactionId(2), ClientId(1), Transaction::Deposit(10000.))), 
                    parsed_line);
     }
@@ -186,7 +1060,7 @@ mod tests {
     #[test]
     fn parse_line_2() {
         let line = "withdrawal, 1, 2, 10000";
-        let parsed_line = parse_line(line, 0, false);
+        let parsed_line = parse_line(line, 0, &ProcessingConfig::default());
         assert_eq!(Ok((TransactionId(2), ClientId(1), Transaction::Withdrawal(10000.))), 
                    parsed_line);
     }
@@ -194,7 +1068,7 @@ mod tests {
     #[test]
     fn parse_line_3() {
         let line = "dispute, 1, 2";
-        let parsed_line = parse_line(line, 0, false);
+        let parsed_line = parse_line(line, 0, &ProcessingConfig::default());
         assert_eq!(Ok((TransactionId::default(), ClientId(1), Transaction::Dispute(TransactionId(2)))), 
                    parsed_line);
     }
@@ -202,7 +1076,7 @@ mod tests {
     #[test]
     fn parse_line_4() {
         let line = "resolve, 1, 2";
-        let parsed_line = parse_line(line, 0, false);
+        let parsed_line = parse_line(line, 0, &ProcessingConfig::default());
         assert_eq!(Ok((TransactionId::default(), ClientId(1), Transaction::Resolve(TransactionId(2)))), 
                    parsed_line);
     }
@@ -210,8 +1084,1183 @@ mod tests {
     #[test]
     fn parse_line_5() {
         let line = "chargeback, 1, 2";
-        let parsed_line = parse_line(line, 0, false);
+        let parsed_line = parse_line(line, 0, &ProcessingConfig::default());
         assert_eq!(Ok((TransactionId::default(), ClientId(1), Transaction::Chargeback(TransactionId(2)))), 
                    parsed_line);
     }
+    
+    #[test]
+    fn parse_line_6() {
+        let line = "open, 1";
+        let parsed_line = parse_line(line, 0, &ProcessingConfig::default());
+        assert_eq!(Ok((TransactionId::default(), ClientId(1), Transaction::Open)), parsed_line);
+    }
+    
+    #[test]
+    fn parse_line_7() {
+        let line = "close, 1";
+        let parsed_line = parse_line(line, 0, &ProcessingConfig::default());
+        assert_eq!(Ok((TransactionId::default(), ClientId(1), Transaction::Close)), parsed_line);
+    }
+    
+    #[test]
+    // deposits with too few fields
+    fn parse_line_8() {
+        let config = ProcessingConfig::default();
+        for (line, found) in [("deposit", 1), ("deposit, 1", 2), ("deposit, 1, 2", 3)] {
+            let parsed_line = parse_line(line, 0, &config);
+            assert_eq!(Err(InvalidTransactionLineWarning::TooFewFields { found, expected: 4 }), 
+                       parsed_line);
+        }
+        assert_eq!("too few fields: found 2, expected 4", 
+                   parse_line("deposit, 1", 0, &config).unwrap_err().to_string());
+    }
+    
+    #[test]
+    // the lenient mode warns about an unknown transaction type while the strict mode rejects it
+    fn strict_rejects_unknown_type() {
+        let file_name = write_temp_file("strict_rejects_unknown_type", 
+                                        "type, client, tx, amount\n\
+                                         deposit, 1, 1, 100\n\
+                                         transfer, 1, 2, 50\n\
+                                         deposit, 1, 3, 20\n");
+
+        // lenient mode: the unknown line is skipped and the other transactions are executed
+        let mut clients_map = ClientMap::default();
+        execute_transactions_from_csv(&mut clients_map, &file_name, 
+                                      &ProcessingConfig::default()).unwrap();
+        assert_eq!("client, available, held, total, locked\n1, 120, 0, 120, false\n", 
+                   format!("{}", clients_map));
+
+        // strict mode: the file is rejected
+        let mut clients_map = ClientMap::default();
+        let result = execute_transactions_from_csv(&mut clients_map, &file_name, 
+                                                   &ProcessingConfig::strict());
+        assert!(result.is_err());
+    }
+    
+    #[test]
+    fn strict_rejects_negative_amount() {
+        let file_name = write_temp_file("strict_rejects_negative_amount", 
+                                        "type, client, tx, amount\n\
+                                         deposit, 1, 1, 100\n\
+                                         deposit, 1, 2, -50\n");
+
+        // lenient mode: the negative deposit is ignored
+        let mut clients_map = ClientMap::default();
+        execute_transactions_from_csv(&mut clients_map, &file_name, 
+                                      &ProcessingConfig::default()).unwrap();
+        assert_eq!("client, available, held, total, locked\n1, 100, 0, 100, false\n", 
+                   format!("{}", clients_map));
+
+        // strict mode, overriding all policies but the negative amounts one
+        let config = ProcessingConfig { reject_negative_amounts: true, ..Default::default() };
+        let mut clients_map = ClientMap::default();
+        assert!(execute_transactions_from_csv(&mut clients_map, &file_name, &config).is_err());
+    }
+    
+    #[test]
+    // the commit hook is called every `commit_interval` applied transactions
+    fn commit_hook_1() {
+        use std::rc::Rc;
+        use std::cell::Cell;
+        use crate::config::CommitHook;
+
+        // ten valid transactions, and an invalid one which should not be counted
+        let file_name = write_temp_file("commit_hook_1", 
+                                        "type, client, tx, amount\n\
+                                         deposit, 1, 1, 100\n\
+                                         deposit, 2, 2, 100\n\
+                                         deposit, 1, 3, 100\n\
+                                         withdrawal, 1, 4, 50\n\
+                                         invalid, 1, 5, 10\n\
+                                         dispute, 2, 2\n\
+                                         resolve, 2, 2\n\
+                                         deposit, 3, 6, 10\n\
+                                         deposit, 3, 7, 10\n\
+                                         deposit, 3, 8, 10\n\
+                                         deposit, 3, 9, 10\n");
+
+        // count the calls, checking that the map is not empty at each call
+        let n_calls = Rc::new(Cell::new(0));
+        let n_calls_in_hook = Rc::clone(&n_calls);
+        let hook = CommitHook::new(3, move |clients_map| {
+            n_calls_in_hook.set(n_calls_in_hook.get() + 1);
+            assert!(!clients_map.is_empty());
+        });
+        let config = ProcessingConfig { commit_hook: Some(hook), ..Default::default() };
+
+        let mut clients_map = ClientMap::default();
+        execute_transactions_from_csv(&mut clients_map, &file_name, &config).unwrap();
+        assert_eq!(3, n_calls.get());
+    }
+    
+    #[test]
+    // when clients must be opened explicitly, transactions on other clients are skipped
+    fn require_explicit_open_1() {
+        let file_name = write_temp_file("require_explicit_open_1", 
+                                        "type, client, tx, amount\n\
+                                         open, 1\n\
+                                         deposit, 1, 1, 100\n\
+                                         deposit, 2, 2, 100\n\
+                                         dispute, 2, 2\n");
+
+        // by default, both clients are created
+        let mut clients_map = ClientMap::default();
+        execute_transactions_from_csv(&mut clients_map, &file_name, 
+                                      &ProcessingConfig::default()).unwrap();
+        assert_eq!("client, available, held, total, locked\n\
+                    1, 100, 0, 100, false\n\
+                    2, 0, 100, 100, false\n", 
+                   format!("{}", clients_map));
+
+        // with explicit opening, only the first client exists
+        let config = ProcessingConfig { require_explicit_open: true, ..Default::default() };
+        let mut clients_map = ClientMap::default();
+        execute_transactions_from_csv(&mut clients_map, &file_name, &config).unwrap();
+        assert_eq!("client, available, held, total, locked\n\
+                    1, 100, 0, 100, false\n", 
+                   format!("{}", clients_map));
+    }
+    
+    #[test]
+    // withdrawals exceeding the available funds are ignored by default, or reverse a disputed
+    // deposit with the same amount
+    fn over_withdrawal_1() {
+        let file_name = write_temp_file("over_withdrawal_1", 
+                                        "type, client, tx, amount\n\
+                                         deposit, 1, 1, 100\n\
+                                         deposit, 1, 2, 30\n\
+                                         dispute, 1, 1\n\
+                                         withdrawal, 1, 3, 100\n\
+                                         withdrawal, 1, 4, 50\n");
+
+        // default: both withdrawals are ignored
+        let mut clients_map = ClientMap::default();
+        execute_transactions_from_csv(&mut clients_map, &file_name, 
+                                      &ProcessingConfig::default()).unwrap();
+        assert_eq!("client, available, held, total, locked\n1, 30, 100, 130, false\n", 
+                   format!("{}", clients_map));
+
+        // reversal: the first withdrawal reverses the disputed deposit, the second one (which
+        // matches no disputed deposit) is ignored
+        let config = ProcessingConfig { over_withdrawal: OverWithdrawalPolicy::ReverseDeposit, 
+                                        ..Default::default() };
+        let mut clients_map = ClientMap::default();
+        execute_transactions_from_csv(&mut clients_map, &file_name, &config).unwrap();
+        assert_eq!("client, available, held, total, locked\n1, 30, 0, 30, false\n", 
+                   format!("{}", clients_map));
+    }
+    
+    #[test]
+    fn processing_stats_1() {
+        let file_name = write_temp_file("processing_stats_1", 
+                                        "type, client, tx, amount\n\
+                                         deposit, 1, 1, 100\n\
+                                         \n\
+                                         deposit, 1, 2, -10\n\
+                                         invalid\n\
+                                         withdrawal, 1, 3, 30\n\
+                                         withdrawal, 1, 4, 80\n");
+        let mut clients_map = ClientMap::default();
+        let stats = execute_transactions_from_csv(&mut clients_map, &file_name, 
+                                                  &ProcessingConfig::default()).unwrap().stats;
+        assert_eq!(ProcessingStats { lines: 6, applied: 2, skipped: 3, queued: 0 }, stats);
+    }
+    
+    #[test]
+    // amounts which can not be represented exactly are accepted with a warning in lenient mode
+    // and rejected in strict mode
+    fn precision_loss_1() {
+        let line = "deposit, 1, 2, 100.00000000000000001";
+        let parsed_line = parse_line(line, 1, &ProcessingConfig::default());
+        assert_eq!(Ok((TransactionId(2), ClientId(1), Transaction::Deposit(100.))), parsed_line);
+        let parsed_line = parse_line(line, 1, &ProcessingConfig::strict());
+        assert_eq!(Err(InvalidTransactionLineWarning::PrecisionLoss("100.00000000000000001".to_string())), 
+                   parsed_line);
+
+        // the strict mode rejects the file
+        let file_name = write_temp_file("precision_loss_1", 
+                                        "type, client, tx, amount\n\
+                                         deposit, 1, 2, 100.00000000000000001\n");
+        let mut clients_map = ClientMap::default();
+        assert!(execute_transactions_from_csv(&mut clients_map, &file_name, 
+                                              &ProcessingConfig::strict()).is_err());
+    }
+    
+    #[test]
+    // transactions on a locked account can be queued and executed when it is unlocked
+    fn queue_when_locked_1() {
+        let file_name = write_temp_file("queue_when_locked_1", 
+                                        "type, client, tx, amount\n\
+                                         deposit, 1, 1, 100\n\
+                                         deposit, 1, 2, 50\n\
+                                         dispute, 1, 1\n\
+                                         chargeback, 1, 1\n\
+                                         deposit, 1, 3, 20\n\
+                                         withdrawal, 1, 4, 60\n");
+
+        // by default, the transactions after the chargeback are skipped
+        let config = ProcessingConfig::default();
+        let mut clients_map = ClientMap::default();
+        let stats = execute_transactions_from_csv(&mut clients_map, &file_name, &config).unwrap().stats;
+        assert_eq!(2, stats.skipped);
+        clients_map.unlock(ClientId(1), &config).unwrap();
+        assert_eq!("client, available, held, total, locked\n1, 50, 0, 50, false\n", 
+                   format!("{}", clients_map));
+
+        // when queued, they are executed in order after unlocking the account
+        let config = ProcessingConfig { queue_when_locked: true, ..Default::default() };
+        let mut clients_map = ClientMap::default();
+        let stats = execute_transactions_from_csv(&mut clients_map, &file_name, &config).unwrap().stats;
+        assert_eq!(2, stats.queued);
+        assert_eq!("client, available, held, total, locked\n1, 50, 0, 50, true\n", 
+                   format!("{}", clients_map));
+        clients_map.unlock(ClientId(1), &config).unwrap();
+        assert_eq!("client, available, held, total, locked\n1, 10, 0, 10, false\n", 
+                   format!("{}", clients_map));
+    }
+    
+    #[test]
+    // a file with a single transaction and no header, with or without a trailing newline
+    fn single_line_1() {
+        for content in ["deposit, 1, 1, 100", "deposit, 1, 1, 100\n"] {
+            let file_name = write_temp_file("single_line_1", content);
+            let mut clients_map = ClientMap::default();
+            let stats = execute_transactions_from_csv(&mut clients_map, &file_name, 
+                                                      &ProcessingConfig::default()).unwrap().stats;
+            assert_eq!(ProcessingStats { lines: 1, applied: 1, skipped: 0, queued: 0 }, stats);
+            assert_eq!("client, available, held, total, locked\n1, 100, 0, 100, false\n", 
+                       format!("{}", clients_map));
+        }
+    }
+    
+    #[test]
+    // line numbers in errors start from 1
+    fn line_numbers_1() {
+        let file_name = write_temp_file("line_numbers_1", 
+                                        "type, client, tx, amount\n\
+                                         deposit, 1, 1, 100\n\
+                                         deposit, 1, 2, -100\n");
+        let mut clients_map = ClientMap::default();
+        let error = execute_transactions_from_csv(&mut clients_map, &file_name, 
+                                                  &ProcessingConfig::strict()).unwrap_err();
+        assert_eq!("Negative amount -100 for transaction 2 (line 3)", error.to_string());
+    }
+    
+    #[test]
+    // an invalid first line which is not a header is reported
+    fn header_1() {
+        let file_name = write_temp_file("header_1", 
+                                        "invalid, 1, 1, 100\n\
+                                         deposit, 1, 1, 100\n");
+        let mut clients_map = ClientMap::default();
+        let stats = execute_transactions_from_csv(&mut clients_map, &file_name, 
+                                                  &ProcessingConfig::default()).unwrap().stats;
+        assert_eq!(ProcessingStats { lines: 2, applied: 1, skipped: 1, queued: 0 }, stats);
+
+        // a header is not reported, unless header detection is disabled
+        let file_name = write_temp_file("header_1_bis", 
+                                        "type, client, tx, amount\n\
+                                         deposit, 1, 1, 100\n");
+        let mut clients_map = ClientMap::default();
+        let stats = execute_transactions_from_csv(&mut clients_map, &file_name, 
+                                                  &ProcessingConfig::default()).unwrap().stats;
+        assert_eq!(ProcessingStats { lines: 2, applied: 1, skipped: 0, queued: 0 }, stats);
+        let config = ProcessingConfig { detect_header: false, ..Default::default() };
+        let mut clients_map = ClientMap::default();
+        let stats = execute_transactions_from_csv(&mut clients_map, &file_name, &config).unwrap().stats;
+        assert_eq!(ProcessingStats { lines: 2, applied: 1, skipped: 1, queued: 0 }, stats);
+    }
+    
+    #[test]
+    // a transaction on a locked account is skipped, unless in fail-fast mode
+    fn fail_fast_1() {
+        let file_name = write_temp_file("fail_fast_1", 
+                                        "deposit, 1, 1, 100\n\
+                                         dispute, 1, 1\n\
+                                         chargeback, 1, 1\n\
+                                         deposit, 1, 2, 20\n\
+                                         deposit, 2, 3, 30\n");
+
+        // keep going (default)
+        let mut clients_map = ClientMap::default();
+        let stats = execute_transactions_from_csv(&mut clients_map, &file_name, 
+                                                  &ProcessingConfig::default()).unwrap().stats;
+        assert_eq!(ProcessingStats { lines: 5, applied: 4, skipped: 1, queued: 0 }, stats);
+
+        // fail fast: processing stops at the fourth line
+        let config = ProcessingConfig { fail_fast: true, ..Default::default() };
+        let mut clients_map = ClientMap::default();
+        let error = execute_transactions_from_csv(&mut clients_map, &file_name, &config)
+            .unwrap_err();
+        assert_eq!("The client account is locked (client 1) (line 4)", error.to_string());
+        assert!(!clients_map.contains_key(&ClientId(2)));
+    }
+    
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serialize_warnings() {
+        let serialize = |warning| serde_json::to_string(&warning).unwrap();
+        assert_eq!(r#"{"UnknownType":"transfer"}"#, 
+                   serialize(InvalidTransactionLineWarning::UnknownType("transfer".to_string())));
+        assert_eq!(r#"{"TooFewFields":{"found":2,"expected":4}}"#, 
+                   serialize(InvalidTransactionLineWarning::TooFewFields { found: 2, expected: 4 }));
+        assert_eq!(r#"{"PrecisionLoss":"0.1234567890123456789"}"#, 
+                   serialize(InvalidTransactionLineWarning::PrecisionLoss(
+                           "0.1234567890123456789".to_string())));
+        assert_eq!(r#""InvalidFields""#, serialize(InvalidTransactionLineWarning::InvalidFields));
+    }
+    
+    #[test]
+    // a file using semicolons as delimiters
+    fn delimiter_1() {
+        let file_name = write_temp_file("delimiter_1", 
+                                        "type; client; tx; amount\n\
+                                         deposit; 1; 1; 100\n\
+                                         withdrawal; 1; 2; 40\n");
+        let config = ProcessingConfig { delimiter: ';', ..Default::default() };
+        let mut clients_map = ClientMap::default();
+        let stats = execute_transactions_from_csv(&mut clients_map, &file_name, &config).unwrap().stats;
+        assert_eq!(ProcessingStats { lines: 3, applied: 2, skipped: 0, queued: 0 }, stats);
+        assert_eq!("client, available, held, total, locked\n1, 60, 0, 60, false\n", 
+                   format!("{}", clients_map));
+    }
+    
+    #[test]
+    // amounts with currency symbols
+    fn currency_symbols_1() {
+        let config = ProcessingConfig { currency_symbols: vec!["$".to_string(), "USD".to_string()], 
+                                        ..Default::default() };
+        for line in ["deposit, 1, 2, $100.00", "deposit, 1, 2, 100.00 USD"] {
+            assert_eq!(Ok((TransactionId(2), ClientId(1), Transaction::Deposit(100.))), 
+                       parse_line(line, 1, &config));
+        }
+
+        // unknown symbols, or symbols when not enabled, are still invalid
+        assert_eq!(Err(InvalidTransactionLineWarning::InvalidFields), 
+                   parse_line("deposit, 1, 2, 100.00 EUR", 1, &config));
+        assert_eq!(Err(InvalidTransactionLineWarning::InvalidFields), 
+                   parse_line("deposit, 1, 2, $100.00", 1, &ProcessingConfig::default()));
+    }
+    
+    #[test]
+    // a dispute appearing before its deposit is deferred and applied once the deposit is read,
+    // followed by the deferred resolve of the same transaction; the dispute of a transaction which
+    // never appears has no effect at the end
+    fn defer_unknown_disputes_1() {
+        let content = "dispute, 1, 1\n\
+                       dispute, 1, 9\n\
+                       deposit, 1, 1, 100\n\
+                       deposit, 1, 2, 50\n\
+                       dispute, 2, 3\n\
+                       resolve, 2, 3\n\
+                       deposit, 2, 3, 10\n\
+                       withdrawal, 2, 4, 5\n";
+        let config = ProcessingConfig { defer_unknown_disputes: Some(10), 
+                                        record_line_results: true, ..Default::default() };
+        let mut clients_map = ClientMap::default();
+        let report = execute_transactions_from_bytes(&mut clients_map, content.as_bytes(), &config)
+            .unwrap();
+        assert_eq!(ProcessingStats { lines: 8, applied: 8, skipped: 0, queued: 0 }, report.stats);
+        assert_eq!((1..=8).collect::<Vec<_>>(), 
+                   report.line_results.iter().map(|result| result.n_line).collect::<Vec<_>>());
+        assert_eq!("client, available, held, total, locked\n\
+                    1, 50, 100, 150, false\n\
+                    2, 5, 0, 5, false\n", 
+                   format!("{}", clients_map));
+
+        // without deferral, the early dispute has no effect
+        let mut clients_map = ClientMap::default();
+        execute_transactions_from_bytes(&mut clients_map, content.as_bytes(), 
+                                        &ProcessingConfig::default()).unwrap();
+        assert_eq!(Some(0.), clients_map.get_summary(&ClientId(1)).map(|summary| summary.held));
+    }
+    
+    #[test]
+    // once the bound is reached, disputes of unknown transactions are executed immediately
+    fn defer_unknown_disputes_2() {
+        let content = "dispute, 1, 1\n\
+                       dispute, 1, 2\n\
+                       deposit, 1, 1, 100\n\
+                       deposit, 1, 2, 50\n";
+        let config = ProcessingConfig { defer_unknown_disputes: Some(1), ..Default::default() };
+        let mut clients_map = ClientMap::default();
+        execute_transactions_from_bytes(&mut clients_map, content.as_bytes(), &config).unwrap();
+        assert_eq!("client, available, held, total, locked\n1, 50, 100, 150, false\n", 
+                   format!("{}", clients_map));
+    }
+    
+    #[test]
+    // processing the same file twice into the same map has no effect the second time, while
+    // another file is still processed
+    fn skip_processed_files_1() {
+        let file_name = write_temp_file("skip_processed_files_1", 
+                                        "deposit, 1, 1, 100\n\
+                                         deposit, 1, 2, 50\n\
+                                         dispute, 1, 1\n\
+                                         resolve, 1, 1\n\
+                                         dispute, 1, 2\n");
+        let config = ProcessingConfig { skip_processed_files: true, quiet: true, 
+                                        ..Default::default() };
+        let mut clients_map = ClientMap::default();
+        let stats = execute_transactions_from_csv(&mut clients_map, &file_name, &config).unwrap()
+            .stats;
+        assert_eq!(ProcessingStats { lines: 5, applied: 5, skipped: 0, queued: 0 }, stats);
+        let output = clients_map.to_string();
+        let n_transactions = clients_map.get(&ClientId(1)).unwrap().n_transactions();
+
+        // the same content, read from a file or from memory, is skipped
+        let stats = execute_transactions_from_csv(&mut clients_map, &file_name, &config).unwrap()
+            .stats;
+        assert_eq!(ProcessingStats::default(), stats);
+        let content = std::fs::read(&file_name).unwrap();
+        execute_transactions_from_bytes(&mut clients_map, &content, &config).unwrap();
+        assert_eq!(output, clients_map.to_string());
+        assert_eq!(n_transactions, clients_map.get(&ClientId(1)).unwrap().n_transactions());
+
+        // other content is processed
+        let stats = execute_transactions_from_bytes(&mut clients_map, b"resolve, 1, 2\n", &config)
+            .unwrap().stats;
+        assert_eq!(1, stats.applied);
+        assert_eq!("client, available, held, total, locked\n1, 150, 0, 150, false\n", 
+                   clients_map.to_string());
+    }
+    
+    #[test]
+    // a dispute bringing the held funds above the maximum is skipped
+    fn max_held_1() {
+        let file_name = write_temp_file("max_held_1", 
+                                        "deposit, 1, 1, 100\n\
+                                         deposit, 1, 2, 50\n\
+                                         dispute, 1, 1\n\
+                                         dispute, 1, 2\n");
+        let config = ProcessingConfig { max_held: Some(120.), ..Default::default() };
+        let mut clients_map = ClientMap::default();
+        let stats = execute_transactions_from_csv(&mut clients_map, &file_name, &config).unwrap().stats;
+        assert_eq!(ProcessingStats { lines: 4, applied: 3, skipped: 1, queued: 0 }, stats);
+        assert_eq!("client, available, held, total, locked\n1, 50, 100, 150, false\n", 
+                   format!("{}", clients_map));
+    }
+    
+    #[test]
+    // withdrawals up to the limit succeed, the one crossing it is skipped, and smaller ones which
+    // still fit are executed
+    fn withdrawal_limit_1() {
+        let file_name = write_temp_file("withdrawal_limit_1", 
+                                        "deposit, 1, 1, 1000\n\
+                                         withdrawal, 1, 2, 300\n\
+                                         withdrawal, 1, 3, 200\n\
+                                         withdrawal, 1, 4, 100\n\
+                                         withdrawal, 1, 5, 50\n");
+        let config = ProcessingConfig { withdrawal_limit: Some(550.), quiet: true, 
+                                        ..Default::default() };
+        let mut clients_map = ClientMap::default();
+        let report = execute_transactions_from_csv(&mut clients_map, &file_name, &config).unwrap();
+        assert_eq!(ProcessingStats { lines: 5, applied: 4, skipped: 1, queued: 0 }, report.stats);
+        assert_eq!(vec!["Withdrawal 4 would bring the cumulative withdrawals above the limit of 550 (client 1) (line 4)".to_string()], 
+                   report.line_errors.iter().map(|e| e.to_string()).collect::<Vec<_>>());
+        assert_eq!("client, available, held, total, locked\n1, 450, 0, 450, false\n", 
+                   format!("{}", clients_map));
+    }
+    
+    #[test]
+    // once the maximum number of clients is reached, transactions creating new clients are
+    // skipped, but those of existing clients are still executed
+    fn max_clients_1() {
+        let file_name = write_temp_file("max_clients_1", 
+                                        "deposit, 1, 1, 10\n\
+                                         deposit, 2, 2, 20\n\
+                                         deposit, 3, 3, 30\n\
+                                         dispute, 4, 1\n\
+                                         deposit, 1, 5, 50\n");
+        let config = ProcessingConfig { max_clients: Some(2), quiet: true, ..Default::default() };
+        let mut clients_map = ClientMap::default();
+        let report = execute_transactions_from_csv(&mut clients_map, &file_name, &config).unwrap();
+        assert_eq!(ProcessingStats { lines: 5, applied: 3, skipped: 2, queued: 0 }, report.stats);
+        assert_eq!(vec!["Client 3 would bring the number of clients above the maximum of 2 (line 3)".to_string(), 
+                        "Client 4 would bring the number of clients above the maximum of 2 (line 4)".to_string()], 
+                   report.line_errors.iter().map(|e| e.to_string()).collect::<Vec<_>>());
+        assert_eq!("client, available, held, total, locked\n\
+                    1, 60, 0, 60, false\n\
+                    2, 20, 0, 20, false\n", 
+                   format!("{}", clients_map));
+    }
+    
+    #[test]
+    // comment lines are skipped, with `#` by default and other prefixes if declared
+    fn comment_prefixes_1() {
+        let file_name = write_temp_file("comment_prefixes_1", 
+                                        "# daily export\n\
+                                         deposit, 1, 1, 100\n\
+                                         // manual correction\n\
+                                         \t; withdrawal, 1, 2, 30\n\
+                                         withdrawal, 1, 3, 50\n");
+
+        // by default, only the first comment is recognised
+        let config = ProcessingConfig { quiet: true, ..Default::default() };
+        let mut clients_map = ClientMap::default();
+        let report = execute_transactions_from_csv(&mut clients_map, &file_name, &config).unwrap();
+        assert_eq!(ProcessingStats { lines: 4, applied: 2, skipped: 2, queued: 0 }, report.stats);
+
+        // with the other prefixes, all the comments are skipped
+        let mut config = ProcessingConfig { quiet: true, ..Default::default() };
+        config.comment_prefixes.extend(["//".to_string(), ";".to_string()]);
+        let mut clients_map = ClientMap::default();
+        let report = execute_transactions_from_csv(&mut clients_map, &file_name, &config).unwrap();
+        assert_eq!(ProcessingStats { lines: 2, applied: 2, skipped: 0, queued: 0 }, report.stats);
+        assert_eq!("client, available, held, total, locked\n1, 50, 0, 50, false\n", 
+                   format!("{}", clients_map));
+    }
+    
+    #[test]
+    // with a rate table, the amounts are converted to the base currency, and the lines with an
+    // unknown or missing currency are skipped
+    fn rates_1() {
+        let file_name = write_temp_file("rates_1", 
+                                        "type, client, tx, amount, currency\n\
+                                         deposit, 1, 1, 100, EUR\n\
+                                         deposit, 2, 2, 100, GBP\n\
+                                         deposit, 1, 3, 10, USD\n\
+                                         withdrawal, 2, 4, 20, EUR\n\
+                                         deposit, 1, 5, 100, JPY\n\
+                                         deposit, 1, 6, 100\n\
+                                         dispute, 1, 1, \n");
+        let config = ProcessingConfig { 
+            rates: HashMap::from([("USD".to_string(), 1.), ("EUR".to_string(), 1.1), 
+                                  ("GBP".to_string(), 1.25)]), 
+            quiet: true, 
+            ..Default::default() 
+        };
+        let mut clients_map = ClientMap::default();
+        let report = execute_transactions_from_csv(&mut clients_map, &file_name, &config).unwrap();
+        assert_eq!(vec!["unknown currency `JPY` (line 6)".to_string(), 
+                        "empty currency field (line 7)".to_string()], 
+                   report.line_errors.iter().map(|e| e.to_string()).collect::<Vec<_>>());
+        assert_eq!("client, available, held, total, locked\n\
+                    1, 10, 110, 120, false\n\
+                    2, 103, 0, 103, false\n", 
+                   format!("{}", clients_map.display(&OutputConfig { decimal_places: Some(4), 
+                                                                     ..Default::default() })));
+    }
+    
+    #[test]
+    // a dispute for a client who was never seen creates an empty account, reported as a phantom
+    fn phantom_clients_1() {
+        let file_name = write_temp_file("phantom_clients_1", 
+                                        "deposit, 1, 1, 100\n\
+                                         dispute, 2, 1\n");
+        let mut clients_map = ClientMap::default();
+        execute_transactions_from_csv(&mut clients_map, &file_name, 
+                                      &ProcessingConfig::default()).unwrap();
+        assert_eq!(2, clients_map.len());
+        assert_eq!(vec![ClientId(2)], clients_map.phantom_clients());
+    }
+    
+    #[test]
+    // the report lists the skipped lines
+    fn processing_report_1() {
+        let file_name = write_temp_file("processing_report_1", 
+                                        "type, client, tx, amount\n\
+                                         deposit, 1, 1, 100\n\
+                                         transfer, 1, 2, 50\n\
+                                         \n\
+                                         deposit, 1, 3, -20\n");
+        let mut clients_map = ClientMap::default();
+        let report = execute_transactions_from_csv(&mut clients_map, &file_name, 
+                                                   &ProcessingConfig::default()).unwrap();
+        assert_eq!(1, report.clients_map.len());
+        assert_eq!(ProcessingStats { lines: 4, applied: 1, skipped: 2, queued: 0 }, report.stats);
+        assert_eq!(vec!["unknown transaction type `transfer` (line 3)".to_string(), 
+                        "Negative amount -20 for transaction 3 (line 5)".to_string()], 
+                   report.line_errors.iter().map(|e| e.to_string()).collect::<Vec<_>>());
+
+        // a missing file is an I/O error
+        let error = execute_transactions_from_csv(&mut clients_map, "/nonexistent/file.csv", 
+                                                  &ProcessingConfig::default()).unwrap_err();
+        assert!(matches!(error, ProcessingError::Io(_)));
+    }
+    
+    #[test]
+    // IDs with leading zeros are accepted unless rejected explicitly
+    fn leading_zero_ids_1() {
+        assert_eq!(Ok((TransactionId(2), ClientId(1), Transaction::Deposit(100.))), 
+                   parse_line("deposit, 01, 2, 100", 1, &ProcessingConfig::default()));
+        
+        let config = ProcessingConfig { reject_leading_zero_ids: true, ..Default::default() };
+        assert_eq!(Err(InvalidTransactionLineWarning::LeadingZeroId("01".to_string())), 
+                   parse_line("deposit, 01, 2, 100", 1, &config));
+        assert_eq!(Err(InvalidTransactionLineWarning::LeadingZeroId("002".to_string())), 
+                   parse_line("dispute, 1, 002", 1, &config));
+
+        // a single zero is not a leading zero
+        assert_eq!(Ok((TransactionId(0), ClientId(0), Transaction::Deposit(100.))), 
+                   parse_line("deposit, 0, 0, 100", 1, &config));
+    }
+    
+    #[test]
+    // apply lines one by one
+    fn apply_csv_line_1() {
+        let config = ProcessingConfig::default();
+        let mut clients_map = ClientMap::default();
+        clients_map.apply_csv_line("deposit, 1, 1, 100", &config).unwrap();
+        clients_map.apply_csv_line("dispute, 1, 1", &config).unwrap();
+        assert_eq!("client, available, held, total, locked\n1, 0, 100, 100, false\n", 
+                   format!("{}", clients_map));
+        
+        // invalid lines are skipped, unless they violate a strict policy
+        clients_map.apply_csv_line("transfer, 1, 2, 10", &config).unwrap();
+        let error = clients_map.apply_csv_line("transfer, 1, 2, 10", &ProcessingConfig::strict())
+            .unwrap_err();
+        assert!(matches!(error, ProcessingError::Strict(_)));
+        assert_eq!(1, clients_map.len());
+    }
+    
+    #[test]
+    #[cfg(feature = "reqwest")]
+    // a file served over HTTP is processed as a local one
+    fn url_1() {
+        use std::io::Write;
+        use std::net::TcpListener;
+
+        // serve the file, then a 404 error, on a local port
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            let body = "type, client, tx, amount\ndeposit, 1, 1, 100\nwithdrawal, 1, 2, 40\n";
+            let responses = [format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}", 
+                                     body.len(), body), 
+                             "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n".to_string()];
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+
+                // read the request up to the empty line ending its headers
+                let mut request = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                while request.read_line(&mut line).unwrap() > 2 { line.clear(); }
+
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+
+        let url = format!("http://{}/transactions.csv", address);
+        let mut clients_map = ClientMap::default();
+        let report = execute_transactions_from_url(&mut clients_map, &url, 
+                                                   &ProcessingConfig::default()).unwrap();
+        assert_eq!(ProcessingStats { lines: 3, applied: 2, skipped: 0, queued: 0 }, report.stats);
+        assert_eq!("client, available, held, total, locked\n1, 60, 0, 60, false\n", 
+                   format!("{}", report.clients_map));
+
+        // an error status is reported as an HTTP error
+        let error = execute_transactions_from_url(&mut clients_map, &url, 
+                                                  &ProcessingConfig::default()).unwrap_err();
+        assert!(matches!(error, ProcessingError::Http(_)));
+        server.join().unwrap();
+    }
+    
+    #[test]
+    // integer amounts and amounts ending with a decimal point are accepted, unless the latter are
+    // rejected explicitly
+    fn integer_amounts_1() {
+        let config = ProcessingConfig::default();
+        for amount in ["100", "100.", "100.0"] {
+            assert_eq!(Ok((TransactionId(2), ClientId(1), Transaction::Deposit(100.))), 
+                       parse_line(&format!("deposit, 1, 2, {}", amount), 1, &config));
+        }
+
+        let config = ProcessingConfig { reject_trailing_decimal_point: true, ..Default::default() };
+        assert_eq!(Err(InvalidTransactionLineWarning::TrailingDecimalPoint("100.".to_string())), 
+                   parse_line("deposit, 1, 2, 100.", 1, &config));
+        for amount in ["100", "100.0"] {
+            assert_eq!(Ok((TransactionId(2), ClientId(1), Transaction::Withdrawal(100.))), 
+                       parse_line(&format!("withdrawal, 1, 2, {}", amount), 1, &config));
+        }
+    }
+    
+    #[test]
+    // an explicit leading `+` is accepted, unless rejected explicitly or in strict mode
+    fn leading_plus_1() {
+        let line = "deposit, 1, 2, +100.00";
+        assert_eq!(Ok((TransactionId(2), ClientId(1), Transaction::Deposit(100.))), 
+                   parse_line(line, 1, &ProcessingConfig::default()));
+
+        let config = ProcessingConfig { reject_leading_plus: true, ..Default::default() };
+        assert_eq!(Err(InvalidTransactionLineWarning::LeadingPlus("+100.00".to_string())), 
+                   parse_line(line, 1, &config));
+
+        // in strict mode, processing stops with a clear message
+        let mut clients_map = ClientMap::default();
+        let error = execute_transactions_from_bytes(&mut clients_map, line.as_bytes(), 
+                                                    &ProcessingConfig::strict()).unwrap_err();
+        assert_eq!("amount +100.00 has an explicit leading `+` (line 1)", error.to_string());
+    }
+    
+    #[test]
+    // header rows are skipped wherever they appear
+    fn header_2() {
+        let file_name = write_temp_file("header_2", 
+                                        "type, client, tx, amount\n\
+                                         deposit, 1, 1, 100\n\
+                                         type, client, tx, amount\n\
+                                         deposit, 1, 2, 50\n");
+        let mut clients_map = ClientMap::default();
+        let report = execute_transactions_from_csv(&mut clients_map, &file_name, 
+                                                   &ProcessingConfig::default()).unwrap();
+        assert_eq!(ProcessingStats { lines: 4, applied: 2, skipped: 0, queued: 0 }, report.stats);
+        assert!(report.line_errors.is_empty());
+        assert_eq!("client, available, held, total, locked\n1, 150, 0, 150, false\n", 
+                   format!("{}", report.clients_map));
+    }
+    
+    #[test]
+    // lines with additional data are applied or skipped depending on the configuration
+    fn extra_data_1() {
+        let line = "deposit, 1, 2, 100, junk";
+        for (extra_data, expected) in [
+            (ExtraDataPolicy::WarnAndApply, 
+             Ok((TransactionId(2), ClientId(1), Transaction::Deposit(100.)))),
+            (ExtraDataPolicy::WarnAndSkip, Err(InvalidTransactionLineWarning::ExtraData)),
+            (ExtraDataPolicy::Silent, 
+             Ok((TransactionId(2), ClientId(1), Transaction::Deposit(100.))))] {
+            let config = ProcessingConfig { extra_data, ..Default::default() };
+            assert_eq!(expected, parse_line(line, 1, &config));
+        }
+
+        // skipped lines are reported as such
+        let file_name = write_temp_file("extra_data_1", 
+                                        "deposit, 1, 1, 10\ndeposit, 1, 2, 100, junk\n");
+        let config = ProcessingConfig { extra_data: ExtraDataPolicy::WarnAndSkip, 
+                                        ..Default::default() };
+        let mut clients_map = ClientMap::default();
+        let report = execute_transactions_from_csv(&mut clients_map, &file_name, &config).unwrap();
+        assert_eq!(vec!["additional data after the expected fields (line 2)".to_string()], 
+                   report.line_errors.iter().map(|e| e.to_string()).collect::<Vec<_>>());
+    }
+    
+    #[test]
+    // only the given range of data lines is processed, and line numbers refer to the whole file
+    fn skip_take_1() {
+        // ten data lines after a header, the sixth of which is invalid
+        let lines: Vec<String> = (1..=10)
+            .map(|i| match i {
+                6 => "deposit, 1".to_string(),
+                _ => format!("deposit, 1, {}, {}", i, i)
+            })
+            .collect();
+        let content = format!("type, client, tx, amount\n{}\n", lines.join("\n"));
+        let file_name = write_temp_file("skip_take_1", &content);
+        let config = ProcessingConfig { skip_lines: 3, take_lines: Some(4), ..Default::default() };
+        let mut clients_map = ClientMap::default();
+        let report = execute_transactions_from_csv(&mut clients_map, &file_name, &config).unwrap();
+        assert_eq!(ProcessingStats { lines: 5, applied: 3, skipped: 1, queued: 0 }, report.stats);
+        assert_eq!(vec!["too few fields: found 2, expected 4 (line 7)".to_string()], 
+                   report.line_errors.iter().map(|e| e.to_string()).collect::<Vec<_>>());
+        assert_eq!("client, available, held, total, locked\n1, 16, 0, 16, false\n", 
+                   format!("{}", report.clients_map));
+
+        // without `take_lines`, all the lines after the skipped ones are processed
+        let config = ProcessingConfig { skip_lines: 8, ..Default::default() };
+        let mut clients_map = ClientMap::default();
+        let report = execute_transactions_from_csv(&mut clients_map, &file_name, &config).unwrap();
+        assert_eq!("client, available, held, total, locked\n1, 19, 0, 19, false\n", 
+                   format!("{}", report.clients_map));
+    }
+    
+    #[test]
+    // empty or whitespace-only fields are reported specifically
+    fn empty_fields_1() {
+        let config = ProcessingConfig::default();
+        for (line, name) in [("deposit, , 2, 100", "client"), 
+                             ("withdrawal,  \t, 2, 100", "client"), 
+                             ("deposit, 1, , 100", "tx"), 
+                             ("dispute, 1,  ", "tx"), 
+                             ("deposit, 1, 2, ", "amount"), 
+                             ("open, ", "client")] {
+            assert_eq!(Err(InvalidTransactionLineWarning::EmptyField(name)), 
+                       parse_line(line, 1, &config), "{}", line);
+        }
+
+        // the warning gives the line number
+        let file_name = write_temp_file("empty_fields_1", 
+                                        "deposit, 1, 1, 10\ndeposit, , 2, 100\n");
+        let mut clients_map = ClientMap::default();
+        let report = execute_transactions_from_csv(&mut clients_map, &file_name, &config).unwrap();
+        assert_eq!(vec!["empty client field (line 2)".to_string()], 
+                   report.line_errors.iter().map(|e| e.to_string()).collect::<Vec<_>>());
+    }
+    
+    #[test]
+    // processing a byte slice gives the same result as processing a file with the same content
+    fn bytes_1() {
+        let content = "type, client, tx, amount\n\
+                       deposit, 1, 1, 100\n\
+                       deposit, 2, 2, 50\n\
+                       withdrawal, 1, 3, 30\n\
+                       dispute, 2, 2\n\
+                       transfer, 1, 4, 10\n";
+        let file_name = write_temp_file("bytes_1", content);
+        let config = ProcessingConfig { quiet: true, ..Default::default() };
+        let mut file_clients_map = ClientMap::default();
+        let file_report = execute_transactions_from_csv(&mut file_clients_map, &file_name, &config)
+            .unwrap();
+        let mut bytes_clients_map = ClientMap::default();
+        let bytes_report = execute_transactions_from_bytes(&mut bytes_clients_map, 
+                                                           content.as_bytes(), &config).unwrap();
+        assert_eq!(file_report.stats, bytes_report.stats);
+        assert_eq!(file_report.line_errors, bytes_report.line_errors);
+        assert_eq!(format!("{}", file_report.clients_map), 
+                   format!("{}", bytes_report.clients_map));
+    }
+    
+    #[test]
+    // with a warning hook, the warnings and the output can be combined in a deterministic order
+    fn warning_hook_1() {
+        use std::rc::Rc;
+        use std::cell::RefCell;
+        use crate::config::WarningHook;
+
+        let file_name = write_temp_file("warning_hook_1", 
+                                        "type, client, tx, amount\n\
+                                         deposit, 1, 1, 100\n\
+                                         deposit, 1, x, 100\n\
+                                         deposit, 2, 2, 50, junk\n\
+                                         withdrawal, 1, 3, 30\n\
+                                         deposit, 1, 1, 10\n\
+                                         transfer, 1, 4, 10\n");
+        let buffer = Rc::new(RefCell::new(String::new()));
+        let buffer_in_hook = Rc::clone(&buffer);
+        let hook = WarningHook::new(move |message| {
+            let mut buffer = buffer_in_hook.borrow_mut();
+            buffer.push_str(message);
+            buffer.push('\n');
+        });
+        let config = ProcessingConfig { warning_hook: Some(hook), ..Default::default() };
+        let mut clients_map = ClientMap::default();
+        let report = execute_transactions_from_csv(&mut clients_map, &file_name, &config).unwrap();
+        buffer.borrow_mut().push_str(&report.clients_map.to_string());
+        assert_eq!("invalid transaction line encountered (line 3)\n\
+                    Additional data on line 4\n\
+                    Warning: Transaction 1 of client 1 (deposit 10) has the same ID as an earlier one with different data (deposit 100); the duplicate will be ignored, but the data may be corrupted\n\
+                    unknown transaction type `transfer` (line 7)\n\
+                    client, available, held, total, locked\n\
+                    1, 70, 0, 70, false\n\
+                    2, 50, 0, 50, false\n", 
+                   *buffer.borrow());
+    }
+    
+    #[test]
+    // in all-or-nothing mode, a single invalid line leaves the map untouched
+    fn all_or_nothing_1() {
+        let config = ProcessingConfig { all_or_nothing: true, quiet: true, ..Default::default() };
+        let mut clients_map = ClientMap::default();
+        clients_map.insert(ClientId(1), Client::new(10., 0., false)).unwrap();
+        let file_name = write_temp_file("all_or_nothing_1", 
+                                        "type, client, tx, amount\n\
+                                         deposit, 1, 1, 100\n\
+                                         deposit, 2, 2, 50\n\
+                                         deposit, 2, x, 50\n\
+                                         withdrawal, 1, 3, 30\n\
+                                         transfer, 1, 4, 10\n");
+        let error = execute_transactions_from_csv(&mut clients_map, &file_name, &config)
+            .unwrap_err();
+        assert_eq!("2 invalid line(s), no transaction was applied: \
+                    invalid transaction line encountered (line 4); \
+                    unknown transaction type `transfer` (line 6)", 
+                   error.to_string());
+        assert_eq!("client, available, held, total, locked\n1, 10, 0, 10, false\n", 
+                   format!("{}", clients_map));
+
+        // a valid file is applied as usual
+        let file_name = write_temp_file("all_or_nothing_2", 
+                                        "type, client, tx, amount\n\
+                                         deposit, 1, 1, 100\n\
+                                         deposit, 2, 2, 50\n");
+        let report = execute_transactions_from_csv(&mut clients_map, &file_name, &config)
+            .unwrap();
+        assert_eq!(2, report.stats.applied);
+        assert_eq!("client, available, held, total, locked\n\
+                    1, 110, 0, 110, false\n\
+                    2, 50, 0, 50, false\n", 
+                   format!("{}", report.clients_map));
+    }
+    
+    #[test]
+    // amounts with more than four decimal places under each policy
+    fn excess_decimals_1() {
+        let line = "deposit, 1, 2, 100.123456";
+        for (policy, expected) in [
+            (ExcessDecimalsPolicy::Accept, Ok(100.123456)),
+            (ExcessDecimalsPolicy::Truncate, Ok(100.1234)),
+            (ExcessDecimalsPolicy::Round, Ok(100.1235)),
+            (ExcessDecimalsPolicy::Reject, 
+             Err(InvalidTransactionLineWarning::ExcessDecimalPlaces { 
+                 amount: "100.123456".to_string(), max_decimal_places: 4 
+             })),
+        ] {
+            let config = ProcessingConfig { excess_decimals: policy, ..Default::default() };
+            let expected = expected
+                .map(|amount| (TransactionId(2), ClientId(1), Transaction::Deposit(amount)));
+            assert_eq!(expected, parse_line(line, 1, &config), "{:?}", policy);
+        }
+
+        // amounts with at most four significant decimal places are never changed
+        let config = ProcessingConfig { 
+            excess_decimals: ExcessDecimalsPolicy::Reject, ..Default::default() 
+        };
+        assert_eq!(Ok((TransactionId(2), ClientId(1), Transaction::Withdrawal(100.1234))), 
+                   parse_line("withdrawal, 1, 2, 100.123400", 1, &config));
+        assert_eq!("amount 100.123456 has more than 4 decimal places", 
+                   parse_line(line, 1, &config).unwrap_err().to_string());
+    }
+    
+    #[test]
+    // a currency only allows its own decimal places: none for JPY, and two for USD
+    fn currency_1() {
+        let mut config = ProcessingConfig::default();
+        config.set_currency("JPY").unwrap();
+        assert_eq!(Err(InvalidTransactionLineWarning::ExcessDecimalPlaces { 
+                       amount: "100.5".to_string(), max_decimal_places: 0 
+                   }), 
+                   parse_line("deposit, 1, 2, 100.5", 1, &config));
+        assert_eq!(Ok((TransactionId(2), ClientId(1), Transaction::Deposit(100.))), 
+                   parse_line("deposit, 1, 2, 100", 1, &config));
+
+        config.set_currency("USD").unwrap();
+        assert_eq!(Err(InvalidTransactionLineWarning::ExcessDecimalPlaces { 
+                       amount: "1.005".to_string(), max_decimal_places: 2 
+                   }), 
+                   parse_line("withdrawal, 1, 2, 1.005", 1, &config));
+        assert_eq!(Ok((TransactionId(2), ClientId(1), Transaction::Withdrawal(1.05))), 
+                   parse_line("withdrawal, 1, 2, 1.050", 1, &config));
+
+        // an invalid code leaves the configuration unchanged
+        assert!(config.set_currency("dollar").is_err());
+        assert_eq!(2, config.max_decimal_places);
+    }
+    
+    #[test]
+    // amounts with thousands separators, quoted when the separator is the delimiter
+    fn grouping_separator_1() {
+        let config = ProcessingConfig { grouping_separator: Some(','), quiet: true, 
+                                        ..Default::default() };
+        let expected = Ok((TransactionId(2), ClientId(1), Transaction::Deposit(1_000.)));
+        assert_eq!(expected, parse_line("deposit, 1, 2, \"1,000.00\"", 1, &config));
+        assert_eq!(expected, parse_line("deposit,1,2,\"1,000.00\"", 1, &config));
+        let config = ProcessingConfig { delimiter: ';', ..config };
+        assert_eq!(expected, parse_line("deposit; 1; 2; 1,000.00", 1, &config));
+        assert_eq!(Ok((TransactionId(2), ClientId(1), Transaction::Withdrawal(1_234_567.5))), 
+                   parse_line("withdrawal; 1; 2; \"1,234,567.5\"", 1, &config));
+
+        // without a grouping separator, quoted amounts are accepted but not grouped ones
+        let config = ProcessingConfig::default();
+        assert_eq!(Ok((TransactionId(2), ClientId(1), Transaction::Deposit(100.))), 
+                   parse_line("deposit, 1, 2, \"100\"", 1, &config));
+        assert_eq!(Err(InvalidTransactionLineWarning::InvalidFields), 
+                   parse_line("deposit, 1, 2, \"1,000.00\"", 1, &config));
+
+        // unclosed quotes make the line invalid
+        assert_eq!(Err(InvalidTransactionLineWarning::InvalidFields), 
+                   parse_line("deposit, 1, 2, \"1,000.00", 1, &config));
+    }
+    
+    #[test]
+    // out-of-order transaction IDs are reported if required, with a warning or an error
+    fn id_order_1() {
+        use std::rc::Rc;
+        use std::cell::RefCell;
+        use crate::config::WarningHook;
+
+        let warnings = Rc::new(RefCell::new(Vec::new()));
+        let warnings_in_hook = Rc::clone(&warnings);
+        let hook = WarningHook::new(move |message| {
+            warnings_in_hook.borrow_mut().push(message.to_string())
+        });
+        let config = ProcessingConfig { check_id_order: true, warning_hook: Some(hook), 
+                                        ..Default::default() };
+
+        // IDs increasing for each client, even if not globally, are in order
+        let file_name = write_temp_file("id_order_1", 
+                                        "type, client, tx, amount\n\
+                                         deposit, 1, 1, 100\n\
+                                         deposit, 2, 5, 50\n\
+                                         withdrawal, 1, 2, 30\n\
+                                         dispute, 1, 1\n\
+                                         deposit, 1, 3, 10\n");
+        let mut clients_map = ClientMap::default();
+        execute_transactions_from_csv(&mut clients_map, &file_name, &config).unwrap();
+        assert!(warnings.borrow().is_empty());
+
+        // a lower ID is executed with a warning, comparing with the highest earlier one
+        let file_name = write_temp_file("id_order_2", 
+                                        "type, client, tx, amount\n\
+                                         deposit, 1, 1, 100\n\
+                                         deposit, 1, 5, 50\n\
+                                         withdrawal, 1, 3, 30\n\
+                                         deposit, 1, 4, 10\n\
+                                         deposit, 2, 2, 10\n");
+        let mut clients_map = ClientMap::default();
+        execute_transactions_from_csv(&mut clients_map, &file_name, &config).unwrap();
+        assert_eq!(vec!["Warning: Transaction 3 of client 1 has a lower ID than transaction 5; the transactions may be out of order (line 4)", 
+                        "Warning: Transaction 4 of client 1 has a lower ID than transaction 5; the transactions may be out of order (line 5)"], 
+                   *warnings.borrow());
+        assert_eq!("client, available, held, total, locked\n\
+                    1, 130, 0, 130, false\n\
+                    2, 10, 0, 10, false\n", 
+                   format!("{}", clients_map));
+
+        // in strict mode, it is an error
+        let mut config = ProcessingConfig { check_id_order: true, ..ProcessingConfig::strict() };
+        let mut clients_map = ClientMap::default();
+        let error = execute_transactions_from_csv(&mut clients_map, &file_name, &config)
+            .unwrap_err();
+        assert_eq!("Transaction 3 of client 1 has a lower ID than transaction 5; the transactions may be out of order (line 4)", 
+                   error.to_string());
+
+        // the check is disabled by default, even in strict mode
+        config.check_id_order = false;
+        let mut clients_map = ClientMap::default();
+        assert!(execute_transactions_from_csv(&mut clients_map, &file_name, &config).is_ok());
+    }
+    
+    #[test]
+    // notes after the expected fields are kept and shown for the last transaction of each client
+    fn notes_1() {
+        use crate::config::OutputConfig;
+
+        let file_name = write_temp_file("notes_1", 
+                                        "type, client, tx, amount, notes\n\
+                                         deposit, 1, 1, 100, paid by card\n\
+                                         deposit, 2, 2, 50, \n\
+                                         withdrawal, 1, 3, 30, cash, counter 2\n\
+                                         deposit, 3, 4, 10, \"first\" deposit\n\
+                                         dispute, 2, 2\n");
+        let config = ProcessingConfig { notes_column: true, 
+                                        extra_data: ExtraDataPolicy::WarnAndSkip, 
+                                        ..Default::default() };
+        let mut clients_map = ClientMap::default();
+        let report = execute_transactions_from_csv(&mut clients_map, &file_name, &config).unwrap();
+        assert_eq!(5, report.stats.applied);
+        let output_config = OutputConfig { show_notes: true, verbose: true, 
+                                           ..Default::default() };
+        assert_eq!(format!("{}, notes\n\
+                            1, 70, 0, 70, false, 0, 70, \"cash, counter 2\"\n\
+                            2, 0, 50, 50, false, 0, 0, \n\
+                            3, 10, 0, 10, false, 0, 10, \"\"\"first\"\" deposit\"\n", 
+                           VERBOSE_CSV_HEADER), 
+                   format!("{}", clients_map.display(&output_config)));
+
+        // without the option, the notes are extra data
+        let config = ProcessingConfig { extra_data: ExtraDataPolicy::WarnAndSkip, quiet: true, 
+                                        ..Default::default() };
+        let mut clients_map = ClientMap::default();
+        let report = execute_transactions_from_csv(&mut clients_map, &file_name, &config).unwrap();
+        assert_eq!(4, report.line_errors.len());
+    }
+    
+    #[test]
+    // the outcome of each data line is recorded if required, in order
+    fn line_results_1() {
+        let file_name = write_temp_file("line_results_1", 
+                                        "type, client, tx, amount\n\
+                                         deposit, 1, 1, 100\n\
+                                         deposit, 1, x, 100\n\
+                                         \n\
+                                         withdrawal, 1, 2, 30\n\
+                                         transfer, 1, 3, 10\n\
+                                         dispute, 1, 1\n\
+                                         chargeback, 1, 1\n\
+                                         deposit, 1, 4, 10\n\
+                                         withdrawal, 2, 5, 10\n");
+        let config = ProcessingConfig { record_line_results: true, quiet: true, 
+                                        ..Default::default() };
+        let mut clients_map = ClientMap::default();
+        let report = execute_transactions_from_csv(&mut clients_map, &file_name, &config).unwrap();
+        assert_eq!("2, applied\n\
+                    3, skipped: invalid transaction line encountered\n\
+                    5, applied\n\
+                    6, skipped: unknown transaction type `transfer`\n\
+                    7, applied\n\
+                    8, applied\n\
+                    9, skipped: The client account is locked (client 1)\n\
+                    10, skipped: Withdrawal of 10 exceeds the available funds of client 2 (0)\n", 
+                   report.line_results.iter().map(|result| format!("{}\n", result)).join(""));
+
+        // they are not recorded by default
+        let config = ProcessingConfig { quiet: true, ..Default::default() };
+        let mut clients_map = ClientMap::default();
+        let report = execute_transactions_from_csv(&mut clients_map, &file_name, &config).unwrap();
+        assert!(report.line_results.is_empty());
+    }
+    
+    #[test]
+    // all the accepted spellings of booleans, and some rejected ones
+    fn parse_bool_1() {
+        for field in ["true", "1", "yes", "TRUE", "Yes", " true "] {
+            assert_eq!(Some(true), parse_bool(field), "{}", field);
+        }
+        for field in ["false", "0", "no", "False", "NO", "\tno"] {
+            assert_eq!(Some(false), parse_bool(field), "{}", field);
+        }
+        for field in ["", "y", "n", "2", "-1", "locked", "ture", "yes no"] {
+            assert_eq!(None, parse_bool(field), "{}", field);
+        }
+    }
+
+    #[test]
+    // in all-or-nothing mode, the commit hook is not called while the lines are processed, and
+    // only once if they are all applied
+    fn all_or_nothing_commit_hook_1() {
+        use std::rc::Rc;
+        use std::cell::RefCell;
+        use crate::config::CommitHook;
+
+        let commits = Rc::new(RefCell::new(Vec::new()));
+        let commits_in_hook = Rc::clone(&commits);
+        let hook = CommitHook::new(1, move |clients_map| {
+            commits_in_hook.borrow_mut().push(clients_map.to_string())
+        });
+        let config = ProcessingConfig { all_or_nothing: true, quiet: true, commit_hook: Some(hook), 
+                                        ..Default::default() };
+        let mut clients_map = ClientMap::default();
+        let file_name = write_temp_file("all_or_nothing_commit_hook_1", 
+                                        "type, client, tx, amount\n\
+                                         deposit, 1, 1, 100\n\
+                                         deposit, 1, x, 50\n");
+        execute_transactions_from_csv(&mut clients_map, &file_name, &config).unwrap_err();
+        assert!(commits.borrow().is_empty());
+
+        let file_name = write_temp_file("all_or_nothing_commit_hook_2", 
+                                        "type, client, tx, amount\n\
+                                         deposit, 1, 1, 100\n\
+                                         deposit, 1, 2, 50\n");
+        execute_transactions_from_csv(&mut clients_map, &file_name, &config).unwrap();
+        assert_eq!(vec!["client, available, held, total, locked\n1, 150, 0, 150, false\n"], 
+                   *commits.borrow());
+    }
+
+    #[test]
+    // non-finite amounts are skipped as invalid lines, and deposits overflowing the funds are
+    // skipped, rather than leaving the client data inconsistent
+    fn non_finite_amounts_1() {
+        let config = ProcessingConfig { record_line_results: true, quiet: true, 
+                                        ..Default::default() };
+        let mut clients_map = ClientMap::default();
+        let report = execute_transactions_from_bytes(&mut clients_map, 
+                                                     b"type, client, tx, amount\n\
+                                                       deposit, 1, 1, NaN\n\
+                                                       deposit, 1, 2, inf\n\
+                                                       deposit, 1, 3, 1e400\n\
+                                                       deposit, 1, 4, 1e308\n\
+                                                       deposit, 1, 5, 1e308\n", 
+                                                     &config).unwrap();
+        assert_eq!("2, skipped: amount NaN is not a finite number\n\
+                    3, skipped: amount inf is not a finite number\n\
+                    4, skipped: amount 1e400 is not a finite number\n\
+                    5, applied\n\
+                    6, skipped: Deposit 5 would bring the funds beyond the largest representable amount (client 1)\n", 
+                   report.line_results.iter().map(|result| format!("{}\n", result)).join(""));
+        let client = clients_map.get(&ClientId(1)).unwrap();
+        assert_eq!(1e308, client.available());
+        assert!(client.is_consistent());
+    }
 }
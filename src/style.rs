@@ -10,3 +10,14 @@ pub fn warning_style(message: String, is_term: bool) -> String {
 
 #[cfg(any(not(feature = "atty"), feature = "no_color"))]
 pub fn warning_style(message: String, _: bool) -> String { message }
+
+
+/// check if stderr is a terminal (never the case on WebAssembly, which has no process)
+#[cfg(all(feature = "atty", not(target_arch = "wasm32")))]
+pub fn stderr_is_term() -> bool {
+    atty::is(atty::Stream::Stderr)
+}
+
+
+#[cfg(any(not(feature = "atty"), target_arch = "wasm32"))]
+pub fn stderr_is_term() -> bool { false }
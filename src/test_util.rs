@@ -0,0 +1,151 @@
+use std::collections::HashSet;
+use rand::{ Rng, SeedableRng };
+use rand_chacha::ChaCha8Rng;
+use crate::client::ClientId;
+use crate::transaction::{ Transaction, TransactionId };
+
+
+// the state of a client during the generation of transactions
+#[derive(Default)]
+struct GeneratedClient {
+    available: f64,
+    deposits: Vec<(TransactionId, f64)>,
+    disputed: Vec<(TransactionId, f64)>,
+    locked: bool,
+}
+
+
+/// generate a reproducible sequence of valid transactions
+///
+/// The same `seed` always gives the same sequence, whatever the platform. Clients have IDs from 1
+/// to `n_clients` and their accounts are opened by their first deposit; deposits and withdrawals
+/// have IDs increasing from 1, and dispute-family transactions have the default ID, as when read
+/// from a file. The sequences are valid: withdrawals never exceed the available funds, only
+/// deposits which are not under dispute are disputed, only disputed deposits are resolved or
+/// charged back, and no transaction follows a chargeback on the same account. At least one
+/// account is never locked, so that exactly `n_txns` transactions are generated.
+///
+/// # Panics
+///
+/// This function panics if `n_clients` is 0 and `n_txns` is not.
+///
+/// # Example
+///
+/// ```
+/// use banking_exercise::client::*;
+/// use banking_exercise::config::ProcessingConfig;
+/// use banking_exercise::test_util::generate_transactions;
+///
+/// let mut clients_map = ClientMap::default();
+/// for (transaction_id, client_id, transaction) in generate_transactions(42, 3, 100) {
+///     if !clients_map.contains_key(&client_id) {
+///         clients_map.insert(client_id, Client::new(0., 0., false)).unwrap();
+///     }
+///     clients_map.execute_transaction(transaction_id, client_id, transaction,
+///                                     &ProcessingConfig::default()).unwrap();
+/// }
+/// ```
+pub fn generate_transactions(seed: u64, n_clients: u16, n_txns: usize)
+    -> Vec<(TransactionId, ClientId, Transaction)>
+{
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let mut clients: Vec<GeneratedClient> =
+        (0..n_clients).map(|_| GeneratedClient::default()).collect();
+    let mut opened = HashSet::new();
+    let mut next_id = 1;
+    let mut transactions = Vec::with_capacity(n_txns);
+
+    while transactions.len() < n_txns {
+
+        // pick a client whose account is not locked
+        let index = rng.gen_range(0..clients.len());
+        if clients[index].locked { continue; }
+        let n_unlocked = clients.iter().filter(|client| !client.locked).count();
+        let client_id = ClientId(index as u16 + 1);
+        let client = &mut clients[index];
+
+        // the first transaction of each client is a deposit
+        let kind = if opened.insert(client_id) { 0 } else { rng.gen_range(0..10) };
+
+        // draw the transaction type: 0 to 3 for a deposit, 4 and 5 for a withdrawal, 6 and 7 for
+        // a dispute, 8 for a resolve, and 9 for a chargeback, falling back to a deposit if the
+        // transaction would not be valid; amounts have at most four decimal places
+        let transaction = match kind {
+            4..=5 if client.available > 0. => {
+                let amount = (rng.gen_range(0.0..=client.available) * 10_000.).floor() / 10_000.;
+                client.available -= amount;
+                Transaction::Withdrawal(amount)
+            },
+            6..=7 if !client.deposits.is_empty() => {
+                let (id, amount) = client.deposits.swap_remove(
+                    rng.gen_range(0..client.deposits.len()));
+                client.available -= amount;
+                client.disputed.push((id, amount));
+                Transaction::Dispute(id)
+            },
+            8 if !client.disputed.is_empty() => {
+                let (id, amount) = client.disputed.swap_remove(
+                    rng.gen_range(0..client.disputed.len()));
+                client.available += amount;
+                client.deposits.push((id, amount));
+                Transaction::Resolve(id)
+            },
+            9 if !client.disputed.is_empty() && n_unlocked > 1 => {
+                let (id, _) = client.disputed.swap_remove(
+                    rng.gen_range(0..client.disputed.len()));
+                client.locked = true;
+                Transaction::Chargeback(id)
+            },
+            _ => {
+                let amount = rng.gen_range(1..=10_000_000) as f64 / 10_000.;
+                client.available += amount;
+                client.deposits.push((TransactionId(next_id), amount));
+                Transaction::Deposit(amount)
+            },
+        };
+
+        // deposits and withdrawals get a new ID
+        let transaction_id = match transaction {
+            Transaction::Deposit(_) | Transaction::Withdrawal(_) => {
+                next_id += 1;
+                TransactionId(next_id - 1)
+            },
+            _ => TransactionId::default(),
+        };
+        transactions.push((transaction_id, client_id, transaction));
+    }
+    transactions
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::client::*;
+    use crate::config::ProcessingConfig;
+
+    #[test]
+    // the same seed gives the same transactions, and different seeds different ones
+    fn generate_transactions_1() {
+        let transactions = generate_transactions(7, 5, 1_000);
+        assert_eq!(1_000, transactions.len());
+        assert_eq!(transactions, generate_transactions(7, 5, 1_000));
+        assert_ne!(transactions, generate_transactions(8, 5, 1_000));
+    }
+
+    #[test]
+    // the generated transactions can all be executed, leaving the clients consistent
+    fn generate_transactions_2() {
+        let mut clients_map = ClientMap::default();
+        let config = ProcessingConfig { quiet: true, ..Default::default() };
+        for (transaction_id, client_id, transaction) in generate_transactions(1, 10, 5_000) {
+            if !clients_map.contains_key(&client_id) {
+                clients_map.insert(client_id, Client::new(0., 0., false)).unwrap();
+            }
+            clients_map.execute_transaction(transaction_id, client_id, transaction, &config)
+                .unwrap();
+        }
+        assert!(clients_map.locked_clients().len() < 10);
+    }
+}
@@ -1,22 +1,98 @@
 /// a structure storing transactions
 ///
-/// Transactions without IDs will be assigned the ID 0
-#[derive(Debug, PartialEq)]
+/// Transactions without IDs will be assigned the ID 0. `Open` and `Close` open and close the 
+/// client account.
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Transaction {
     Deposit(f64),
     Withdrawal(f64),
     Dispute(TransactionId),
     Resolve(TransactionId),
-    Chargeback(TransactionId)
+    Chargeback(TransactionId),
+    Open,
+    Close
+}
+
+
+impl Transaction {
+
+    /// the type of the transaction, as written in transaction files (`"deposit"`, `"withdrawal"`,
+    /// `"dispute"`, `"resolve"`, `"chargeback"`, `"open"`, or `"close"`)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use banking_exercise::transaction::Transaction;
+    ///
+    /// assert_eq!("deposit", Transaction::Deposit(100.).kind());
+    /// ```
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Transaction::Deposit(_) => "deposit",
+            Transaction::Withdrawal(_) => "withdrawal",
+            Transaction::Dispute(_) => "dispute",
+            Transaction::Resolve(_) => "resolve",
+            Transaction::Chargeback(_) => "chargeback",
+            Transaction::Open => "open",
+            Transaction::Close => "close",
+        }
+    }
+}
+
+
+impl core::fmt::Display for Transaction {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "{}", self.kind())?;
+        match self {
+            Transaction::Deposit(amount) | Transaction::Withdrawal(amount) => 
+                write!(f, " {}", amount),
+            Transaction::Dispute(id) | Transaction::Resolve(id) | Transaction::Chargeback(id) => 
+                write!(f, " {}", id.0),
+            Transaction::Open | Transaction::Close => Ok(()),
+        }
+    }
 }
 
 
 /// a transaction ID
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct TransactionId(pub u32);
 
-impl Default for TransactionId {
-    fn default() -> Self {
-        TransactionId(0)
+
+/// type used for the client ID
+///
+/// Clients are displayed in increasing order of their IDs, as given by the `Ord` implementation.
+#[derive(Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ClientId(pub u16);
+
+
+impl core::fmt::Display for ClientId {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    // each transaction type has its own kind, which starts its display
+    fn kind_1() {
+        for (transaction, kind) in [(Transaction::Deposit(1.), "deposit"), 
+                                    (Transaction::Withdrawal(1.), "withdrawal"), 
+                                    (Transaction::Dispute(TransactionId(1)), "dispute"), 
+                                    (Transaction::Resolve(TransactionId(1)), "resolve"), 
+                                    (Transaction::Chargeback(TransactionId(1)), "chargeback"), 
+                                    (Transaction::Open, "open"), 
+                                    (Transaction::Close, "close")] {
+            assert_eq!(kind, transaction.kind());
+            assert!(transaction.to_string().starts_with(kind));
+        }
     }
 }
@@ -0,0 +1,202 @@
+use std::process::{ Command, Output };
+
+
+// write some content to a temporary file and return its name
+fn write_temp_file(name: &str, content: &str) -> String {
+    let path = std::env::temp_dir().join(format!("banking_exercise_cli_{}.csv", name));
+    std::fs::write(&path, content).unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+
+// run the executable with some arguments
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_banking_exercise")).args(args).output().unwrap()
+}
+
+
+#[test]
+// the `parse` subcommand prints the parsed transactions and the parsing errors
+fn parse_subcommand() {
+    let file_name = write_temp_file("parse_subcommand", 
+                                    "type, client, tx, amount\n\
+                                     deposit, 1, 1, 100.5\n\
+                                     deposit, 1, x, 10\n\
+                                     withdrawal, 2, 2, 50\n\
+                                     transfer, 1, 3, 10\n\
+                                     dispute, 1, 1\n");
+    let output = run(&["parse", &file_name]);
+    assert!(output.status.success());
+    assert_eq!("line 2: client 1, transaction 1: deposit 100.5\n\
+                line 3: error: invalid transaction line encountered\n\
+                line 4: client 2, transaction 2: withdrawal 50\n\
+                line 5: error: unknown transaction type `transfer`\n\
+                line 6: client 1, transaction 0: dispute 1\n", 
+               String::from_utf8(output.stdout).unwrap());
+}
+
+
+#[test]
+// the `--timing` option prints the processing time and throughput to `stderr`
+fn timing_option() {
+    let file_name = write_temp_file("timing_option", 
+                                    "type, client, tx, amount\n\
+                                     deposit, 1, 1, 100\n\
+                                     withdrawal, 1, 2, 50\n");
+    let output = run(&[&file_name, "--timing"]);
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with("Processed 2 transactions (2 applied, 0 skipped) in "));
+    assert!(stderr.contains(" s ("));
+    assert!(stderr.trim_end().ends_with("transactions per second)"));
+
+    // the timing is not printed by default
+    let output = run(&[&file_name]);
+    assert!(output.stderr.is_empty());
+}
+
+
+#[test]
+// line numbers in warnings match the ones of text editors, including empty lines
+fn warning_line_numbers() {
+    let file_name = write_temp_file("warning_line_numbers", 
+                                    "type, client, tx, amount\n\
+                                     deposit, 1, 1, 100\n\
+                                     \n\
+                                     deposit, 1, x, 100\n");
+    let output = run(&[&file_name]);
+    assert!(output.status.success());
+    assert_eq!("invalid transaction line encountered (line 4)\n", 
+               String::from_utf8(output.stderr).unwrap());
+}
+
+
+#[test]
+// `--version` prints the version of the crate, even with other arguments
+fn version_option() {
+    for args in [vec!["--version"], vec!["transactions.csv", "--version"]] {
+        let output = run(&args);
+        assert!(output.status.success());
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.starts_with(&format!("banking_exercise {}", env!("CARGO_PKG_VERSION"))));
+    }
+}
+
+
+#[test]
+// a warning is printed when the total funds of a client go beyond 2^53
+fn large_totals_warning() {
+    let file_name = write_temp_file("large_totals_warning", 
+                                    "type, client, tx, amount\n\
+                                     deposit, 1, 1, 9007199254740000\n\
+                                     deposit, 1, 2, 900\n\
+                                     deposit, 1, 3, 100\n\
+                                     deposit, 1, 4, 100\n");
+    let output = run(&[&file_name]);
+    assert!(output.status.success());
+    assert_eq!("Warning: The total funds of client 1 (9007199254741000) are beyond 2^53 after transaction 3; integer amounts may lose precision\n", 
+               String::from_utf8(output.stderr).unwrap());
+
+    // the warning can be disabled
+    let output = run(&[&file_name, "--large-totals=ignore"]);
+    assert!(output.stderr.is_empty());
+}
+
+
+#[test]
+// with `--warnings-to-stdout`, the warnings are printed before the output on `stdout`
+fn warnings_to_stdout() {
+    let file_name = write_temp_file("warnings_to_stdout", 
+                                    "type, client, tx, amount\n\
+                                     deposit, 1, 1, 100\n\
+                                     deposit, 1, x, 100\n\
+                                     withdrawal, 1, 2, 30\n\
+                                     transfer, 1, 3, 10\n");
+    let output = run(&[&file_name, "--warnings-to-stdout"]);
+    assert!(output.status.success());
+    assert!(output.stderr.is_empty());
+    assert_eq!("invalid transaction line encountered (line 3)\n\
+                unknown transaction type `transfer` (line 5)\n\
+                client, available, held, total, locked\n\
+                1, 70, 0, 70, false\n", 
+               String::from_utf8(output.stdout).unwrap());
+}
+
+
+#[test]
+// `--line-results` writes the outcome of each data line to a file
+fn line_results_option() {
+    let file_name = write_temp_file("line_results_option", 
+                                    "type, client, tx, amount\n\
+                                     deposit, 1, 1, 100\n\
+                                     withdrawal, 1, 2, 500\n\
+                                     \n\
+                                     deposit, 1, x, 100\n\
+                                     dispute, 1, 1\n");
+    let results_file = std::env::temp_dir().join("banking_exercise_cli_line_results.txt");
+    let output = run(&[&file_name, "--quiet", 
+                       &format!("--line-results={}", results_file.to_str().unwrap())]);
+    assert!(output.status.success());
+    assert_eq!("client, available, held, total, locked\n1, 0, 100, 100, false\n", 
+               String::from_utf8(output.stdout).unwrap());
+    assert_eq!("2, applied\n\
+                3, skipped: Withdrawal of 500 exceeds the available funds of client 1 (100)\n\
+                5, skipped: invalid transaction line encountered\n\
+                6, applied\n", 
+               std::fs::read_to_string(results_file).unwrap());
+}
+
+
+#[test]
+// the `explain` subcommand prints the history of a transaction
+fn explain_subcommand() {
+    let file_name = write_temp_file("explain_subcommand", 
+                                    "type, client, tx, amount\n\
+                                     deposit, 1, 1, 100\n\
+                                     deposit, 2, 2, 50\n\
+                                     dispute, 1, 1\n\
+                                     withdrawal, 1, 3, 20\n\
+                                     dispute, 1, 1\n\
+                                     resolve, 1, 1\n\
+                                     dispute, 2, 1\n\
+                                     chargeback, 1, 1\n");
+    let output = run(&["explain", &file_name, "1"]);
+    assert!(output.status.success());
+    assert_eq!("line 2: deposit 100 by client 1: applied (available 100, held 0, total 100)\n\
+                line 4: dispute 1 by client 1: applied, the transaction is under dispute (available 0, held 100, total 100)\n\
+                line 6: dispute 1 by client 1: no effect\n\
+                line 7: resolve 1 by client 1: applied, the dispute is resolved (available 100, held 0, total 100)\n\
+                line 9: chargeback 1 by client 1: no effect\n", 
+               String::from_utf8(output.stdout).unwrap());
+
+    // unknown transactions and invalid IDs
+    let output = run(&["explain", &file_name, "7"]);
+    assert_eq!("Transaction 7 not found\n", String::from_utf8(output.stdout).unwrap());
+    let output = run(&["explain", &file_name, "x"]);
+    assert!(!output.status.success());
+    assert_eq!("ERROR: Invalid transaction ID x\n", String::from_utf8(output.stderr).unwrap());
+}
+
+
+#[test]
+// the exit code distinguishes the errors, following `sysexits.h`
+fn exit_codes() {
+    let output = run(&["banking_exercise_cli_no_such_file.csv"]);
+    assert_eq!(Some(74), output.status.code());
+    let output = run(&["transactions.csv", "--no-such-option"]);
+    assert_eq!(Some(64), output.status.code());
+    assert_eq!("ERROR: Unknown option --no-such-option\n", 
+               String::from_utf8(output.stderr).unwrap());
+}
+
+
+#[test]
+// subcommands missing their arguments print their usage and exit with `EX_USAGE`
+fn subcommand_usage() {
+    let output = run(&["parse"]);
+    assert_eq!(Some(64), output.status.code());
+    assert_eq!("ERROR: Usage: banking_exercise parse <file>\n", 
+               String::from_utf8(output.stderr).unwrap());
+    let output = run(&["explain", "transactions.csv"]);
+    assert_eq!(Some(64), output.status.code());
+}